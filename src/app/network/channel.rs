@@ -0,0 +1,273 @@
+use std::collections::VecDeque;
+
+use super::*;
+
+// Sent packets remembered to process their acks (RTT and reliable messages)
+const SENT_PACKETS_BUFFER_SIZE: usize = 256;
+
+// Reliable messages that can be in flight, for both the sender queue and the receiver buffer
+const RELIABLE_WINDOW_SIZE: usize = 256;
+
+const NET_INITIAL_RTT: Duration = Duration::from_millis(100);
+const NET_MIN_RESEND_INTERVAL: Duration = Duration::from_millis(20);
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Channel {
+    Unreliable,      // may be lost, duplicated packets are dropped
+    ReliableOrdered, // resent until acked, delivered in the order they were sent
+}
+
+// Every packet of a connection has a sequence number and the acks of the last 33 packets received,
+// so acks can be lost without resending anything
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct PacketHeader {
+    pub sequence: u16,
+    pub ack: u16,      // most recent sequence received
+    pub ack_bits: u32, // bit n is set if the sequence ack - 1 - n was received
+}
+
+impl Serialize for PacketHeader {
+    fn serialize(&self, serializer: &mut Serializer) -> Result<(), SerializationError> {
+        self.sequence.serialize(serializer)?;
+        self.ack.serialize(serializer)?;
+        self.ack_bits.serialize(serializer)?;
+        Ok(())
+    }
+}
+
+impl Deserialize for PacketHeader {
+    fn deserialize(deserializer: &mut Deserializer) -> Result<Self, SerializationError> {
+        let sequence = u16::deserialize(deserializer)?;
+        let ack = u16::deserialize(deserializer)?;
+        let ack_bits = u32::deserialize(deserializer)?;
+        Ok(PacketHeader { sequence, ack, ack_bits })
+    }
+}
+
+#[derive(Copy, Clone, Debug)]
+struct SentPacket {
+    sequence: u16,
+    timestamp: Instant,
+    reliable_id: Option<u16>,
+    acked: bool,
+}
+
+#[derive(Clone, Debug)]
+struct ReliableMessage {
+    id: u16,
+    payload: DataPayload,
+    last_sent: Option<Instant>,
+}
+
+// Sequence numbers, acks, RTT and the reliable channel state of a connection
+#[derive(Clone, Debug)]
+pub struct ConnectionChannels {
+    local_sequence: u16,
+    remote_sequence: u16, // u16::MAX acks nothing since it won't match a sent packet yet
+    ack_bits: u32,
+    sent_packets: Vec<Option<SentPacket>>,
+    rtt: Duration,
+
+    next_send_reliable_id: u16,
+    reliable_send_queue: VecDeque<ReliableMessage>,
+    next_recv_reliable_id: u16,
+    reliable_recv_buffer: VecDeque<Option<DataPayload>>, // starting at next_recv_reliable_id
+    needs_ack: bool,
+}
+
+impl ConnectionChannels {
+    pub(super) fn new() -> Self {
+        Self {
+            local_sequence: 0,
+            remote_sequence: u16::MAX,
+            ack_bits: 0,
+            sent_packets: vec![None; SENT_PACKETS_BUFFER_SIZE],
+            rtt: NET_INITIAL_RTT,
+
+            next_send_reliable_id: 0,
+            reliable_send_queue: VecDeque::new(),
+            next_recv_reliable_id: 0,
+            reliable_recv_buffer: VecDeque::new(),
+            needs_ack: false,
+        }
+    }
+
+    // Smoothed round trip time of the acked packets
+    pub fn rtt(&self) -> Duration { self.rtt }
+
+    pub fn pending_reliable_messages(&self) -> usize { self.reliable_send_queue.len() }
+
+    // A reliable message was received and no packet was sent since
+    pub(super) fn needs_ack(&self) -> bool { self.needs_ack }
+
+    // Header of a new packet, with the acks of the received ones
+    pub(super) fn next_packet_header(&mut self, reliable_id: Option<u16>) -> PacketHeader {
+        let sequence = self.local_sequence;
+        self.local_sequence = self.local_sequence.wrapping_add(1);
+
+        self.sent_packets[sequence as usize % SENT_PACKETS_BUFFER_SIZE] = Some(SentPacket {
+            sequence,
+            timestamp: Instant::now(),
+            reliable_id,
+            acked: false,
+        });
+        self.needs_ack = false;
+
+        PacketHeader {
+            sequence,
+            ack: self.remote_sequence,
+            ack_bits: self.ack_bits,
+        }
+    }
+
+    // Returns false if the packet was already received or is too old to know it
+    pub(super) fn process_header(&mut self, header: PacketHeader) -> bool {
+        self.ack_packet(header.ack);
+        for i in 0..32 {
+            if header.ack_bits & (1 << i) != 0 {
+                self.ack_packet(header.ack.wrapping_sub(i + 1));
+            }
+        }
+
+        if sequence_greater_than(header.sequence, self.remote_sequence) {
+            let shift = header.sequence.wrapping_sub(self.remote_sequence) as u32;
+            self.ack_bits = if shift > 32 { 0 } else { ((self.ack_bits as u64) << shift) as u32 };
+            if shift <= 32 { self.ack_bits |= 1 << (shift - 1); }
+            self.remote_sequence = header.sequence;
+            true
+        } else {
+            let diff = self.remote_sequence.wrapping_sub(header.sequence) as u32;
+            if diff == 0 || diff > 32 || self.ack_bits & (1 << (diff - 1)) != 0 {
+                return false;
+            }
+
+            self.ack_bits |= 1 << (diff - 1);
+            true
+        }
+    }
+
+    pub(super) fn push_reliable(&mut self, payload: DataPayload) -> Result<(), NetError> {
+        if self.reliable_send_queue.len() >= RELIABLE_WINDOW_SIZE {
+            return Err(NetError::ReliableChannelFull);
+        }
+
+        let id = self.next_send_reliable_id;
+        self.next_send_reliable_id = self.next_send_reliable_id.wrapping_add(1);
+        self.reliable_send_queue.push_back(ReliableMessage { id, payload, last_sent: None });
+        Ok(())
+    }
+
+    // Reliable messages never sent, or not acked after a while
+    pub(super) fn reliable_messages_to_send(&mut self) -> Vec<(u16, DataPayload)> {
+        let resend_interval = (self.rtt * 2).max(NET_MIN_RESEND_INTERVAL);
+        let now = Instant::now();
+
+        self.reliable_send_queue.iter_mut()
+            .filter(|message| message.last_sent.map_or(true, |t| now - t >= resend_interval))
+            .map(|message| {
+                message.last_sent = Some(now);
+                (message.id, message.payload.clone())
+            })
+            .collect()
+    }
+
+    // Returns the reliable messages that can be delivered in order
+    pub(super) fn receive_reliable(&mut self, id: u16, payload: DataPayload) -> Vec<DataPayload> {
+        // Even if it's a duplicate, the sender didn't get the ack
+        self.needs_ack = true;
+
+        // Already delivered messages wrap around to a big offset
+        let offset = id.wrapping_sub(self.next_recv_reliable_id) as usize;
+        if offset >= RELIABLE_WINDOW_SIZE { return Vec::new(); }
+
+        if self.reliable_recv_buffer.len() <= offset {
+            self.reliable_recv_buffer.resize(offset + 1, None);
+        }
+        self.reliable_recv_buffer[offset] = Some(payload);
+
+        let mut messages = Vec::new();
+        while let Some(Some(_)) = self.reliable_recv_buffer.front() {
+            messages.push(self.reliable_recv_buffer.pop_front().unwrap().unwrap());
+            self.next_recv_reliable_id = self.next_recv_reliable_id.wrapping_add(1);
+        }
+        messages
+    }
+
+    fn ack_packet(&mut self, sequence: u16) {
+        let sent_packet = match &mut self.sent_packets[sequence as usize % SENT_PACKETS_BUFFER_SIZE] {
+            Some(sent_packet) if sent_packet.sequence == sequence && !sent_packet.acked => sent_packet,
+            _ => return,
+        };
+
+        sent_packet.acked = true;
+        self.rtt = self.rtt.mul_f32(0.9) + sent_packet.timestamp.elapsed().mul_f32(0.1);
+
+        if let Some(id) = sent_packet.reliable_id {
+            self.reliable_send_queue.retain(|message| message.id != id);
+        }
+    }
+}
+
+// Sequence comparison that handles the wrap around
+fn sequence_greater_than(s1: u16, s2: u16) -> bool {
+    ((s1 > s2) && (s1 - s2 <= 32768)) || ((s1 < s2) && (s2 - s1 > 32768))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn payload(byte: u8) -> DataPayload {
+        Data::build_payload(byte).unwrap()
+    }
+
+    #[test]
+    fn test_acks() {
+        let mut sender = ConnectionChannels::new();
+        let mut receiver = ConnectionChannels::new();
+
+        let headers: Vec<PacketHeader> = (0..4).map(|_| sender.next_packet_header(None)).collect();
+
+        // Out of order and duplicated packets
+        assert!(receiver.process_header(headers[0]));
+        assert!(receiver.process_header(headers[2]));
+        assert!(receiver.process_header(headers[1]));
+        assert!(!receiver.process_header(headers[2]));
+
+        let ack_header = receiver.next_packet_header(None);
+        assert_eq!(ack_header.ack, 2);
+        assert_eq!(ack_header.ack_bits & 0b11, 0b11);
+
+        assert!(sender.process_header(ack_header));
+        assert!(sender.sent_packets.iter().flatten().filter(|p| p.acked).count() == 3);
+
+        assert!(sequence_greater_than(0, u16::MAX));
+        assert!(!sequence_greater_than(u16::MAX, 0));
+    }
+
+    #[test]
+    fn test_reliable_ordered() {
+        let mut sender = ConnectionChannels::new();
+        let mut receiver = ConnectionChannels::new();
+
+        for i in 0..3 { sender.push_reliable(payload(i)).unwrap(); }
+        let messages = sender.reliable_messages_to_send();
+        assert_eq!(messages.len(), 3);
+        assert!(sender.reliable_messages_to_send().is_empty());
+
+        // Delivered in order, once
+        assert!(receiver.receive_reliable(messages[1].0, messages[1].1.clone()).is_empty());
+        let delivered = receiver.receive_reliable(messages[0].0, messages[0].1.clone());
+        assert_eq!(delivered.len(), 2);
+        assert_eq!(u8::parse(delivered[0].data()).unwrap(), 0);
+        assert_eq!(u8::parse(delivered[1].data()).unwrap(), 1);
+        assert!(receiver.receive_reliable(messages[0].0, messages[0].1.clone()).is_empty());
+        assert!(receiver.needs_ack());
+
+        // Acked messages are not resent
+        let header = sender.next_packet_header(Some(messages[0].0));
+        receiver.process_header(header);
+        sender.process_header(receiver.next_packet_header(None));
+        assert_eq!(sender.pending_reliable_messages(), 2);
+    }
+}
@@ -1,3 +1,4 @@
+use std::collections::VecDeque;
 use std::io;
 use std::net::{SocketAddr, UdpSocket, ToSocketAddrs, Ipv4Addr};
 
@@ -28,18 +29,20 @@ pub enum ClientEvent {
     DisconnectedByServer,
     SentHeartbeat,
     SentConnectionRetry,
-    Data(DataPayload),
+    Data(Channel, DataPayload),
 }
 
 #[derive(Clone, Debug)]
 pub struct ServerConnection {
     server_addr: SocketAddr,
     heartbeat: ConnectionHeartbeat,
+    channels: ConnectionChannels,
 }
 
 impl ServerConnection {
     pub fn server_addr(&self) -> SocketAddr { self.server_addr }
     pub fn heartbeat(&self) -> &ConnectionHeartbeat { &self.heartbeat }
+    pub fn channels(&self) -> &ConnectionChannels   { &self.channels }
 }
 
 #[derive(Copy, Clone, Debug)]
@@ -61,6 +64,7 @@ pub struct Client {
     addr: SocketAddr,
     state: ClientState,
     socket: UdpSocket,
+    events: VecDeque<ClientEvent>, // reliable messages received after a missing one
 }
 
 // Fields public interface
@@ -77,6 +81,7 @@ impl Client {
                     socket: s,
                     id,
                     addr,
+                    events: VecDeque::new(),
                 })
             }
 
@@ -96,6 +101,10 @@ impl Client {
             return Err(ClientError::ClientNotConnected.into());
         }
 
+        if let Some(event) = self.events.pop_front() {
+            return Ok(Some(event));
+        }
+
         let mut buffer = [0; NET_MAX_PACKET_SIZE];
         match self.socket.recv_from(&mut buffer) {
             Ok((len, addr)) => {
@@ -152,11 +161,14 @@ impl Client {
                     return Ok(Some(ClientEvent::ServerTimedOut));
                 }
 
-                if conn.heartbeat.should_retry_send(NET_RETRY_INTERVAL) {
+                Self::send_reliable_messages(&self.socket, conn)?;
+
+                if conn.heartbeat.should_retry_send(NET_RETRY_INTERVAL) || conn.channels.needs_ack() {
                     // @TODO logging
                     //println!("[net][client] sending heartbeat!");
 
-                    let message = Heartbeat::build_message();
+                    let header = conn.channels.next_packet_header(None);
+                    let message = Heartbeat::build_message(header);
                     Self::send_message(message, &self.socket, conn)?;
                     return Ok(Some(ClientEvent::SentHeartbeat));
                 }
@@ -181,6 +193,7 @@ impl Client {
         let mut conn = ServerConnection {
             server_addr,
             heartbeat: ConnectionHeartbeat::new(),
+            channels: ConnectionChannels::new(),
         };
 
         let message = ConnectionRequest::build_message(self.id);
@@ -204,14 +217,25 @@ impl Client {
         Ok(())
     }
 
-    pub fn send<S: Serialize>(&mut self, data: S) -> Result<(), NetError> {
+    pub fn send<S: Serialize>(&mut self, channel: Channel, data: S) -> Result<(), NetError> {
         let conn = match &mut self.state {
             ClientState::Connected(conn) => conn,
             _ => return Err(ClientError::ClientNotConnected.into()),
         };
 
-        let message = Data::build_message(data)?;
-        Self::send_message(message, &self.socket, conn)
+        let data_payload = Data::build_payload(data)?;
+        match channel {
+            Channel::Unreliable => {
+                let header = conn.channels.next_packet_header(None);
+                let message = Data::build_message(header, None, data_payload);
+                Self::send_message(message, &self.socket, conn)
+            }
+
+            Channel::ReliableOrdered => {
+                conn.channels.push_reliable(data_payload)?;
+                Self::send_reliable_messages(&self.socket, conn)
+            }
+        }
     }
 }
 
@@ -292,7 +316,12 @@ impl Client {
         message: Message,
     ) -> Result<Option<ClientEvent>, NetError> {
         match message.payload {
-            MessagePayload::Heartbeat => Ok(Some(ClientEvent::ServerHeartbeat)),
+            MessagePayload::Heartbeat(header) => {
+                if let ClientState::Connected(conn) = &mut self.state {
+                    conn.channels.process_header(header);
+                }
+                Ok(Some(ClientEvent::ServerHeartbeat))
+            }
 
             MessagePayload::DisconnectNotice(id) if id == self.id => {
                 // @TODO logging
@@ -302,7 +331,27 @@ impl Client {
                 Ok(Some(ClientEvent::DisconnectedByServer))
             }
 
-            MessagePayload::Data(data_payload) => Ok(Some(ClientEvent::Data(data_payload))),
+            MessagePayload::Data(header, reliable_id, data_payload) => {
+                let conn = match &mut self.state {
+                    ClientState::Connected(conn) => conn,
+                    _ => unreachable!(),
+                };
+
+                if !conn.channels.process_header(header) {
+                    return Ok(None);
+                }
+
+                match reliable_id {
+                    None => Ok(Some(ClientEvent::Data(Channel::Unreliable, data_payload))),
+                    Some(id) => {
+                        let messages = conn.channels.receive_reliable(id, data_payload);
+                        self.events.extend(messages.into_iter().map(|data_payload| {
+                            ClientEvent::Data(Channel::ReliableOrdered, data_payload)
+                        }));
+                        Ok(self.events.pop_front())
+                    }
+                }
+            }
 
             _ => {
                 // @TODO logging
@@ -312,6 +361,20 @@ impl Client {
         }
     }
 
+    // Sends the new reliable messages and resends the ones not acked in time
+    fn send_reliable_messages(
+        socket: &UdpSocket,
+        connection: &mut ServerConnection,
+    ) -> Result<(), NetError> {
+        for (id, data_payload) in connection.channels.reliable_messages_to_send() {
+            let header = connection.channels.next_packet_header(Some(id));
+            let message = Data::build_message(header, Some(id), data_payload);
+            Self::send_message(message, socket, connection)?;
+        }
+
+        Ok(())
+    }
+
     fn send_message(
         message: Message,
         socket: &UdpSocket,
@@ -1,6 +1,7 @@
 use super::*;
 
 pub const PROTOCOL_ID: u32 = 0x2e413454;
+pub const PROTOCOL_VERSION: u8 = 2;
 
 #[derive(Clone, Debug)]
 pub struct DataPayload {
@@ -47,7 +48,7 @@ type ChallengeData = [u8; NET_CHALLENGE_SIZE];
 
 #[derive(Clone, Debug)]
 pub enum MessagePayload {
-    Heartbeat,
+    Heartbeat(PacketHeader), // also used to send the acks when there's no data to send
 
     ConnectionRequest(ClientId),
     ConnectionAccept,
@@ -57,7 +58,7 @@ pub enum MessagePayload {
     ChallengeRequest(ChallengeData),
     ChallengeResponse(ClientId, ChallengeData),
 
-    Data(DataPayload),
+    Data(PacketHeader, Option<u16>, DataPayload), // reliable message id, for the reliable channel
 }
 
 #[derive(Copy, Clone, PartialEq, Debug)]
@@ -68,11 +69,11 @@ pub enum ConnectionRejectReason {
 
 pub struct Heartbeat;
 impl Heartbeat {
-    pub fn build_message() -> Message {
+    pub fn build_message(header: PacketHeader) -> Message {
         Message {
             protocol: PROTOCOL_ID,
-            version: 1,
-            payload: MessagePayload::Heartbeat,
+            version: PROTOCOL_VERSION,
+            payload: MessagePayload::Heartbeat(header),
         }
     }
 }
@@ -82,7 +83,7 @@ impl ConnectionRequest {
     pub fn build_message(client_id: ClientId) -> Message {
         Message {
             protocol: PROTOCOL_ID,
-            version: 1,
+            version: PROTOCOL_VERSION,
             payload: MessagePayload::ConnectionRequest(client_id),
         }
     }
@@ -93,7 +94,7 @@ impl ConnectionAccept {
     pub fn build_message() -> Message {
         Message {
             protocol: PROTOCOL_ID,
-            version: 1,
+            version: PROTOCOL_VERSION,
             payload: MessagePayload::ConnectionAccept,
         }
     }
@@ -104,7 +105,7 @@ impl ConnectionReject {
     pub fn build_message(reason: ConnectionRejectReason) -> Message {
         Message {
             protocol: PROTOCOL_ID,
-            version: 1,
+            version: PROTOCOL_VERSION,
             payload: MessagePayload::ConnectionReject(reason),
         }
     }
@@ -115,7 +116,7 @@ impl DisconnectNotice {
     pub fn build_message(client_id: ClientId) -> Message {
         Message {
             protocol: PROTOCOL_ID,
-            version: 1,
+            version: PROTOCOL_VERSION,
             payload: MessagePayload::DisconnectNotice(client_id),
         }
     }
@@ -126,7 +127,7 @@ impl ChallengeRequest {
     pub fn build_message(challenge_data: ChallengeData) -> Message {
         Message {
             protocol: PROTOCOL_ID,
-            version: 1,
+            version: PROTOCOL_VERSION,
             payload: MessagePayload::ChallengeRequest(challenge_data),
         }
     }
@@ -137,7 +138,7 @@ impl ChallengeResponse {
     pub fn build_message(client_id: ClientId, challenge_data: ChallengeData) -> Message {
         Message {
             protocol: PROTOCOL_ID,
-            version: 1,
+            version: PROTOCOL_VERSION,
             payload: MessagePayload::ChallengeResponse(client_id, challenge_data),
         }
     }
@@ -145,7 +146,19 @@ impl ChallengeResponse {
 
 pub struct Data;
 impl Data {
-    pub fn build_message<S: Serialize>(data_payload: S) -> Result<Message, SerializationError> {
+    pub fn build_message(
+        header: PacketHeader,
+        reliable_id: Option<u16>,
+        data_payload: DataPayload
+    ) -> Message {
+        Message {
+            protocol: PROTOCOL_ID,
+            version: PROTOCOL_VERSION,
+            payload: MessagePayload::Data(header, reliable_id, data_payload),
+        }
+    }
+
+    pub fn build_payload<S: Serialize>(data_payload: S) -> Result<DataPayload, SerializationError> {
         let mut data = [0u32; NET_MAX_PAYLOAD_SIZE / 4];
 
         let mut serializer = Serializer::new(&mut data);
@@ -164,28 +177,13 @@ impl Data {
             >(data)
         };
 
-        Ok(Message {
-            protocol: PROTOCOL_ID,
-            version: 1,
-            payload: MessagePayload::Data(
-                DataPayload {
-                    byte_count,
-                    data,
-                }
-            ),
-        })
+        Ok(DataPayload { byte_count, data })
     }
 
-    pub fn build_message_raw(data: [u8; NET_MAX_PAYLOAD_SIZE], byte_count: usize) -> Message {
-        Message {
-            protocol: PROTOCOL_ID,
-            version: 1,
-            payload: MessagePayload::Data(
-                DataPayload {
-                    byte_count: byte_count as u32,
-                    data,
-                }
-            ),
+    pub fn build_payload_raw(data: [u8; NET_MAX_PAYLOAD_SIZE], byte_count: usize) -> DataPayload {
+        DataPayload {
+            byte_count: byte_count as u32,
+            data,
         }
     }
 }
@@ -203,7 +201,11 @@ impl Serialize for Message {
         self.version.serialize(serializer)?;
 
         match self.payload {
-            MessagePayload::Heartbeat => serializer.serialize_u8(0x0),
+            MessagePayload::Heartbeat(header) => {
+                serializer.serialize_u8(0x0)?;
+                header.serialize(serializer)?;
+                Ok(())
+            },
 
             MessagePayload::ConnectionRequest(client_id) => {
                 serializer.serialize_u8(0x1)?;
@@ -239,8 +241,10 @@ impl Serialize for Message {
                 Ok(())
             },
 
-            MessagePayload::Data(ref data_payload) => {
+            MessagePayload::Data(header, reliable_id, ref data_payload) => {
                 serializer.serialize_u8(0x0a)?;
+                header.serialize(serializer)?;
+                reliable_id.serialize(serializer)?;
                 data_payload.serialize(serializer)?;
                 Ok(())
             },
@@ -256,10 +260,13 @@ impl Deserialize for Message {
         if protocol != PROTOCOL_ID { return Err(SerializationError::InvalidProtocol); }
 
         let version = u8::deserialize(deserializer)?;
-        if version != 1 { return Err(SerializationError::InvalidVersion); }
+        if version != PROTOCOL_VERSION { return Err(SerializationError::InvalidVersion); }
 
         let payload = match deserializer.deserialize_u8()? {
-            0x0 => MessagePayload::Heartbeat,
+            0x0 => {
+                let header = PacketHeader::deserialize(deserializer)?;
+                MessagePayload::Heartbeat(header)
+            },
 
             0x1 => {
                 let client_id = ClientId::deserialize(deserializer)?;
@@ -291,8 +298,10 @@ impl Deserialize for Message {
             }
 
             0x0a => {
+                let header = PacketHeader::deserialize(deserializer)?;
+                let reliable_id = Option::<u16>::deserialize(deserializer)?;
                 let data_payload = DataPayload::deserialize(deserializer)?;
-                MessagePayload::Data(data_payload)
+                MessagePayload::Data(header, reliable_id, data_payload)
             }
 
             _ => return Err(SerializationError::ValueOutOfRange),
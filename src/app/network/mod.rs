@@ -1,8 +1,10 @@
+mod channel;
 mod client;
 mod messages;
 mod serialization;
 mod server;
 
+pub use channel::*;
 pub use client::*;
 pub use messages::*;
 pub use serialization::*;
@@ -17,7 +19,7 @@ use crate::app::ImDraw;
 pub use serialization::*;
 
 const NET_MAX_PACKET_SIZE : usize = 512; // @XXX this should be 256, but it's bigger since we didn't implement fragmentation
-const NET_MAX_PAYLOAD_SIZE: usize = NET_MAX_PACKET_SIZE - 8; // This should always be divisible by 4
+const NET_MAX_PAYLOAD_SIZE: usize = NET_MAX_PACKET_SIZE - 20; // This should always be divisible by 4
 const NET_CHALLENGE_SIZE  : usize = 16;
 
 const NET_RETRY_INTERVAL  : Duration = Duration::from_millis(100);
@@ -33,6 +35,7 @@ pub enum NetError {
     SerializationError(SerializationError),
     ServerError(ServerError),
     ClientError(ClientError),
+    ReliableChannelFull,
 }
 
 impl fmt::Display for NetError {
@@ -230,11 +233,11 @@ mod tests {
 
         // Send data
         let send_data = MyData { i: -42, u: 42 };
-        client.send(send_data)?;
+        client.send(Channel::Unreliable, send_data)?;
 
         let server_event = server_next_event(&mut server)?;
         match server_event {
-            ServerEvent::Data(id, data_payload) => {
+            ServerEvent::Data(id, _, data_payload) => {
                 println!("data_payload: {:?}", data_payload);
                 assert_eq!(id, 1);
 
@@ -246,11 +249,12 @@ mod tests {
         }
 
         let send_data = MyData { i: -43, u: 43 };
-        server.send(1, send_data)?;
+        server.send(1, Channel::ReliableOrdered, send_data)?;
 
         let client_event = client_next_event(&mut client)?;
         match client_event {
-            ClientEvent::Data(data_payload) => {
+            ClientEvent::Data(channel, data_payload) => {
+                assert_eq!(channel, Channel::ReliableOrdered);
                 println!("data_payload: {:?}", data_payload);
                 let recv_data = MyData::parse(data_payload.data())?;
                 assert_eq!(recv_data.i, send_data.i);
@@ -1,3 +1,4 @@
+use std::collections::VecDeque;
 use std::io;
 use std::net::{SocketAddr, UdpSocket, ToSocketAddrs};
 //use byteorder::{LittleEndian, BigEndian, ReadBytesExt, WriteBytesExt};
@@ -24,7 +25,7 @@ pub enum ServerEvent {
     ClientDisconnect(ClientId),
     ClientTimeOut(ClientId),
     SentHeartbeat(ClientId),
-    Data(ClientId, DataPayload),
+    Data(ClientId, Channel, DataPayload),
 }
 
 #[derive(Debug)]
@@ -32,12 +33,14 @@ pub struct ClientConnection {
     client_id: ClientId,
     client_addr: SocketAddr,
     heartbeat: ConnectionHeartbeat,
+    channels: ConnectionChannels,
 }
 
 impl ClientConnection {
     pub fn client_id(&self) -> ClientId { self.client_id }
     pub fn addr(&self) -> SocketAddr    { self.client_addr }
     pub fn heartbeat(&self) -> &ConnectionHeartbeat { &self.heartbeat }
+    pub fn channels(&self) -> &ConnectionChannels   { &self.channels }
 }
 
 /*
@@ -54,6 +57,7 @@ pub struct Server {
     listen_addr: SocketAddr,
     connections: ConnectionList,
     client_event_index: usize,
+    events: VecDeque<ServerEvent>, // reliable messages received after a missing one
 }
 
 // Fields public interface
@@ -75,6 +79,7 @@ impl Server {
                     listen_addr:   bind_addr,
                     connections:   Vec::new(),
                     client_event_index: 0,
+                    events: VecDeque::new(),
                 })
             }
 
@@ -91,6 +96,10 @@ impl Server {
     pub fn next_event(
         &mut self,
     ) -> Result<Option<ServerEvent>, NetError> {
+        if let Some(event) = self.events.pop_front() {
+            return Ok(Some(event));
+        }
+
         let mut buffer = [0; NET_MAX_PACKET_SIZE];
         match self.socket.recv_from(&mut buffer) {
             Ok((len, addr)) => {
@@ -111,6 +120,7 @@ impl Server {
             }
 
             let mut conn = &mut self.connections[self.client_event_index];
+            self.client_event_index += 1;
 
             if conn.heartbeat.has_timed_out(NET_TIMEOUT_INTERVAL) {
                 // @TODO logging
@@ -122,11 +132,14 @@ impl Server {
                 return Ok(Some(ServerEvent::ClientTimeOut(client_id)));
             }
 
-            if conn.heartbeat.should_retry_send(NET_RETRY_INTERVAL) {
+            Self::send_reliable_messages(&self.socket, &mut conn)?;
+
+            if conn.heartbeat.should_retry_send(NET_RETRY_INTERVAL) || conn.channels.needs_ack() {
                 // @TODO logging
                 //println!("[net][server] sending heartbeat!");
 
-                let message = Heartbeat::build_message();
+                let header = conn.channels.next_packet_header(None);
+                let message = Heartbeat::build_message(header);
                 Self::send_message(message, &self.socket, &mut conn)?;
 
                 return Ok(Some(ServerEvent::SentHeartbeat(conn.client_id)));
@@ -149,7 +162,7 @@ impl Server {
         // Maintain order of connections. In case we want too many connections, we
         // should improve this somehow
         self.connections.remove(index);
-        if index > self.client_event_index { self.client_event_index -= 1; }
+        if index < self.client_event_index { self.client_event_index -= 1; }
 
         Ok(())
     }
@@ -168,22 +181,27 @@ impl Server {
         disconnects_result
     }
 
-    pub fn send<S: Serialize>(&mut self, client_id: ClientId, data: S) -> Result<(), NetError> {
+    pub fn send<S: Serialize>(
+        &mut self,
+        client_id: ClientId,
+        channel: Channel,
+        data: S
+    ) -> Result<(), NetError> {
         let conn = self.connections
             .iter_mut()
             .find(|conns| conns.client_id == client_id)
             .ok_or(NetError::from(ServerError::ClientNotConnected))?;
 
-        let message = Data::build_message(data)?;
-        Self::send_message(message, &self.socket, conn)
+        let data_payload = Data::build_payload(data)?;
+        Self::send_data(data_payload, channel, &self.socket, conn)
     }
 
-    pub fn broadcast<S: Serialize>(&mut self, data: S) -> Result<(), NetError> {
-        let message = Data::build_message(data)?;
+    pub fn broadcast<S: Serialize>(&mut self, channel: Channel, data: S) -> Result<(), NetError> {
+        let data_payload = Data::build_payload(data)?;
 
         let mut result = Ok(());
         for conn in self.connections.iter_mut() {
-            match Self::send_message(message.clone(), &self.socket, conn) {
+            match Self::send_data(data_payload.clone(), channel, &self.socket, conn) {
                 Ok(_) => {},
                 Err(e) => result = Err(e),
             }
@@ -254,18 +272,35 @@ impl Server {
                 }
             }
 
-            MessagePayload::Heartbeat => {
+            MessagePayload::Heartbeat(header) => {
                 let conn = Self::get_connection(&mut self.connections, addr)
                     .ok_or(NetError::from(ServerError::PacketNotFromConnectedClient))?;
 
                 conn.heartbeat.update_recv();
+                conn.channels.process_header(header);
                 Ok(Some(ServerEvent::ClientHeartbeat(conn.client_id)))
             }
 
-            MessagePayload::Data(data_payload) => {
+            MessagePayload::Data(header, reliable_id, data_payload) => {
                 let conn = Self::get_connection(&mut self.connections, addr)
                     .ok_or(NetError::from(ServerError::PacketNotFromConnectedClient))?;
-                Ok(Some(ServerEvent::Data(conn.client_id, data_payload)))
+
+                conn.heartbeat.update_recv();
+                if !conn.channels.process_header(header) {
+                    return Ok(None);
+                }
+
+                let client_id = conn.client_id;
+                match reliable_id {
+                    None => Ok(Some(ServerEvent::Data(client_id, Channel::Unreliable, data_payload))),
+                    Some(id) => {
+                        let messages = conn.channels.receive_reliable(id, data_payload);
+                        self.events.extend(messages.into_iter().map(|data_payload| {
+                            ServerEvent::Data(client_id, Channel::ReliableOrdered, data_payload)
+                        }));
+                        Ok(self.events.pop_front())
+                    }
+                }
             }
 
             _ => {
@@ -295,12 +330,47 @@ impl Server {
             client_id,
             client_addr: *addr,
             heartbeat: ConnectionHeartbeat::new(),
+            channels: ConnectionChannels::new(),
         };
 
         connections.push(connection);
         connections.last_mut().unwrap()
     }
 
+    fn send_data(
+        data_payload: DataPayload,
+        channel: Channel,
+        socket: &UdpSocket,
+        connection: &mut ClientConnection,
+    ) -> Result<(), NetError> {
+        match channel {
+            Channel::Unreliable => {
+                let header = connection.channels.next_packet_header(None);
+                let message = Data::build_message(header, None, data_payload);
+                Self::send_message(message, socket, connection)
+            }
+
+            Channel::ReliableOrdered => {
+                connection.channels.push_reliable(data_payload)?;
+                Self::send_reliable_messages(socket, connection)
+            }
+        }
+    }
+
+    // Sends the new reliable messages and resends the ones not acked in time
+    fn send_reliable_messages(
+        socket: &UdpSocket,
+        connection: &mut ClientConnection,
+    ) -> Result<(), NetError> {
+        for (id, data_payload) in connection.channels.reliable_messages_to_send() {
+            let header = connection.channels.next_packet_header(Some(id));
+            let message = Data::build_message(header, Some(id), data_payload);
+            Self::send_message(message, socket, connection)?;
+        }
+
+        Ok(())
+    }
+
     fn send_message(
         message: Message,
         socket: &UdpSocket,
@@ -29,6 +29,7 @@ impl Color {
 #[allow(dead_code)] pub static GREEN:       Color = Color { r: 0., g: 1., b: 0., a: 1. };
 #[allow(dead_code)] pub static BLUE:        Color = Color { r: 0., g: 0., b: 1., a: 1. };
 #[allow(dead_code)] pub static MAGENTA:     Color = Color { r: 1., g: 0., b: 1., a: 1. };
+#[allow(dead_code)] pub static GRAY:        Color = Color { r: 0.5, g: 0.5, b: 0.5, a: 1. };
 #[allow(dead_code)] pub static TRANSPARENT: Color = Color { r: 0., g: 0., b: 0., a: 0. };
 
 impl From<[f32; 4]> for Color {
@@ -1,14 +1,20 @@
-use crate::app::input::{
-    ControllerAxisThreshold,
-    mapping::RegularInputMapping,
-    button::RemappableButton,
+use crate::app::{
+    App,
+    ImDraw,
+    input::{
+        ControllerAxisThreshold,
+        mapping::{InputMapping, RegularInputMapping},
+        button::{Button, RemappableButton},
+    },
 };
+use crate::game::rules::{Rules, rotation::RotationDirection};
 
 // @TODO Tetris keys + UI keys
 pub const KEY_LEFT       : &str = "left";
 pub const KEY_RIGHT      : &str = "right";
 pub const KEY_ROTATE_CW  : &str = "rotate_cw";
 pub const KEY_ROTATE_CCW : &str = "rotate_ccw";
+pub const KEY_ROTATE_180 : &str = "rotate_180";
 pub const KEY_HOLD       : &str = "hold";
 pub const KEY_SOFT_DROP  : &str = "soft_drop";
 pub const KEY_HARD_DROP  : &str = "hard_drop";
@@ -81,6 +87,14 @@ pub fn get_default_input_mapping() -> RegularInputMapping {
         input_mapping.add_button_mapping(KEY_ROTATE_CW.to_string(), button);
     }
 
+    {
+        let mut button = RemappableButton::new();
+        button.add_key(sdl2::keyboard::Scancode::A);
+        button.add_controller_button(0, sdl2::controller::Button::Y);
+
+        input_mapping.add_button_mapping(KEY_ROTATE_180.to_string(), button);
+    }
+
     {
         let mut button = RemappableButton::new();
         button.add_key(sdl2::keyboard::Scancode::C);
@@ -116,3 +130,119 @@ pub fn get_default_input_mapping() -> RegularInputMapping {
 
     input_mapping
 }
+
+// Tetris actions of a single update, already processed from the input mapping (DAS, soft drop
+// interval, etc). This decouples the game update from the input system, so it can be fed by
+// other sources (tests, replays, network)
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, ImDraw)]
+pub struct TetrisInput {
+    pub horizontal_movement: i8, // -1: left, 1: right
+    pub rotation: Option<RotationDirection>,
+    pub soft_drop: bool,
+    pub hard_drop: bool,
+    pub hold: bool,
+
+    // Buttons being held, used for the initial actions (IRS/IHS) when the piece spawns
+    pub rotation_held: Option<RotationDirection>,
+    pub hold_held: bool,
+}
+
+impl TetrisInput {
+    pub fn from_input_mapping<M: InputMapping>(
+        input_mapping: &M,
+        rules: &Rules,
+        level: u32,
+        app: &App, // @Remove only used to check input repeated over time
+    ) -> Self {
+        let mut input = Self::default();
+
+        let das_repeat_delay = rules.get_das_repeat_delay(level);
+        let das_repeat_interval = rules.get_das_repeat_interval(level);
+
+        let left_button = input_mapping.button(KEY_LEFT.to_string());
+        if left_button.pressed_repeat_with_delay(
+            das_repeat_delay,
+            das_repeat_interval,
+            app
+        ) {
+            input.horizontal_movement -= 1;
+        }
+
+        let right_button = input_mapping.button(KEY_RIGHT.to_string());
+        if right_button.pressed_repeat_with_delay(
+            das_repeat_delay,
+            das_repeat_interval,
+            app
+        ) {
+            input.horizontal_movement += 1;
+        }
+
+        let down_button = input_mapping.button(KEY_SOFT_DROP.to_string());
+        input.soft_drop = down_button.pressed_repeat(rules.soft_drop_interval, app);
+
+        // Clockwise and counterclockwise cancel each other, 180 has priority over both
+        let mut rotation = 0;
+        let mut rotation_held = 0;
+
+        let ccw_button = input_mapping.button(KEY_ROTATE_CCW.to_string());
+        if ccw_button.pressed() { rotation -= 1; }
+        if ccw_button.down()    { rotation_held -= 1; }
+
+        let cw_button = input_mapping.button(KEY_ROTATE_CW.to_string());
+        if cw_button.pressed() { rotation += 1; }
+        if cw_button.down()    { rotation_held += 1; }
+
+        let half_button = input_mapping.button(KEY_ROTATE_180.to_string());
+        if half_button.pressed() { rotation = 2; }
+        if half_button.down()    { rotation_held = 2; }
+
+        input.rotation = rotation_direction(rotation);
+        input.rotation_held = rotation_direction(rotation_held);
+
+        // @TODO DAS
+        input.hard_drop = input_mapping.button(KEY_HARD_DROP.to_string()).pressed();
+
+        let hold_button = input_mapping.button(KEY_HOLD.to_string());
+        input.hold = hold_button.pressed();
+        input.hold_held = hold_button.down();
+
+        input
+    }
+
+    // Inputs of several updates in one, for updates that don't simulate a tick. The actions are
+    // kept until a tick uses them, the held buttons are the latest
+    pub fn merge(&self, next: &TetrisInput) -> Self {
+        Self {
+            horizontal_movement: if next.horizontal_movement != 0 {
+                next.horizontal_movement
+            } else {
+                self.horizontal_movement
+            },
+            rotation: next.rotation.or(self.rotation),
+            soft_drop: self.soft_drop || next.soft_drop,
+            hard_drop: self.hard_drop || next.hard_drop,
+            hold: self.hold || next.hold,
+
+            rotation_held: next.rotation_held,
+            hold_held: next.hold_held,
+        }
+    }
+
+    // Only the held buttons, for the ticks after the one that used the actions
+    pub fn held(&self) -> Self {
+        Self {
+            rotation_held: self.rotation_held,
+            hold_held: self.hold_held,
+            ..Self::default()
+        }
+    }
+}
+
+fn rotation_direction(rotation: i8) -> Option<RotationDirection> {
+    match rotation {
+        -1 => Some(RotationDirection::Counterclockwise),
+         1 => Some(RotationDirection::Clockwise),
+         2 => Some(RotationDirection::Half),
+         _ => None,
+    }
+}
@@ -0,0 +1,83 @@
+use crate::app::*;
+
+// Host bookkeeping of a battle: who is still playing, who receives each attack and who won
+#[derive(Debug, ImDraw)]
+pub struct Battle {
+    players: Vec<BattlePlayer>,
+    target_index: usize,
+}
+
+#[derive(Copy, Clone, Debug, ImDraw)]
+pub struct BattlePlayer {
+    pub id: ClientId,
+    pub has_topped_out: bool,
+}
+
+impl Battle {
+    pub fn new(player_ids: &[ClientId]) -> Self {
+        Self {
+            players: player_ids.iter()
+                .map(|&id| BattlePlayer { id, has_topped_out: false })
+                .collect(),
+            target_index: 0,
+        }
+    }
+
+    pub fn players(&self) -> &[BattlePlayer] { &self.players }
+
+    // The opponents still playing take turns receiving the attacks (even targeting)
+    pub fn next_target(&mut self, attacker_id: ClientId) -> Option<ClientId> {
+        for _ in 0..self.players.len() {
+            self.target_index = (self.target_index + 1) % self.players.len();
+
+            let player = self.players[self.target_index];
+            if player.id != attacker_id && !player.has_topped_out {
+                return Some(player.id);
+            }
+        }
+
+        None
+    }
+
+    // Returns the winner if the battle ended with this top out
+    pub fn top_out(&mut self, player_id: ClientId) -> Option<ClientId> {
+        match self.players.iter_mut().find(|player| player.id == player_id) {
+            Some(player) if !player.has_topped_out => player.has_topped_out = true,
+            _ => return None,
+        }
+
+        self.winner()
+    }
+
+    // The last player standing
+    pub fn winner(&self) -> Option<ClientId> {
+        let mut alive_players = self.players.iter().filter(|player| !player.has_topped_out);
+        match (alive_players.next(), alive_players.next()) {
+            (Some(player), None) if self.players.len() > 1 => Some(player.id),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_battle() {
+        let mut battle = Battle::new(&[0, 1, 2]);
+
+        // Attacks alternate between the opponents
+        assert_eq!(battle.next_target(0), Some(1));
+        assert_eq!(battle.next_target(0), Some(2));
+        assert_eq!(battle.next_target(0), Some(1));
+
+        assert_eq!(battle.top_out(1), None);
+        assert_eq!(battle.top_out(1), None);
+        assert_eq!(battle.next_target(0), Some(2));
+        assert_eq!(battle.next_target(2), Some(0));
+
+        assert_eq!(battle.top_out(2), Some(0));
+        assert_eq!(battle.next_target(0), None);
+    }
+}
@@ -0,0 +1,30 @@
+// FNV-1a, to identify data sent over the network (checksums, custom rotation systems). It doesn't
+// need to be strong, just cheap and the same in all platforms
+pub struct Fnv1a(u32);
+
+impl Fnv1a {
+    pub fn new() -> Self { Self(0x811c9dc5) }
+
+    pub fn write_u8(&mut self, v: u8) {
+        self.0 ^= v as u32;
+        self.0 = self.0.wrapping_mul(0x01000193);
+    }
+
+    pub fn write_u32(&mut self, v: u32) {
+        for b in v.to_le_bytes() { self.write_u8(b); }
+    }
+
+    pub fn write_u64(&mut self, v: u64) {
+        for b in v.to_le_bytes() { self.write_u8(b); }
+    }
+
+    pub fn write_bytes(&mut self, bytes: &[u8]) {
+        for &b in bytes { self.write_u8(b); }
+    }
+
+    pub fn finish(&self) -> u32 { self.0 }
+}
+
+impl Default for Fnv1a {
+    fn default() -> Self { Self::new() }
+}
@@ -1,36 +1,130 @@
 use crate::app::*;
 use crate::linalg::Vec2i;
 use crate::game::{
-    pieces::{Piece, PieceVariant},
+    input::TetrisInput,
+    pieces::{
+        Piece,
+        PieceVariant,
+        PIECES,
+        MAX_PIECE_HEIGHT,
+        get_piece_set,
+        find_custom_rotation_system,
+        get_custom_rotation_system,
+    },
     playfield::{BlockType, Playfield},
     randomizer::{Randomizer, RandomizerType},
     rules::{
         RotationSystem,
+        rotation::RotationDirection,
         lock::{LastPieceAction, LockedPiece, LockedPieceResult},
+        scoring::ScoringState,
+        topout::TopOutReason,
     },
-    tetris_game::NEXT_PIECES_COUNT,
+    tetris_game::{NEXT_PIECES_COUNT, TetrisState},
 };
 
 pub enum MultiplayerMessages {
+    // Lockstep: the spectators simulate the game with the player inputs
     Connect(Connect),
-    Update(Update),
+    Inputs(Inputs),
+    Checksum(Checksum),
+    InputRequest(InputRequest),
+
+    // Battle
+    BattleStart(BattleStart),
+    Attack(Attack),
+    PlayerInputs(PlayerInputs),
+    PlayerInputRequest(PlayerInputRequest),
+    ToppedOut(ToppedOut),
+    BattleEnd(BattleEnd),
 }
 
+// Maximum amount of input frames in a single Inputs message (fits NET_MAX_PAYLOAD_SIZE)
+pub const MAX_INPUT_FRAMES_PER_MESSAGE: usize = 64;
+
+// TetrisGame is deterministic, so the seed and the rules are enough to start simulating it
 #[derive(Debug)]
 pub struct Connect {
-    pub timestamp: u64,
-    pub tetris_game: NetworkedTetrisGame,
-
-    // Removed from TetrisGame that needed to be synched
+    pub seed: u64,
     //rules: Rules, // @XXX using rotation system instead since we are just testing and we have no macro to automate Serialize/Deserialize yet
     pub rotation_system: RotationSystem,
-    pub randomizer: Randomizer,
 }
 
+// Input of a single game update (tick). The seed and all the input frames are a replay
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, ImDraw)]
+pub struct InputFrame {
+    pub dt: u32, // microseconds, the player simulation also uses the truncated value
+    pub input: TetrisInput,
+    pub garbage: u8, // rows received before this update, so the garbage enters at the same tick
+}
+
+// Input frames starting at first_tick. The live messages repeat the last frames, so a lost packet
+// doesn't stop the spectators
 #[derive(Debug)]
-pub struct Update {
-    pub timestamp: u64,
-    pub tetris_game: NetworkedTetrisGame,
+pub struct Inputs {
+    pub first_tick: u32,
+    pub latest_tick: u32, // ticks simulated by the player, to know if there are missing frames
+    pub frames: Vec<InputFrame>,
+}
+
+// Ticks between the checksums sent by the player
+pub const CHECKSUM_INTERVAL: u32 = 60;
+
+// Checksum of the game state after simulating `tick` frames, for desync detection
+#[derive(Debug)]
+pub struct Checksum {
+    pub tick: u32,
+    pub checksum: u32,
+}
+
+// spectator -> player: resend the input frames starting at `tick`
+#[derive(Debug)]
+pub struct InputRequest {
+    pub tick: u32,
+}
+
+// Battle
+// Every player simulates its own TetrisGame. The host decides who receives each attack and who won
+
+// host -> players
+#[derive(Debug)]
+pub struct BattleStart {
+    pub seed: u64, // all players get the same pieces
+    pub rotation_system: RotationSystem, // @XXX same as Connect, Rules aren't serialized yet
+}
+
+// player -> host: attack sent by the player
+// host -> player: garbage received by the player, sent by player_id
+#[derive(Debug)]
+pub struct Attack {
+    pub player_id: ClientId,
+    pub rows: u8,
+}
+
+// Input frames of a player starting at first_tick, so the opponents can simulate (and draw) its
+// game. Like Inputs, the last frames are repeated in every message
+#[derive(Debug)]
+pub struct PlayerInputs {
+    pub player_id: ClientId,
+    pub first_tick: u32,
+    pub frames: Vec<InputFrame>,
+}
+
+// player -> host -> player_id: resend the input frames of player_id starting at `tick`
+#[derive(Debug)]
+pub struct PlayerInputRequest {
+    pub player_id: ClientId,
+    pub tick: u32,
+}
+
+#[derive(Debug)]
+pub struct ToppedOut {
+    pub player_id: ClientId,
+}
+
+#[derive(Debug)]
+pub struct BattleEnd {
+    pub winner_id: ClientId,
 }
 
 // @Refactor Serialize/Deserialize should be a macro
@@ -41,11 +135,17 @@ pub struct Update {
 pub struct NetworkedTetrisGame {
     pub timestamp: u64, // per game
 
-    pub has_topped_out: bool, // per game
+    pub state: TetrisState, // per game
+    pub state_timestamp: u64, // per state
+    pub top_out_reason: Option<TopOutReason>, // per game
+    pub rotation_system: RotationSystem, // per game. All the pieces belong to its piece set
     pub playfield: Playfield,   // per game
 
     pub current_score: u32,       // per game
     pub total_lines_cleared: u32, // per game
+    pub total_line_clear_awards: u32, // per game
+    pub tgm_level: u32, // per game
+    pub scoring_state: ScoringState, // per game
 
     pub current_piece: Option<(Piece, Vec2i)>,
     pub next_piece_types: [PieceVariant; NEXT_PIECES_COUNT], // per game
@@ -82,17 +182,62 @@ impl Serialize for MultiplayerMessages {
         match self {
             MultiplayerMessages::Connect(c) => {
                 0u8.serialize(serializer)?;
-                c.timestamp.serialize(serializer)?;
-                c.tetris_game.serialize(serializer)?;
+                c.seed.serialize(serializer)?;
                 //c.rules.serialize(serializer)?;
                 c.rotation_system.serialize(serializer)?;
-                c.randomizer.serialize(serializer)?;
             },
 
-            MultiplayerMessages::Update(u) => {
+            MultiplayerMessages::Inputs(i) => {
                 1u8.serialize(serializer)?;
-                u.timestamp.serialize(serializer)?;
-                u.tetris_game.serialize(serializer)?;
+                i.first_tick.serialize(serializer)?;
+                i.latest_tick.serialize(serializer)?;
+                i.frames.serialize(serializer)?;
+            },
+
+            MultiplayerMessages::BattleStart(b) => {
+                2u8.serialize(serializer)?;
+                b.seed.serialize(serializer)?;
+                b.rotation_system.serialize(serializer)?;
+            },
+
+            MultiplayerMessages::Attack(a) => {
+                3u8.serialize(serializer)?;
+                a.player_id.serialize(serializer)?;
+                a.rows.serialize(serializer)?;
+            },
+
+            MultiplayerMessages::PlayerInputs(p) => {
+                4u8.serialize(serializer)?;
+                p.player_id.serialize(serializer)?;
+                p.first_tick.serialize(serializer)?;
+                p.frames.serialize(serializer)?;
+            },
+
+            MultiplayerMessages::ToppedOut(t) => {
+                5u8.serialize(serializer)?;
+                t.player_id.serialize(serializer)?;
+            },
+
+            MultiplayerMessages::BattleEnd(b) => {
+                6u8.serialize(serializer)?;
+                b.winner_id.serialize(serializer)?;
+            },
+
+            MultiplayerMessages::Checksum(c) => {
+                7u8.serialize(serializer)?;
+                c.tick.serialize(serializer)?;
+                c.checksum.serialize(serializer)?;
+            },
+
+            MultiplayerMessages::InputRequest(r) => {
+                8u8.serialize(serializer)?;
+                r.tick.serialize(serializer)?;
+            },
+
+            MultiplayerMessages::PlayerInputRequest(r) => {
+                9u8.serialize(serializer)?;
+                r.player_id.serialize(serializer)?;
+                r.tick.serialize(serializer)?;
             },
         }
         Ok(())
@@ -103,32 +248,79 @@ impl Deserialize for MultiplayerMessages {
     fn deserialize(deserializer: &mut Deserializer) -> Result<Self, SerializationError> {
         let t = match u8::deserialize(deserializer)? {
             0 => {
-                let timestamp = u64::deserialize(deserializer)?;
-                let tetris_game = NetworkedTetrisGame::deserialize(deserializer)?;
+                let seed = u64::deserialize(deserializer)?;
                 //let rules = Rules::deserialize(deserializer)?;
                 let rotation_system = RotationSystem::deserialize(deserializer)?;
-                let randomizer = Randomizer::deserialize(deserializer)?;
 
                 MultiplayerMessages::Connect(Connect {
-                    timestamp,
-                    tetris_game,
+                    seed,
                     //rules,
                     rotation_system,
-                    randomizer,
                 })
             },
 
             1 => {
-                let timestamp = u64::deserialize(deserializer)?;
-                let tetris_game = NetworkedTetrisGame::deserialize(deserializer)?;
+                let first_tick = u32::deserialize(deserializer)?;
+                let latest_tick = u32::deserialize(deserializer)?;
+                let frames = Vec::<InputFrame>::deserialize(deserializer)?;
+                if frames.len() > MAX_INPUT_FRAMES_PER_MESSAGE {
+                    return Err(SerializationError::ValueOutOfRange);
+                }
+
+                MultiplayerMessages::Inputs(Inputs { first_tick, latest_tick, frames })
+            }
 
-                MultiplayerMessages::Update(Update {
-                    timestamp,
-                    tetris_game,
-                })
+            2 => {
+                let seed = u64::deserialize(deserializer)?;
+                let rotation_system = RotationSystem::deserialize(deserializer)?;
+                MultiplayerMessages::BattleStart(BattleStart { seed, rotation_system })
+            }
+
+            3 => {
+                let player_id = ClientId::deserialize(deserializer)?;
+                let rows = u8::deserialize(deserializer)?;
+                MultiplayerMessages::Attack(Attack { player_id, rows })
+            }
+
+            4 => {
+                let player_id = ClientId::deserialize(deserializer)?;
+                let first_tick = u32::deserialize(deserializer)?;
+                let frames = Vec::<InputFrame>::deserialize(deserializer)?;
+                if frames.len() > MAX_INPUT_FRAMES_PER_MESSAGE {
+                    return Err(SerializationError::ValueOutOfRange);
+                }
+
+                MultiplayerMessages::PlayerInputs(PlayerInputs { player_id, first_tick, frames })
+            }
+
+            5 => {
+                let player_id = ClientId::deserialize(deserializer)?;
+                MultiplayerMessages::ToppedOut(ToppedOut { player_id })
+            }
+
+            6 => {
+                let winner_id = ClientId::deserialize(deserializer)?;
+                MultiplayerMessages::BattleEnd(BattleEnd { winner_id })
+            }
+
+            7 => {
+                let tick = u32::deserialize(deserializer)?;
+                let checksum = u32::deserialize(deserializer)?;
+                MultiplayerMessages::Checksum(Checksum { tick, checksum })
             }
 
-            _ => unreachable!(),
+            8 => {
+                let tick = u32::deserialize(deserializer)?;
+                MultiplayerMessages::InputRequest(InputRequest { tick })
+            }
+
+            9 => {
+                let player_id = ClientId::deserialize(deserializer)?;
+                let tick = u32::deserialize(deserializer)?;
+                MultiplayerMessages::PlayerInputRequest(PlayerInputRequest { player_id, tick })
+            }
+
+            _ => return Err(SerializationError::ValueOutOfRange),
         };
         Ok(t)
     }
@@ -148,6 +340,16 @@ impl Deserialize for Playfield {
         let grid_size = Vec2i::deserialize(deserializer)?;
         let visible_height = u8::deserialize(deserializer)?;
         let blocks = Vec::<BlockType>::deserialize(deserializer)?;
+
+        // Rules limit the playfield dimensions to u8
+        let is_valid_size = |size: i32| size > 0 && size <= u8::MAX as i32;
+        if !is_valid_size(grid_size.x) || !is_valid_size(grid_size.y)
+            || visible_height as i32 > grid_size.y
+            || blocks.len() != (grid_size.x * grid_size.y) as usize
+        {
+            return Err(SerializationError::ValueOutOfRange);
+        }
+
         Ok(Self { grid_size, visible_height, blocks })
     }
 }
@@ -156,11 +358,17 @@ impl Serialize for NetworkedTetrisGame {
     fn serialize(&self, serializer: &mut Serializer) -> Result<(), SerializationError> {
         self.timestamp.serialize(serializer)?;
 
-        self.has_topped_out.serialize(serializer)?;
+        self.state.serialize(serializer)?;
+        self.state_timestamp.serialize(serializer)?;
+        self.top_out_reason.serialize(serializer)?;
+        self.rotation_system.serialize(serializer)?;
         self.playfield.serialize(serializer)?;
 
         self.current_score.serialize(serializer)?;
         self.total_lines_cleared.serialize(serializer)?;
+        self.total_line_clear_awards.serialize(serializer)?;
+        self.tgm_level.serialize(serializer)?;
+        self.scoring_state.serialize(serializer)?;
 
         self.current_piece.serialize(serializer)?;
         self.next_piece_types.serialize(serializer)?;
@@ -181,11 +389,17 @@ impl Deserialize for NetworkedTetrisGame {
     fn deserialize(deserializer: &mut Deserializer) -> Result<Self, SerializationError> {
         let timestamp = u64::deserialize(deserializer)?;
 
-        let has_topped_out = bool::deserialize(deserializer)?;
+        let state = TetrisState::deserialize(deserializer)?;
+        let state_timestamp = u64::deserialize(deserializer)?;
+        let top_out_reason = Option::<TopOutReason>::deserialize(deserializer)?;
+        let rotation_system = RotationSystem::deserialize(deserializer)?;
         let playfield = Playfield::deserialize(deserializer)?;
 
         let current_score = u32::deserialize(deserializer)?;
         let total_lines_cleared = u32::deserialize(deserializer)?;
+        let total_line_clear_awards = u32::deserialize(deserializer)?;
+        let tgm_level = u32::deserialize(deserializer)?;
+        let scoring_state = ScoringState::deserialize(deserializer)?;
 
         let current_piece = Option::<(Piece, Vec2i)>::deserialize(deserializer)?;
         let next_piece_types = <[PieceVariant; NEXT_PIECES_COUNT]>::deserialize(deserializer)?;
@@ -198,14 +412,34 @@ impl Deserialize for NetworkedTetrisGame {
         let movement_last_timestamp_x = u64::deserialize(deserializer)?;
         let movement_last_timestamp_y = u64::deserialize(deserializer)?;
 
+        // Pieces of other piece sets can't be drawn or simulated
+        let piece_set = get_piece_set(rotation_system);
+        let is_valid_block = |block: &BlockType| match block {
+            BlockType::Piece(variant) => piece_set.contains(variant),
+            _ => true,
+        };
+        let is_valid = next_piece_types.iter().all(|variant| piece_set.contains(variant))
+            && playfield.blocks.iter().all(is_valid_block)
+            && current_piece.map_or(true, |(piece, _)| piece.rotation_system == rotation_system)
+            && hold_piece.map_or(true, |piece| piece.rotation_system == rotation_system);
+        if !is_valid {
+            return Err(SerializationError::ValueOutOfRange);
+        }
+
         Ok(Self {
             timestamp,
 
-            has_topped_out,
+            state,
+            state_timestamp,
+            top_out_reason,
+            rotation_system,
             playfield,
 
             current_score,
             total_lines_cleared,
+            total_line_clear_awards,
+            tgm_level,
+            scoring_state,
 
             current_piece,
             next_piece_types,
@@ -221,6 +455,72 @@ impl Deserialize for NetworkedTetrisGame {
     }
 }
 
+impl Serialize for TetrisState {
+    fn serialize(&self, serializer: &mut Serializer) -> Result<(), SerializationError> {
+        match *self {
+            TetrisState::Spawning       => serializer.serialize_packed_u8::<0, 4>(0)?,
+            TetrisState::Falling        => serializer.serialize_packed_u8::<0, 4>(1)?,
+            TetrisState::Locking        => serializer.serialize_packed_u8::<0, 4>(2)?,
+            TetrisState::LineClearDelay => serializer.serialize_packed_u8::<0, 4>(3)?,
+            TetrisState::ToppedOut      => serializer.serialize_packed_u8::<0, 4>(4)?,
+        }
+        Ok(())
+    }
+}
+
+impl Deserialize for TetrisState {
+    fn deserialize(deserializer: &mut Deserializer) -> Result<Self, SerializationError> {
+        let t = match deserializer.deserialize_packed_u8::<0, 4>()? {
+            0 => TetrisState::Spawning,
+            1 => TetrisState::Falling,
+            2 => TetrisState::Locking,
+            3 => TetrisState::LineClearDelay,
+            _ => TetrisState::ToppedOut,
+        };
+        Ok(t)
+    }
+}
+
+impl Serialize for TopOutReason {
+    fn serialize(&self, serializer: &mut Serializer) -> Result<(), SerializationError> {
+        match *self {
+            TopOutReason::BlockOut       => serializer.serialize_packed_u8::<0, 3>(0)?,
+            TopOutReason::LockOut        => serializer.serialize_packed_u8::<0, 3>(1)?,
+            TopOutReason::PartialLockOut => serializer.serialize_packed_u8::<0, 3>(2)?,
+            TopOutReason::GarbageOut     => serializer.serialize_packed_u8::<0, 3>(3)?,
+        }
+        Ok(())
+    }
+}
+
+impl Deserialize for TopOutReason {
+    fn deserialize(deserializer: &mut Deserializer) -> Result<Self, SerializationError> {
+        let t = match deserializer.deserialize_packed_u8::<0, 3>()? {
+            0 => TopOutReason::BlockOut,
+            1 => TopOutReason::LockOut,
+            2 => TopOutReason::PartialLockOut,
+            _ => TopOutReason::GarbageOut,
+        };
+        Ok(t)
+    }
+}
+
+impl Serialize for ScoringState {
+    fn serialize(&self, serializer: &mut Serializer) -> Result<(), SerializationError> {
+        self.combo.serialize(serializer)?;
+        self.back_to_back.serialize(serializer)?;
+        Ok(())
+    }
+}
+
+impl Deserialize for ScoringState {
+    fn deserialize(deserializer: &mut Deserializer) -> Result<Self, SerializationError> {
+        let combo = Option::<u32>::deserialize(deserializer)?;
+        let back_to_back = Option::<u32>::deserialize(deserializer)?;
+        Ok(Self { combo, back_to_back })
+    }
+}
+
 impl Serialize for Vec2i {
     fn serialize(&self, serializer: &mut Serializer) -> Result<(), SerializationError> {
         self.x.serialize(serializer)?;
@@ -240,12 +540,24 @@ impl Deserialize for Vec2i {
 impl Serialize for RandomizerType {
     fn serialize(&self, serializer: &mut Serializer) -> Result<(), SerializationError> {
         match *self {
-            RandomizerType::Sequential => serializer.serialize_packed_u8::<0, 5>(0)?,
-            RandomizerType::FullRandom => serializer.serialize_packed_u8::<0, 5>(1)?,
-            RandomizerType::Random7Bag => serializer.serialize_packed_u8::<0, 5>(2)?,
-            RandomizerType::TGMACE     => serializer.serialize_packed_u8::<0, 5>(3)?,
-            RandomizerType::TGM1       => serializer.serialize_packed_u8::<0, 5>(4)?,
-            RandomizerType::TGM        => serializer.serialize_packed_u8::<0, 5>(5)?,
+            RandomizerType::Sequential => serializer.serialize_packed_u8::<0, 9>(0)?,
+            RandomizerType::FullRandom => serializer.serialize_packed_u8::<0, 9>(1)?,
+            RandomizerType::Random7Bag => serializer.serialize_packed_u8::<0, 9>(2)?,
+            RandomizerType::TGMACE     => serializer.serialize_packed_u8::<0, 9>(3)?,
+            RandomizerType::TGM1       => serializer.serialize_packed_u8::<0, 9>(4)?,
+            RandomizerType::TGM        => serializer.serialize_packed_u8::<0, 9>(5)?,
+            RandomizerType::TGMCustom { history, retries } => {
+                serializer.serialize_packed_u8::<0, 9>(6)?;
+                history.serialize(serializer)?;
+                retries.serialize(serializer)?;
+            }
+            RandomizerType::TGM3       => serializer.serialize_packed_u8::<0, 9>(7)?,
+            RandomizerType::NES        => serializer.serialize_packed_u8::<0, 9>(8)?,
+            RandomizerType::Bag { copies, extra_pieces } => {
+                serializer.serialize_packed_u8::<0, 9>(9)?;
+                copies.serialize(serializer)?;
+                extra_pieces.serialize(serializer)?;
+            }
         }
         Ok(())
     }
@@ -253,13 +565,25 @@ impl Serialize for RandomizerType {
 
 impl Deserialize for RandomizerType {
     fn deserialize(deserializer: &mut Deserializer) -> Result<Self, SerializationError> {
-        let t = match deserializer.deserialize_packed_u8::<0, 5>()? {
+        let t = match deserializer.deserialize_packed_u8::<0, 9>()? {
             0 => RandomizerType::Sequential,
             1 => RandomizerType::FullRandom,
             2 => RandomizerType::Random7Bag,
             3 => RandomizerType::TGMACE,
             4 => RandomizerType::TGM1,
-            _ => RandomizerType::TGM,
+            5 => RandomizerType::TGM,
+            6 => {
+                let history = u8::deserialize(deserializer)?;
+                let retries = u8::deserialize(deserializer)?;
+                RandomizerType::TGMCustom { history, retries }
+            }
+            7 => RandomizerType::TGM3,
+            8 => RandomizerType::NES,
+            _ => {
+                let copies = u8::deserialize(deserializer)?;
+                let extra_pieces = u8::deserialize(deserializer)?;
+                RandomizerType::Bag { copies, extra_pieces }
+            }
         };
         Ok(t)
     }
@@ -275,20 +599,46 @@ impl Serialize for Randomizer {
 impl Deserialize for Randomizer {
     fn deserialize(_deserializer: &mut Deserializer) -> Result<Self, SerializationError> {
         // @TODO
-        Ok(RandomizerType::Random7Bag.build(0))
+        Ok(RandomizerType::Random7Bag.build(0, &PIECES))
     }
 }
 
+// Standard pieces are 0 to 6, custom pieces are 7 followed by their index
+pub fn piece_variant_id(piece_type: PieceVariant) -> u8 {
+    match piece_type {
+        PieceVariant::S => 0,
+        PieceVariant::Z => 1,
+        PieceVariant::J => 2,
+        PieceVariant::L => 3,
+        PieceVariant::O => 4,
+        PieceVariant::I => 5,
+        PieceVariant::T => 6,
+        PieceVariant::Custom(_) => 7,
+    }
+}
+
+fn deserialize_piece_variant_from_id(
+    id: u8,
+    deserializer: &mut Deserializer
+) -> Result<PieceVariant, SerializationError> {
+    let t = match id {
+        0 => PieceVariant::S,
+        1 => PieceVariant::Z,
+        2 => PieceVariant::J,
+        3 => PieceVariant::L,
+        4 => PieceVariant::O,
+        5 => PieceVariant::I,
+        6 => PieceVariant::T,
+        _ => PieceVariant::Custom(u8::deserialize(deserializer)?),
+    };
+    Ok(t)
+}
+
 impl Serialize for PieceVariant {
     fn serialize(&self, serializer: &mut Serializer) -> Result<(), SerializationError> {
-        match *self {
-            PieceVariant::S => serializer.serialize_packed_u8::<0, 6>(0)?,
-            PieceVariant::Z => serializer.serialize_packed_u8::<0, 6>(1)?,
-            PieceVariant::J => serializer.serialize_packed_u8::<0, 6>(2)?,
-            PieceVariant::L => serializer.serialize_packed_u8::<0, 6>(3)?,
-            PieceVariant::O => serializer.serialize_packed_u8::<0, 6>(4)?,
-            PieceVariant::I => serializer.serialize_packed_u8::<0, 6>(5)?,
-            PieceVariant::T => serializer.serialize_packed_u8::<0, 6>(6)?,
+        serializer.serialize_packed_u8::<0, 7>(piece_variant_id(*self))?;
+        if let PieceVariant::Custom(index) = *self {
+            index.serialize(serializer)?;
         }
         Ok(())
     }
@@ -296,24 +646,22 @@ impl Serialize for PieceVariant {
 
 impl Deserialize for PieceVariant {
     fn deserialize(deserializer: &mut Deserializer) -> Result<Self, SerializationError> {
-        let t = match deserializer.deserialize_packed_u8::<0, 6>()? {
-            0 => PieceVariant::S,
-            1 => PieceVariant::Z,
-            2 => PieceVariant::J,
-            3 => PieceVariant::L,
-            4 => PieceVariant::O,
-            5 => PieceVariant::I,
-            _ => PieceVariant::T,
-        };
-        Ok(t)
+        let id = deserializer.deserialize_packed_u8::<0, 7>()?;
+        deserialize_piece_variant_from_id(id, deserializer)
     }
 }
 
 impl Serialize for BlockType {
     fn serialize(&self, serializer: &mut Serializer) -> Result<(), SerializationError> {
         match *self {
-            BlockType::Piece(piece_type) => piece_type.serialize(serializer)?,
-            BlockType::Empty => serializer.serialize_packed_u8::<0, 7>(7)?,
+            BlockType::Piece(piece_type) => {
+                serializer.serialize_packed_u8::<0, 9>(piece_variant_id(piece_type))?;
+                if let PieceVariant::Custom(index) = piece_type {
+                    index.serialize(serializer)?;
+                }
+            }
+            BlockType::Empty => serializer.serialize_packed_u8::<0, 9>(8)?,
+            BlockType::Garbage => serializer.serialize_packed_u8::<0, 9>(9)?,
         }
         Ok(())
     }
@@ -321,18 +669,10 @@ impl Serialize for BlockType {
 
 impl Deserialize for BlockType {
     fn deserialize(deserializer: &mut Deserializer) -> Result<Self, SerializationError> {
-        let t = match deserializer.deserialize_packed_u8::<0, 7>()? {
-            // @XXX: this is repeating the PieceVariant deserialization,
-            //       any way to avoid this repetition?
-            0 => BlockType::Piece(PieceVariant::S),
-            1 => BlockType::Piece(PieceVariant::Z),
-            2 => BlockType::Piece(PieceVariant::J),
-            3 => BlockType::Piece(PieceVariant::L),
-            4 => BlockType::Piece(PieceVariant::O),
-            5 => BlockType::Piece(PieceVariant::I),
-            6 => BlockType::Piece(PieceVariant::T),
-
-            _ => BlockType::Empty,
+        let t = match deserializer.deserialize_packed_u8::<0, 9>()? {
+            8 => BlockType::Empty,
+            9 => BlockType::Garbage,
+            id => BlockType::Piece(deserialize_piece_variant_from_id(id, deserializer)?),
         };
         Ok(t)
     }
@@ -353,6 +693,9 @@ impl Deserialize for Piece {
         let variant = PieceVariant::deserialize(deserializer)?;
         let rot = deserializer.deserialize_packed_i32::<0, 3>()?;
         let rotation_system = RotationSystem::deserialize(deserializer)?;
+        if !get_piece_set(rotation_system).contains(&variant) {
+            return Err(SerializationError::ValueOutOfRange);
+        }
         Ok(Self { variant, rot, rotation_system })
     }
 }
@@ -360,13 +703,20 @@ impl Deserialize for Piece {
 impl Serialize for RotationSystem {
     fn serialize(&self, serializer: &mut Serializer) -> Result<(), SerializationError> {
         match *self {
-            RotationSystem::Original => serializer.serialize_packed_u8::<0, 6>(0)?,
-            RotationSystem::NRSL     => serializer.serialize_packed_u8::<0, 6>(1)?,
-            RotationSystem::NRSR     => serializer.serialize_packed_u8::<0, 6>(2)?,
-            RotationSystem::Sega     => serializer.serialize_packed_u8::<0, 6>(3)?,
-            RotationSystem::ARS      => serializer.serialize_packed_u8::<0, 6>(4)?,
-            RotationSystem::SRS      => serializer.serialize_packed_u8::<0, 6>(5)?,
-            RotationSystem::DTET     => serializer.serialize_packed_u8::<0, 6>(6)?,
+            RotationSystem::Original => serializer.serialize_packed_u8::<0, 7>(0)?,
+            RotationSystem::NRSL     => serializer.serialize_packed_u8::<0, 7>(1)?,
+            RotationSystem::NRSR     => serializer.serialize_packed_u8::<0, 7>(2)?,
+            RotationSystem::Sega     => serializer.serialize_packed_u8::<0, 7>(3)?,
+            RotationSystem::ARS      => serializer.serialize_packed_u8::<0, 7>(4)?,
+            RotationSystem::SRS      => serializer.serialize_packed_u8::<0, 7>(5)?,
+            RotationSystem::DTET     => serializer.serialize_packed_u8::<0, 7>(6)?,
+
+            // The other side needs to have the same rotation system loaded, but maybe in another
+            // order, so the hash is sent instead of the index
+            RotationSystem::Custom(index) => {
+                serializer.serialize_packed_u8::<0, 7>(7)?;
+                get_custom_rotation_system(index).hash.serialize(serializer)?;
+            }
         }
         Ok(())
     }
@@ -374,14 +724,18 @@ impl Serialize for RotationSystem {
 
 impl Deserialize for RotationSystem {
     fn deserialize(deserializer: &mut Deserializer) -> Result<Self, SerializationError> {
-        let t = match deserializer.deserialize_packed_u8::<0, 6>()? {
+        let t = match deserializer.deserialize_packed_u8::<0, 7>()? {
             0 => RotationSystem::Original,
             1 => RotationSystem::NRSL,
             2 => RotationSystem::NRSR,
             3 => RotationSystem::Sega,
             4 => RotationSystem::ARS,
             5 => RotationSystem::SRS,
-            _ => RotationSystem::DTET,
+            6 => RotationSystem::DTET,
+            _ => {
+                let hash = u32::deserialize(deserializer)?;
+                find_custom_rotation_system(hash).ok_or(SerializationError::ValueOutOfRange)?
+            }
         };
         Ok(t)
     }
@@ -419,11 +773,82 @@ impl Deserialize for LockedPiece {
     }
 }
 
+impl Serialize for RotationDirection {
+    fn serialize(&self, serializer: &mut Serializer) -> Result<(), SerializationError> {
+        match *self {
+            RotationDirection::Clockwise        => serializer.serialize_packed_u8::<0, 2>(0)?,
+            RotationDirection::Counterclockwise => serializer.serialize_packed_u8::<0, 2>(1)?,
+            RotationDirection::Half             => serializer.serialize_packed_u8::<0, 2>(2)?,
+        }
+        Ok(())
+    }
+}
+
+impl Deserialize for RotationDirection {
+    fn deserialize(deserializer: &mut Deserializer) -> Result<Self, SerializationError> {
+        let t = match deserializer.deserialize_packed_u8::<0, 2>()? {
+            0 => RotationDirection::Clockwise,
+            1 => RotationDirection::Counterclockwise,
+            _ => RotationDirection::Half,
+        };
+        Ok(t)
+    }
+}
+
+impl Serialize for TetrisInput {
+    fn serialize(&self, serializer: &mut Serializer) -> Result<(), SerializationError> {
+        serializer.serialize_packed_i8::<-1, 1>(self.horizontal_movement)?;
+        self.rotation.serialize(serializer)?;
+        self.soft_drop.serialize(serializer)?;
+        self.hard_drop.serialize(serializer)?;
+        self.hold.serialize(serializer)?;
+        self.rotation_held.serialize(serializer)?;
+        self.hold_held.serialize(serializer)?;
+        Ok(())
+    }
+}
+
+impl Deserialize for TetrisInput {
+    fn deserialize(deserializer: &mut Deserializer) -> Result<Self, SerializationError> {
+        Ok(TetrisInput {
+            horizontal_movement: deserializer.deserialize_packed_i8::<-1, 1>()?,
+            rotation: Option::<RotationDirection>::deserialize(deserializer)?,
+            soft_drop: bool::deserialize(deserializer)?,
+            hard_drop: bool::deserialize(deserializer)?,
+            hold: bool::deserialize(deserializer)?,
+            rotation_held: Option::<RotationDirection>::deserialize(deserializer)?,
+            hold_held: bool::deserialize(deserializer)?,
+        })
+    }
+}
+
+impl Serialize for InputFrame {
+    fn serialize(&self, serializer: &mut Serializer) -> Result<(), SerializationError> {
+        self.dt.serialize(serializer)?;
+        self.input.serialize(serializer)?;
+        self.garbage.serialize(serializer)?;
+        Ok(())
+    }
+}
+
+impl Deserialize for InputFrame {
+    fn deserialize(deserializer: &mut Deserializer) -> Result<Self, SerializationError> {
+        let dt = u32::deserialize(deserializer)?;
+        let input = TetrisInput::deserialize(deserializer)?;
+        let garbage = u8::deserialize(deserializer)?;
+        Ok(InputFrame { dt, input, garbage })
+    }
+}
+
 impl Serialize for LastPieceAction {
     fn serialize(&self, serializer: &mut Serializer) -> Result<(), SerializationError> {
         match *self {
             LastPieceAction::Movement => false.serialize(serializer)?,
-            LastPieceAction::Rotation => true.serialize(serializer)?,
+            LastPieceAction::Rotation { direction, kick } => {
+                true.serialize(serializer)?;
+                direction.serialize(serializer)?;
+                kick.serialize(serializer)?;
+            }
         }
         Ok(())
     }
@@ -433,7 +858,11 @@ impl Deserialize for LastPieceAction {
     fn deserialize(deserializer: &mut Deserializer) -> Result<Self, SerializationError> {
         let t = match bool::deserialize(deserializer)? {
             false => LastPieceAction::Movement,
-            true  => LastPieceAction::Rotation,
+            true  => {
+                let direction = RotationDirection::deserialize(deserializer)?;
+                let kick = u8::deserialize(deserializer)?;
+                LastPieceAction::Rotation { direction, kick }
+            }
         };
         Ok(t)
     }
@@ -442,24 +871,26 @@ impl Deserialize for LastPieceAction {
 impl Serialize for LockedPieceResult {
     fn serialize(&self, serializer: &mut Serializer) -> Result<(), SerializationError> {
         match self {
-            LockedPieceResult::Nothing => serializer.serialize_packed_u8::<0, 5>(0)?,
-
-            LockedPieceResult::Single(v) => { serializer.serialize_packed_u8::<0, 5>(1)?; v.serialize(serializer)?; }
-            LockedPieceResult::Double(v) => { serializer.serialize_packed_u8::<0, 5>(2)?; v.serialize(serializer)?; }
-            LockedPieceResult::Triple(v) => { serializer.serialize_packed_u8::<0, 5>(3)?; v.serialize(serializer)?; }
-            LockedPieceResult::Tetris(v) => { serializer.serialize_packed_u8::<0, 5>(4)?; v.serialize(serializer)?; }
-
-            _ =>  serializer.serialize_packed_u8::<0, 5>(5)?,
-
-            /*
-            LockedPieceResult::MiniTSpin       => serializer.serialize_packed_u8::<0, 11>(0)?,
-            LockedPieceResult::MiniTSpinSingle => serializer.serialize_packed_u8::<0, 11>(0)?,
-            LockedPieceResult::MiniTSpinDouble => serializer.serialize_packed_u8::<0, 11>(0)?,
-            LockedPieceResult::TSpin           => serializer.serialize_packed_u8::<0, 11>(0)?,
-            LockedPieceResult::TSpinSingle     => serializer.serialize_packed_u8::<0, 11>(0)?,
-            LockedPieceResult::TSpinDouble     => serializer.serialize_packed_u8::<0, 11>(0)?,
-            LockedPieceResult::TSpinTriple     => serializer.serialize_packed_u8::<0, 11>(0)?,
-            */
+            LockedPieceResult::Nothing => serializer.serialize_packed_u8::<0, 12>(0)?,
+
+            LockedPieceResult::Single(v) => { serializer.serialize_packed_u8::<0, 12>(1)?; v.serialize(serializer)?; }
+            LockedPieceResult::Double(v) => { serializer.serialize_packed_u8::<0, 12>(2)?; v.serialize(serializer)?; }
+            LockedPieceResult::Triple(v) => { serializer.serialize_packed_u8::<0, 12>(3)?; v.serialize(serializer)?; }
+            LockedPieceResult::Tetris(v) => { serializer.serialize_packed_u8::<0, 12>(4)?; v.serialize(serializer)?; }
+
+            LockedPieceResult::MiniTSpin          => serializer.serialize_packed_u8::<0, 12>(5)?,
+            LockedPieceResult::MiniTSpinSingle(v) => { serializer.serialize_packed_u8::<0, 12>(6)?; v.serialize(serializer)?; }
+            LockedPieceResult::MiniTSpinDouble(v) => { serializer.serialize_packed_u8::<0, 12>(7)?; v.serialize(serializer)?; }
+            LockedPieceResult::TSpin              => serializer.serialize_packed_u8::<0, 12>(8)?,
+            LockedPieceResult::TSpinSingle(v)     => { serializer.serialize_packed_u8::<0, 12>(9)?; v.serialize(serializer)?; }
+            LockedPieceResult::TSpinDouble(v)     => { serializer.serialize_packed_u8::<0, 12>(10)?; v.serialize(serializer)?; }
+            LockedPieceResult::TSpinTriple(v)     => { serializer.serialize_packed_u8::<0, 12>(11)?; v.serialize(serializer)?; }
+
+            LockedPieceResult::MultiLine { count, lines } => {
+                serializer.serialize_packed_u8::<0, 12>(12)?;
+                serializer.serialize_packed_u8::<5, { MAX_PIECE_HEIGHT as u8 }>(*count)?;
+                for line in &lines[..*count as usize] { line.serialize(serializer)?; }
+            }
         }
         Ok(())
     }
@@ -467,7 +898,7 @@ impl Serialize for LockedPieceResult {
 
 impl Deserialize for LockedPieceResult {
     fn deserialize(deserializer: &mut Deserializer) -> Result<Self, SerializationError> {
-        let t = match deserializer.deserialize_packed_u8::<0, 5>()? {
+        let t = match deserializer.deserialize_packed_u8::<0, 12>()? {
             0 => LockedPieceResult::Nothing,
 
             1 => LockedPieceResult::Single(<[u8; 1]>::deserialize(deserializer)?),
@@ -475,7 +906,20 @@ impl Deserialize for LockedPieceResult {
             3 => LockedPieceResult::Triple(<[u8; 3]>::deserialize(deserializer)?),
             4 => LockedPieceResult::Tetris(<[u8; 4]>::deserialize(deserializer)?),
 
-            _ => LockedPieceResult::MiniTSpin,
+            5  => LockedPieceResult::MiniTSpin,
+            6  => LockedPieceResult::MiniTSpinSingle(<[u8; 1]>::deserialize(deserializer)?),
+            7  => LockedPieceResult::MiniTSpinDouble(<[u8; 2]>::deserialize(deserializer)?),
+            8  => LockedPieceResult::TSpin,
+            9  => LockedPieceResult::TSpinSingle(<[u8; 1]>::deserialize(deserializer)?),
+            10 => LockedPieceResult::TSpinDouble(<[u8; 2]>::deserialize(deserializer)?),
+            11 => LockedPieceResult::TSpinTriple(<[u8; 3]>::deserialize(deserializer)?),
+
+            _ => {
+                let count = deserializer.deserialize_packed_u8::<5, { MAX_PIECE_HEIGHT as u8 }>()?;
+                let mut lines = [0; MAX_PIECE_HEIGHT];
+                for line in &mut lines[..count as usize] { *line = u8::deserialize(deserializer)?; }
+                LockedPieceResult::MultiLine { count, lines }
+            }
         };
         Ok(t)
     }
@@ -489,10 +933,8 @@ pub struct Rules {
     // gameplay rules
 
     // https://tetris.fandom.com/wiki/Drop
-    pub has_hard_drop: bool,
-    pub has_hard_drop_lock: bool, // Firm drop = false
-    pub has_soft_drop: bool,
-    pub has_soft_drop_lock: bool, // Lock when soft dropping
+    pub hard_drop_rule: HardDropRule,
+    pub soft_drop_rule: SoftDropRule,
 
     pub has_hold_piece: bool,
     pub has_ghost_piece: bool,
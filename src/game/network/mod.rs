@@ -1,3 +1,9 @@
+mod battle;
+mod hash;
 mod messages;
+mod rollback;
 
+pub use battle::*;
+pub use hash::*;
 pub use messages::*;
+pub use rollback::*;
@@ -0,0 +1,238 @@
+use std::collections::VecDeque;
+
+use crate::app::ImDraw;
+use crate::game::tetris_game::TetrisGame;
+
+use super::InputFrame;
+
+// Remote frames too far in the future are dropped, they are requested again when the game stalls
+// waiting for them (see is_stalled)
+const MAX_BUFFERED_FRAMES: usize = 256;
+
+#[derive(Copy, Clone, Debug, ImDraw)]
+pub struct RollbackConfig {
+    pub input_delay: u32,         // ticks the local inputs wait before being simulated
+    pub max_rollback_frames: u32, // ticks a remote game can be predicted before stalling
+}
+
+impl Default for RollbackConfig {
+    fn default() -> Self {
+        Self {
+            input_delay: 2,
+            max_rollback_frames: 8,
+        }
+    }
+}
+
+// Game of a remote player (GGPO style). The missing inputs are predicted so the game doesn't wait
+// for the network, and when the real inputs arrive the game is rolled back to the first wrong
+// prediction and simulated again.
+// The remote frames are simulated with their own dt, so both games stay deterministic. The battles
+// run on a fixed timestep, otherwise every predicted dt would be a wrong prediction
+#[derive(Debug)]
+pub struct RollbackGame {
+    game: TetrisGame, // simulated until `tick`, maybe with predicted frames
+    tick: u32,
+    confirmed_tick: u32,         // ticks before this were simulated with the remote frames
+    confirmed_frame: InputFrame, // remote frame of the tick before confirmed_tick
+    max_rollback_frames: u32,
+
+    // Starting at confirmed_tick. The snapshots are the game before simulating each tick
+    snapshots: VecDeque<TetrisGame>,
+    simulated_frames: VecDeque<InputFrame>,
+    remote_frames: VecDeque<Option<InputFrame>>, // can go past `tick`
+
+    rollback_count: u32,
+}
+
+impl_imdraw_todo!(RollbackGame);
+
+impl RollbackGame {
+    pub fn new(game: TetrisGame, max_rollback_frames: u32) -> Self {
+        Self {
+            game,
+            tick: 0,
+            confirmed_tick: 0,
+            confirmed_frame: InputFrame::default(),
+            max_rollback_frames,
+
+            snapshots: VecDeque::new(),
+            simulated_frames: VecDeque::new(),
+            remote_frames: VecDeque::new(),
+
+            rollback_count: 0,
+        }
+    }
+
+    pub fn game(&self)           -> &TetrisGame { &self.game }
+    pub fn tick(&self)           -> u32         { self.tick }
+    pub fn confirmed_tick(&self) -> u32         { self.confirmed_tick }
+    pub fn rollback_count(&self) -> u32         { self.rollback_count }
+
+    pub fn add_remote_frames(&mut self, first_tick: u32, frames: &[InputFrame]) {
+        for (tick, frame) in (first_tick..).zip(frames) {
+            if tick < self.confirmed_tick { continue; }
+
+            let index = (tick - self.confirmed_tick) as usize;
+            if index >= MAX_BUFFERED_FRAMES { break; }
+
+            if self.remote_frames.len() <= index {
+                self.remote_frames.resize(index + 1, None);
+            }
+            self.remote_frames[index] = Some(*frame);
+        }
+    }
+
+    // Simulates the game until target_tick, rolling back first if a prediction was wrong. The dt
+    // is used for the predicted frames
+    pub fn update(&mut self, target_tick: u32, dt: u64) {
+        let mispredicted_index = self.simulated_frames.iter()
+            .zip(self.remote_frames.iter())
+            .position(|(simulated, remote)| matches!(remote, Some(remote) if remote != simulated));
+
+        if let Some(index) = mispredicted_index {
+            self.game = self.snapshots[index].clone();
+            self.snapshots.truncate(index);
+            self.tick = self.confirmed_tick + index as u32;
+
+            // Same amount of ticks, with the same dts
+            let old_frames: Vec<InputFrame> = self.simulated_frames.drain(index..).collect();
+            for frame in old_frames {
+                self.simulate_tick(frame.dt as u64);
+            }
+
+            self.rollback_count += 1;
+        }
+
+        loop {
+            self.confirm_frames();
+            if self.tick >= target_tick { break; }
+
+            // Stall if the prediction would go too far, it's too expensive to roll back
+            if self.is_stalled() { break; }
+
+            self.simulate_tick(dt);
+        }
+    }
+
+    // The prediction went as far as it can. If it lasts, the remote frame of confirmed_tick was
+    // probably lost and has to be requested
+    pub fn is_stalled(&self) -> bool {
+        let index = (self.tick - self.confirmed_tick) as usize;
+        let has_remote_frame = matches!(self.remote_frames.get(index), Some(Some(_)));
+        !has_remote_frame && self.tick - self.confirmed_tick >= self.max_rollback_frames
+    }
+
+    fn simulate_tick(&mut self, dt: u64) {
+        let dt = dt.min(u32::MAX as u64) as u32;
+        let index = (self.tick - self.confirmed_tick) as usize;
+        let frame = match self.remote_frames.get(index) {
+            Some(Some(frame)) => *frame,
+            _ => self.predict_frame(index, dt),
+        };
+
+        self.snapshots.push_back(self.game.clone());
+        self.simulated_frames.push_back(frame);
+        self.game.update_with_input_frame(frame);
+        self.tick += 1;
+    }
+
+    // Nothing new happens: only the buttons of the last known frame are still held
+    fn predict_frame(&self, index: usize, dt: u32) -> InputFrame {
+        let last_frame = self.remote_frames.iter()
+            .take(index)
+            .rev()
+            .flatten()
+            .next()
+            .unwrap_or(&self.confirmed_frame);
+
+        InputFrame {
+            dt,
+            input: last_frame.input.held(),
+            garbage: 0,
+        }
+    }
+
+    // The ticks simulated with the remote frames will never roll back, so their snapshots can go
+    fn confirm_frames(&mut self) {
+        while let (Some(Some(remote)), Some(simulated)) =
+            (self.remote_frames.front(), self.simulated_frames.front())
+        {
+            if remote != simulated { break; }
+
+            self.confirmed_frame = *remote;
+            self.confirmed_tick += 1;
+            self.remote_frames.pop_front();
+            self.simulated_frames.pop_front();
+            self.snapshots.pop_front();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::{
+        input::TetrisInput,
+        rules::{RotationSystem, Rules},
+    };
+
+    #[test]
+    fn test_rollback() {
+        let rules: Rules = RotationSystem::SRS.into();
+        let mut remote = TetrisGame::new(rules.clone(), 42);
+        let mut rollback = RollbackGame::new(TetrisGame::new(rules, 42), 8);
+
+        let idle = InputFrame { dt: 16_666, ..InputFrame::default() };
+        let hard_drop = InputFrame {
+            input: TetrisInput { hard_drop: true, ..TetrisInput::default() },
+            ..idle
+        };
+        let frames = [idle, idle, hard_drop, idle, idle, hard_drop, idle, idle];
+        for frame in frames.iter() {
+            remote.update_with_input_frame(*frame);
+        }
+
+        // Idle frames are predicted correctly, so there's nothing to roll back
+        rollback.add_remote_frames(0, &frames[..2]);
+        rollback.update(4, 16_666);
+        assert_eq!(rollback.tick(), 4);
+        assert_eq!(rollback.confirmed_tick(), 2);
+        assert_eq!(rollback.rollback_count(), 0);
+
+        // The late hard drop was mispredicted
+        rollback.add_remote_frames(2, &frames[2..]);
+        rollback.update(8, 16_666);
+        assert_eq!(rollback.rollback_count(), 1);
+        assert_eq!(rollback.confirmed_tick(), 8);
+        assert_eq!(rollback.game().checksum(), remote.checksum());
+
+        // The prediction stalls after max_rollback_frames
+        rollback.update(20, 16_666);
+        assert_eq!(rollback.tick(), 16);
+        assert!(rollback.is_stalled());
+    }
+
+    #[test]
+    fn test_rollback_with_other_dt() {
+        let rules: Rules = RotationSystem::SRS.into();
+        let mut remote = TetrisGame::new(rules.clone(), 42);
+        let mut rollback = RollbackGame::new(TetrisGame::new(rules, 42), 8);
+
+        // The remote frames are slower than the local ones, so the gravity locks pieces earlier
+        let frames = vec![InputFrame { dt: 33_333, ..InputFrame::default() }; 600];
+        for frame in frames.iter() {
+            remote.update_with_input_frame(*frame);
+        }
+
+        for tick in (0..frames.len()).step_by(4) {
+            rollback.update(tick as u32 + 4, 16_666);
+            rollback.add_remote_frames(tick as u32, &frames[tick..tick + 4]);
+        }
+        rollback.update(frames.len() as u32, 16_666);
+
+        assert!(rollback.rollback_count() > 0);
+        assert_eq!(rollback.confirmed_tick(), 600);
+        assert_eq!(rollback.game().checksum(), remote.checksum());
+    }
+}
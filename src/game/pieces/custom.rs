@@ -0,0 +1,439 @@
+// Rotation systems loaded from text files.
+//
+// The file describes, for each piece, its color, spawn offset, blocks for each rotation and wall
+// kick tests. Lines starting with // are comments. Example:
+//
+//   name EXAMPLE
+//
+//   // Kick tests used by all pieces without their own kick tests.
+//   // kicks <rotation (0-3) or * for all> <cw|ccw> <x,y tests...>
+//   kicks * cw  0,0 1,0 -1,0
+//   kicks * ccw 0,0 -1,0 1,0
+//
+//   piece T
+//   color 0.5 0.0 0.5
+//   spawn 0 0
+//   rotation
+//   ....
+//   .#..
+//   ###.
+//   ....
+//   rotation
+//   ...
+//
+// Rotation grids are written top to bottom. Pieces with less than 4 rotations repeat them in
+// order. Kick tests use the same coordinates as the playfield (y up) and are tested in order.
+//
+// The pieces defined in the file are the piece set dealt by the randomizers. Pieces named S, Z, J,
+// L, O, I or T are the tetrominoes and must have 4 blocks (the T-Spin rules depend on it), any
+// other name defines a new piece (pentominoes, trominoes, ...), which can have any amount of blocks
+// and at most MAX_PIECE_HEIGHT rows.
+
+use std::path::Path;
+use std::sync::RwLock;
+
+use crate::app::{Color, WHITE};
+use crate::game::network::Fnv1a;
+use crate::linalg::Vec2i;
+use super::*;
+
+#[derive(Debug)]
+pub enum CustomRotationSystemError {
+    Io(std::io::Error),
+    Parse { line: usize, message: String },
+}
+
+struct CustomPiece {
+    data: PieceData,
+    spawn_offset: Vec2i,
+    kicks: [[Vec<Vec2i>; 2]; 4], // rotation, direction (cw, ccw)
+}
+
+pub struct CustomRotationSystem {
+    pub name: String,
+    pub hash: u32, // of the commands, identifies the rotation system over the network
+    piece_set: Vec<PieceVariant>,
+    pieces: Vec<CustomPiece>, // same order as piece_set
+}
+
+impl CustomRotationSystem {
+    fn piece(&self, piece_type: PieceVariant) -> &CustomPiece {
+        let index = self.piece_set.iter()
+            .position(|&piece| piece == piece_type)
+            .unwrap_or_else(|| panic!("piece {:?} is not defined in {}", piece_type, self.name));
+        &self.pieces[index]
+    }
+
+    pub(super) fn piece_data(&self, piece_type: PieceVariant) -> &PieceData {
+        &self.piece(piece_type).data
+    }
+
+    pub fn piece_set(&self) -> &[PieceVariant] {
+        &self.piece_set
+    }
+
+    pub fn spawn_offset(&self, piece_type: PieceVariant) -> Vec2i {
+        self.piece(piece_type).spawn_offset
+    }
+
+    pub fn rotation_tests(&self, piece_type: PieceVariant, rot: i32, is_clockwise: bool) -> &[Vec2i] {
+        let rot = (((rot % 4) + 4) % 4) as usize;
+        &self.piece(piece_type).kicks[rot][(!is_clockwise) as usize]
+    }
+
+    pub fn load_from_file<P: AsRef<Path>>(path: P) -> Result<Self, CustomRotationSystemError> {
+        let buffer = std::fs::read_to_string(path).map_err(CustomRotationSystemError::Io)?;
+        Self::parse(&buffer)
+    }
+
+    pub fn parse(buffer: &str) -> Result<Self, CustomRotationSystemError> {
+        let mut name = String::new();
+        let mut default_kicks: [[Option<Vec<Vec2i>>; 2]; 4] = Default::default();
+
+        // Pieces in the order they are defined
+        let mut piece_names: Vec<String> = Vec::new();
+        let mut piece_set: Vec<PieceVariant> = Vec::new();
+        let mut colors: Vec<Option<Color>> = Vec::new();
+        let mut spawn_offsets: Vec<Vec2i> = Vec::new();
+        let mut rotations: Vec<Vec<Vec<Vec2i>>> = Vec::new();
+        let mut kicks: Vec<[[Option<Vec<Vec2i>>; 2]; 4]> = Vec::new();
+        let mut custom_pieces_count = 0u8;
+
+        let mut current_piece: Option<usize> = None;
+        let mut grid: Option<Vec<&str>> = None;
+
+        // Ignoring comments, empty lines and line endings
+        let mut hash = Fnv1a::new();
+
+        // Rotation grids end on the next command
+        let finish_grid = |grid: &mut Option<Vec<&str>>, piece_rotations: &mut Vec<Vec<Vec2i>>, line| {
+            if let Some(rows) = grid.take() {
+                let blocks = parse_grid(&rows);
+                if blocks.is_empty() {
+                    return Err(CustomRotationSystemError::Parse {
+                        line,
+                        message: "rotations must have at least 1 block".to_string(),
+                    });
+                }
+
+                let height = blocks.iter().map(|block| block.y).max().unwrap()
+                    - blocks.iter().map(|block| block.y).min().unwrap() + 1;
+                if height as usize > MAX_PIECE_HEIGHT {
+                    return Err(CustomRotationSystemError::Parse {
+                        line,
+                        message: format!("rotations can't be taller than {} rows", MAX_PIECE_HEIGHT),
+                    });
+                }
+
+                piece_rotations.push(blocks);
+            }
+            Ok(())
+        };
+
+        let mut line_number = 0;
+        for line in buffer.lines() {
+            line_number += 1;
+            let error = |message: &str| CustomRotationSystemError::Parse {
+                line: line_number,
+                message: message.to_string()
+            };
+
+            let line = line.trim();
+            if line.is_empty() || line.starts_with("//") { continue; }
+
+            hash.write_bytes(line.as_bytes());
+            hash.write_u8(b'\n');
+
+            // Rotation grid rows
+            if grid.is_some() {
+                if line.chars().all(|c| c == '.' || c == '#') {
+                    grid.as_mut().unwrap().push(line);
+                    continue;
+                }
+
+                finish_grid(&mut grid, &mut rotations[current_piece.unwrap()], line_number)?;
+            }
+
+            let mut tokens = line.split_whitespace();
+            let command = tokens.next().unwrap();
+            let args: Vec<&str> = tokens.collect();
+
+            match command {
+                "name" => name = args.join(" "),
+
+                "piece" => {
+                    let piece_name = match args.as_slice() {
+                        &[piece_name] => piece_name,
+                        _ => return Err(error("piece must be: name")),
+                    };
+
+                    // Pieces defined again keep adding to the previous definition
+                    if let Some(piece) = piece_names.iter().position(|name| name == piece_name) {
+                        current_piece = Some(piece);
+                        continue;
+                    }
+
+                    let piece_type = match piece_name {
+                        "S" => PieceVariant::S,
+                        "Z" => PieceVariant::Z,
+                        "J" => PieceVariant::J,
+                        "L" => PieceVariant::L,
+                        "O" => PieceVariant::O,
+                        "I" => PieceVariant::I,
+                        "T" => PieceVariant::T,
+                        _ => {
+                            if custom_pieces_count == u8::MAX { return Err(error("too many pieces")); }
+                            custom_pieces_count += 1;
+                            PieceVariant::Custom(custom_pieces_count - 1)
+                        }
+                    };
+
+                    current_piece = Some(piece_set.len());
+                    piece_names.push(piece_name.to_string());
+                    piece_set.push(piece_type);
+                    colors.push(None);
+                    spawn_offsets.push(Vec2i::new());
+                    rotations.push(Vec::new());
+                    kicks.push(Default::default());
+                }
+
+                "color" => {
+                    let piece = current_piece.ok_or_else(|| error("color outside of a piece"))?;
+                    let values = parse_numbers::<f32>(&args).ok_or_else(|| error("invalid color"))?;
+                    if values.len() != 3 { return Err(error("color must be: r g b")); }
+                    colors[piece] = Some(Color { r: values[0], g: values[1], b: values[2], a: 1.0 });
+                }
+
+                "spawn" => {
+                    let piece = current_piece.ok_or_else(|| error("spawn outside of a piece"))?;
+                    let values = parse_numbers::<i32>(&args).ok_or_else(|| error("invalid spawn offset"))?;
+                    if values.len() != 2 { return Err(error("spawn must be: x y")); }
+                    spawn_offsets[piece] = Vec2i { x: values[0], y: values[1] };
+                }
+
+                "rotation" => {
+                    if current_piece.is_none() { return Err(error("rotation outside of a piece")); }
+                    grid = Some(Vec::new());
+                }
+
+                "kicks" => {
+                    if args.len() < 3 { return Err(error("kicks must be: rotation direction tests...")); }
+
+                    let rots = match args[0] {
+                        "*" => 0..4,
+                        rot => {
+                            let rot = rot.parse::<usize>().ok().filter(|&rot| rot < 4)
+                                .ok_or_else(|| error("invalid kick rotation"))?;
+                            rot..rot+1
+                        }
+                    };
+
+                    let dir = match args[1] {
+                        "cw"  => 0,
+                        "ccw" => 1,
+                        _ => return Err(error("kick direction must be cw or ccw")),
+                    };
+
+                    let mut tests = Vec::new();
+                    for test in &args[2..] {
+                        let values = parse_numbers::<i32>(&test.split(',').collect::<Vec<_>>())
+                            .filter(|values| values.len() == 2)
+                            .ok_or_else(|| error("invalid kick test"))?;
+                        tests.push(Vec2i { x: values[0], y: values[1] });
+                    }
+
+                    for rot in rots {
+                        match current_piece {
+                            Some(piece) => kicks[piece][rot][dir] = Some(tests.clone()),
+                            None => default_kicks[rot][dir] = Some(tests.clone()),
+                        }
+                    }
+                }
+
+                _ => return Err(error("unknown command")),
+            }
+        }
+
+        if let Some(piece) = current_piece {
+            finish_grid(&mut grid, &mut rotations[piece], line_number)?;
+        }
+
+        if piece_set.is_empty() {
+            return Err(CustomRotationSystemError::Parse {
+                line: 0,
+                message: "missing pieces".to_string(),
+            });
+        }
+
+        // Build piece data
+        let mut pieces = Vec::with_capacity(piece_set.len());
+        for (index, piece_rotations) in rotations.iter().enumerate() {
+            if piece_rotations.is_empty() {
+                return Err(CustomRotationSystemError::Parse {
+                    line: 0,
+                    message: format!("missing rotations for piece {}", piece_names[index]),
+                });
+            }
+
+            let is_tetromino = !matches!(piece_set[index], PieceVariant::Custom(_));
+            if is_tetromino && piece_rotations.iter().any(|rotation| rotation.len() != 4) {
+                return Err(CustomRotationSystemError::Parse {
+                    line: 0,
+                    message: format!("rotations of piece {} must have 4 blocks", piece_names[index]),
+                });
+            }
+
+            // Rotation systems are never unloaded, so the blocks can be leaked to share the same
+            // static piece data with the built-in rotation systems
+            let mut blocks: [&'static [Vec2i]; 4] = [&[]; 4];
+            for rot in 0..4 {
+                let rotation = &piece_rotations[rot % piece_rotations.len()];
+                blocks[rot] = Box::leak(rotation.clone().into_boxed_slice());
+            }
+
+            // Fill the kick tests. Without tests the piece only rotates in place
+            let mut piece_kicks: [[Vec<Vec2i>; 2]; 4] = Default::default();
+            for rot in 0..4 {
+                for dir in 0..2 {
+                    piece_kicks[rot][dir] = kicks[index][rot][dir].clone()
+                        .or_else(|| default_kicks[rot][dir].clone())
+                        .unwrap_or_else(|| vec![Vec2i::new()]);
+                }
+            }
+
+            pieces.push(CustomPiece {
+                data: PieceData::new(blocks, colors[index].unwrap_or(WHITE)),
+                spawn_offset: spawn_offsets[index],
+                kicks: piece_kicks,
+            });
+        }
+
+        Ok(Self {
+            name,
+            hash: hash.finish(),
+            piece_set,
+            pieces,
+        })
+    }
+}
+
+fn parse_grid(rows: &[&str]) -> Vec<Vec2i> {
+    let height = rows.len() as i32;
+    let mut blocks = Vec::new();
+    for (y, row) in rows.iter().enumerate() {
+        for (x, c) in row.chars().enumerate() {
+            if c == '#' {
+                blocks.push(Vec2i { x: x as i32, y: height - 1 - y as i32 });
+            }
+        }
+    }
+    blocks
+}
+
+fn parse_numbers<T: std::str::FromStr>(args: &[&str]) -> Option<Vec<T>> {
+    args.iter().map(|arg| arg.parse::<T>().ok()).collect()
+}
+
+// Loaded rotation systems live until the end of the program, so pieces can keep referencing them
+// by index (RotationSystem::Custom) and we can keep handing out static piece data. The index
+// depends on the load order, so other machines identify them by hash instead.
+static CUSTOM_ROTATION_SYSTEMS: RwLock<Vec<&'static CustomRotationSystem>> = RwLock::new(Vec::new());
+
+pub fn register_custom_rotation_system(rotation_system: CustomRotationSystem) -> RotationSystem {
+    let mut rotation_systems = CUSTOM_ROTATION_SYSTEMS.write().unwrap();
+    rotation_systems.push(Box::leak(Box::new(rotation_system)));
+    RotationSystem::Custom((rotation_systems.len() - 1) as u8)
+}
+
+pub fn get_custom_rotation_system(index: u8) -> &'static CustomRotationSystem {
+    CUSTOM_ROTATION_SYSTEMS.read().unwrap()[index as usize]
+}
+
+pub fn find_custom_rotation_system(hash: u32) -> Option<RotationSystem> {
+    CUSTOM_ROTATION_SYSTEMS.read().unwrap().iter()
+        .position(|rotation_system| rotation_system.hash == hash)
+        .map(|index| RotationSystem::Custom(index as u8))
+}
+
+pub fn get_custom_rotation_systems() -> Vec<RotationSystem> {
+    let count = CUSTOM_ROTATION_SYSTEMS.read().unwrap().len();
+    (0..count).map(|index| RotationSystem::Custom(index as u8)).collect()
+}
+
+// Loads and registers all rotation systems (.txt files) in the directory
+pub fn load_custom_rotation_systems<P: AsRef<Path>>(path: P) {
+    let entries = match std::fs::read_dir(path) {
+        Ok(entries) => entries,
+        Err(_) => return,
+    };
+
+    let mut paths: Vec<_> = entries
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| path.extension().map_or(false, |extension| extension == "txt"))
+        .collect();
+    paths.sort();
+
+    for path in paths {
+        // @TODO logging
+        match CustomRotationSystem::load_from_file(&path) {
+            Ok(rotation_system) => {
+                println!("Loaded rotation system: {}", path.display());
+                register_custom_rotation_system(rotation_system);
+            }
+            Err(err) => println!("Failed to load rotation system {}: {:?}", path.display(), err),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_custom_rotation_system() {
+        let mut buffer = String::from("name TEST\nkicks * cw 0,0 1,0\n");
+        for piece in ["S", "Z", "J", "L", "O", "I"] {
+            buffer += &format!("piece {}\ncolor 1 0 0\nrotation\n##\n##\n", piece);
+        }
+        buffer += "piece T\nspawn 1 -1\nkicks 0 ccw 0,0 -1,0 0,1\nrotation\n.#.\n###\nrotation\n#.\n##\n#.\n";
+        buffer += "piece X5\nrotation\n.#.\n###\n.#.\npiece I1\nrotation\n#\n";
+
+        let rotation_system = CustomRotationSystem::parse(&buffer).unwrap();
+        assert_eq!(rotation_system.name, "TEST");
+        assert_eq!(rotation_system.piece_set().len(), 9);
+        assert_eq!(rotation_system.piece_set()[8], PieceVariant::Custom(1));
+        assert_eq!(rotation_system.piece_data(PieceVariant::Custom(0)).blocks[3].len(), 5);
+        assert_eq!(rotation_system.piece_data(PieceVariant::Custom(1)).blocks[0].len(), 1);
+        assert_eq!(rotation_system.spawn_offset(PieceVariant::T), Vec2i { x: 1, y: -1 });
+
+        let t = rotation_system.piece_data(PieceVariant::T);
+        assert_eq!(t.blocks[0][0], Vec2i { x: 1, y: 1 });
+        assert_eq!(t.blocks[2], t.blocks[0]);
+        assert_eq!((t.min_y[1], t.max_y[1]), (0, 2));
+
+        assert_eq!(rotation_system.rotation_tests(PieceVariant::O, 2, true).len(), 2);
+        assert_eq!(rotation_system.rotation_tests(PieceVariant::O, 2, false), &[Vec2i::new()]);
+        assert_eq!(rotation_system.rotation_tests(PieceVariant::T, 0, false).len(), 3);
+
+        assert!(CustomRotationSystem::parse("piece X\n").is_err());
+        assert!(CustomRotationSystem::parse("piece T\nrotation\n.#.\n###\n.#.\n").is_err());
+        assert!(CustomRotationSystem::parse("piece I9\nrotation\n#\n#\n#\n#\n#\n#\n#\n#\n#\n").is_err());
+        assert!(CustomRotationSystem::load_from_file("assets/rotation_systems/simple.txt").is_ok());
+
+        let pentominoes = CustomRotationSystem::load_from_file("assets/rotation_systems/pentominoes.txt").unwrap();
+        assert_eq!(pentominoes.piece_set().len(), 18);
+    }
+
+    #[test]
+    fn test_find_custom_rotation_system() {
+        let buffer = "name HASH TEST\npiece I1\nrotation\n#\n";
+        let rotation_system = register_custom_rotation_system(CustomRotationSystem::parse(buffer).unwrap());
+
+        // Same commands with other line endings and comments
+        let hash = CustomRotationSystem::parse("// I1\r\nname HASH TEST\r\npiece I1\r\nrotation\r\n#\r\n")
+            .unwrap()
+            .hash;
+        assert_eq!(find_custom_rotation_system(hash), Some(rotation_system));
+        assert_eq!(find_custom_rotation_system(hash ^ 1), None);
+    }
+}
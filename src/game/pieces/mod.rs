@@ -3,6 +3,7 @@ use crate::linalg::Vec2i;
 
 use super::rules::RotationSystem;
 
+mod custom;
 mod dtet;
 mod nrsl;
 mod nrsr;
@@ -10,6 +11,7 @@ mod original;
 mod sega;
 mod srs;
 
+pub use custom::*;
 use dtet::*;
 use nrsl::*;
 use nrsr::*;
@@ -17,8 +19,14 @@ use original::*;
 use sega::*;
 use srs::*;
 
-#[derive(Copy, Clone, Debug, ImDraw)]
-pub enum PieceVariant { S, Z, J, L, O, I, T }
+// Maximum amount of rows a piece can span, so a single piece can't clear more lines than this
+pub const MAX_PIECE_HEIGHT: usize = 8;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, ImDraw)]
+pub enum PieceVariant {
+    S, Z, J, L, O, I, T,
+    Custom(u8), // Other pieces of custom piece sets (pentominoes, ...), see custom.rs
+}
 
 #[derive(Copy, Clone, Debug, ImDraw)]
 pub struct Piece {
@@ -32,13 +40,13 @@ impl Piece {
         //assert!(rot >= 0 && rot < 4);
         // @TODO: should this be fixed?
         let rot = (((self.rot % 4) + 4) % 4) as usize;
-        &get_piece_data(self.variant, self.rotation_system).blocks[rot]
+        get_piece_data(self.variant, self.rotation_system).blocks[rot]
     }
 
     pub fn blocks_with_rot(&self, rot: i32) -> &'static [Vec2i] {
         //assert!(rot >= 0 && rot < 4);
         let rot = (((rot % 4) + 4) % 4) as usize;
-        &get_piece_data(self.variant, self.rotation_system).blocks[rot]
+        get_piece_data(self.variant, self.rotation_system).blocks[rot]
     }
 
     pub fn min_max_x(self) -> (i8, i8) {
@@ -57,6 +65,14 @@ impl Piece {
     pub fn color(self) -> Color {
         get_piece_data(self.variant, self.rotation_system).color
     }
+
+    // Offset from the default spawn position
+    pub fn spawn_offset(self) -> Vec2i {
+        match self.rotation_system {
+            RotationSystem::Custom(index) => get_custom_rotation_system(index).spawn_offset(self.variant),
+            _ => Vec2i::new(),
+        }
+    }
 }
 
 #[inline(always)]
@@ -64,6 +80,20 @@ pub fn get_piece_variant_color(piece_type: PieceVariant, rotation_system: Rotati
     get_piece_data(piece_type, rotation_system).color
 }
 
+// Pieces dealt by the randomizers. Custom rotation systems can define other piece sets
+#[inline(always)]
+pub fn get_piece_set(rotation_system: RotationSystem) -> &'static [PieceVariant] {
+    match rotation_system {
+        RotationSystem::Custom(index) => get_custom_rotation_system(index).piece_set(),
+        _ => &PIECES,
+    }
+}
+
+// Checks if the piece set has exactly the 7 tetrominoes, in any order
+pub fn is_tetromino_set(pieces: &[PieceVariant]) -> bool {
+    pieces.len() == PIECES.len() && PIECES.iter().all(|piece| pieces.contains(piece))
+}
+
 pub const PIECES : [PieceVariant; 7] = [
     PieceVariant::S,
     PieceVariant::Z,
@@ -83,6 +113,7 @@ fn piece_to_index(piece_type: PieceVariant) -> usize {
         PieceVariant::O => 4,
         PieceVariant::I => 5,
         PieceVariant::T => 6,
+        PieceVariant::Custom(_) => unreachable!("custom pieces only exist in custom rotation systems"),
     }
 }
 
@@ -99,6 +130,9 @@ fn get_piece_data(
         RotationSystem::ARS      => &PIECES_SEGA, // ARS has same piece rotations as Sega
         RotationSystem::SRS      => &PIECES_SRS,
         RotationSystem::DTET     => &PIECES_DTET,
+        RotationSystem::Custom(index) => {
+            return get_custom_rotation_system(index).piece_data(piece_type);
+        }
     };
 
     &(orientation_data.0)[piece_to_index(piece_type)]
@@ -108,7 +142,7 @@ fn get_piece_data(
 // NIT: This could be a very packed struct, but we only have 7 different types and this won't be
 // sent over wire or anything, so having an unpacked struct is fine
 struct PieceData {
-    blocks: [[Vec2i; 4]; 4],
+    blocks: [&'static [Vec2i]; 4], // Custom pieces can have any amount of blocks
     min_x: [i8; 4],
     max_x: [i8; 4],
     min_y: [i8; 4],
@@ -116,4 +150,24 @@ struct PieceData {
     color: Color,
 }
 
+impl PieceData {
+    fn new(blocks: [&'static [Vec2i]; 4], color: Color) -> Self {
+        let mut min_x = [i8::MAX; 4];
+        let mut max_x = [i8::MIN; 4];
+        let mut min_y = [i8::MAX; 4];
+        let mut max_y = [i8::MIN; 4];
+
+        for rot in 0..4 {
+            for block in blocks[rot].iter() {
+                min_x[rot] = min_x[rot].min(block.x as i8);
+                max_x[rot] = max_x[rot].max(block.x as i8);
+                min_y[rot] = min_y[rot].min(block.y as i8);
+                max_y[rot] = max_y[rot].max(block.y as i8);
+            }
+        }
+
+        Self { blocks, min_x, max_x, min_y, max_y, color }
+    }
+}
+
 struct PieceOrientationData([PieceData; 7]);
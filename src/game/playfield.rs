@@ -2,12 +2,11 @@ use crate::app::ImDraw;
 use crate::linalg::Vec2i;
 use super::pieces::PieceVariant;
 
-pub const PLAYFIELD_VISIBLE_HEIGHT : u8 = 20;
-
 #[derive(Copy, Clone, Debug)]
 pub enum BlockType {
     Empty,
     Piece(PieceVariant),
+    Garbage,
 }
 
 #[derive(Clone, Debug)]
@@ -32,18 +31,18 @@ impl Playfield {
         }
     }
 
+    // Returns None for empty blocks. Blocks outside of the grid are always occupied
     // @TODO use row, col, instead of x, y
-    pub fn block(&self, x: i32, y: i32) -> Option<PieceVariant> {
-        if x < 0 || x >= self.grid_size.x { return Some(PieceVariant::S); }
-        if y < 0 || y >= self.grid_size.y { return Some(PieceVariant::S); }
+    pub fn block(&self, x: i32, y: i32) -> Option<BlockType> {
+        if x < 0 || x >= self.grid_size.x { return Some(BlockType::Garbage); }
+        if y < 0 || y >= self.grid_size.y { return Some(BlockType::Garbage); }
 
         let pos = y * self.grid_size.x + x;
         let pos = pos as usize;
 
-        if let BlockType::Piece(piece_type) = self.blocks[pos] {
-            Some(piece_type)
-        } else {
-            None
+        match self.blocks[pos] {
+            BlockType::Empty => None,
+            block_type => Some(block_type),
         }
     }
 
@@ -67,28 +66,35 @@ impl Playfield {
         self.blocks[pos] = BlockType::Empty;
     }
 
-    pub fn get_lines_to_clear(&self) -> (u8, [u8; 4]) {
+    pub fn get_lines_to_clear(&self) -> Vec<u8> {
         // @Maybe refactor to use Vec::chunks
         // self.blocks.chunks(self.grid_size.x).iter()
 
-        let mut total_lines_to_clear = 0u8;
-        let mut lines_to_clear = [0u8; 4];
-
         (0..self.grid_size.y)
-            .for_each(|i| {
+            .filter(|&i| {
                 let line_start = (i * self.grid_size.x) as usize;
                 let line_end   = ((i+1) * self.grid_size.x) as usize;
                 let cnt = self.blocks[line_start..line_end]
                     .iter()
-                    .fold(0, |acc, &x| if let BlockType::Piece(_) = x { acc + 1 } else { acc });
+                    .fold(0, |acc, &x| if let BlockType::Empty = x { acc } else { acc + 1 });
 
-                if cnt == self.grid_size.x {
-                    lines_to_clear[total_lines_to_clear as usize] = i as u8;
-                    total_lines_to_clear += 1;
-                }
-            });
+                cnt == self.grid_size.x
+            })
+            .map(|i| i as u8)
+            .collect()
+    }
 
-        (total_lines_to_clear, lines_to_clear)
+    // Checks if the playfield will be empty after clearing the lines (aka all clear)
+    pub fn is_perfect_clear(&self, lines_to_clear: &[u8]) -> bool {
+        (0..self.grid_size.y)
+            .filter(|&i| !lines_to_clear.contains(&(i as u8)))
+            .all(|i| {
+                let line_start = (i * self.grid_size.x) as usize;
+                let line_end   = ((i+1) * self.grid_size.x) as usize;
+                self.blocks[line_start..line_end]
+                    .iter()
+                    .all(|&x| if let BlockType::Empty = x { true } else { false })
+            })
     }
 
     pub fn try_clear_lines_naive(&mut self) -> bool {
@@ -98,7 +104,7 @@ impl Playfield {
             let line_end   = ((current_line + 1) * self.grid_size.x) as usize;
             let cnt = self.blocks[line_start..line_end]
                 .iter()
-                .fold(0, |acc, &x| if let BlockType::Piece(_) = x { acc + 1 } else { acc });
+                .fold(0, |acc, &x| if let BlockType::Empty = x { acc } else { acc + 1 });
 
             if cnt != self.grid_size.x {
                 if last_free_line != current_line {
@@ -127,6 +133,156 @@ impl Playfield {
 
         last_free_line != self.grid_size.y
     }
+
+    // Pushes the stack up with one garbage row for each hole column, in order (the last hole is
+    // the bottom row). Blocks pushed above the top of the grid are lost, so check for garbage out
+    // before calling this
+    pub fn add_garbage_rows(&mut self, holes: &[u8]) {
+        let rows = holes.len().min(self.grid_size.y as usize);
+        let width = self.grid_size.x as usize;
+
+        self.blocks.rotate_right(rows * width);
+
+        for (row, &hole) in holes[holes.len() - rows..].iter().rev().enumerate() {
+            let line_start = row * width;
+            self.blocks[line_start..line_start + width].fill(BlockType::Garbage);
+            self.blocks[line_start + (hole as usize).min(width - 1)] = BlockType::Empty;
+        }
+    }
+}
+
+// Sticky and cascade line clears
+impl Playfield {
+    fn is_line_full(&self, line: i32) -> bool {
+        let line_start = (line * self.grid_size.x) as usize;
+        let line_end   = ((line + 1) * self.grid_size.x) as usize;
+        self.blocks[line_start..line_end]
+            .iter()
+            .all(|&x| if let BlockType::Empty = x { false } else { true })
+    }
+
+    // Removes the full lines without moving the blocks above them. Returns the amount of lines
+    // removed
+    fn remove_full_lines(&mut self) -> u8 {
+        let mut removed_lines = 0;
+        for line in 0..self.grid_size.y {
+            if self.is_line_full(line) {
+                let line_start = (line * self.grid_size.x) as usize;
+                let line_end   = ((line + 1) * self.grid_size.x) as usize;
+                self.blocks[line_start..line_end].fill(BlockType::Empty);
+                removed_lines += 1;
+            }
+        }
+        removed_lines
+    }
+
+    // Sticky gravity: each group of connected blocks falls as a unit until it lands on the floor
+    // or on another group. Returns if any block has moved
+    pub fn apply_sticky_gravity(&mut self) -> bool {
+        let width = self.grid_size.x;
+        let height = self.grid_size.y;
+        let index = |x: i32, y: i32| (y * width + x) as usize;
+
+        // Find all connected groups (orthogonally adjacent blocks)
+        let mut groups: Vec<Vec<Vec2i>> = Vec::new();
+        let mut group_ids: Vec<Option<usize>> = vec![None; self.blocks.len()];
+
+        for y in 0..height {
+            for x in 0..width {
+                if let BlockType::Empty = self.blocks[index(x, y)] { continue; }
+                if group_ids[index(x, y)].is_some() { continue; }
+
+                let group_id = groups.len();
+                let mut group = Vec::new();
+                let mut stack = vec![Vec2i { x, y }];
+                group_ids[index(x, y)] = Some(group_id);
+
+                while let Some(pos) = stack.pop() {
+                    group.push(pos);
+
+                    let neighbours = [
+                        Vec2i { x: pos.x - 1, y: pos.y },
+                        Vec2i { x: pos.x + 1, y: pos.y },
+                        Vec2i { x: pos.x, y: pos.y - 1 },
+                        Vec2i { x: pos.x, y: pos.y + 1 },
+                    ];
+
+                    for n in neighbours {
+                        if n.x < 0 || n.x >= width || n.y < 0 || n.y >= height { continue; }
+                        if let BlockType::Empty = self.blocks[index(n.x, n.y)] { continue; }
+                        if group_ids[index(n.x, n.y)].is_some() { continue; }
+
+                        group_ids[index(n.x, n.y)] = Some(group_id);
+                        stack.push(n);
+                    }
+                }
+
+                groups.push(group);
+            }
+        }
+
+        // Groups are found from the bottom up, so lower groups move first
+        let mut has_moved = false;
+        loop {
+            let mut has_moved_this_step = false;
+
+            for (group_id, group) in groups.iter_mut().enumerate() {
+                let can_fall = group.iter().all(|pos| {
+                    pos.y > 0 && match group_ids[index(pos.x, pos.y - 1)] {
+                        None => true,
+                        Some(id) => id == group_id,
+                    }
+                });
+
+                if !can_fall { continue; }
+
+                let block_types: Vec<BlockType> = group.iter()
+                    .map(|pos| self.blocks[index(pos.x, pos.y)])
+                    .collect();
+
+                for pos in group.iter() {
+                    self.blocks[index(pos.x, pos.y)] = BlockType::Empty;
+                    group_ids[index(pos.x, pos.y)] = None;
+                }
+
+                for (pos, block_type) in group.iter_mut().zip(block_types) {
+                    pos.y -= 1;
+                    self.blocks[index(pos.x, pos.y)] = block_type;
+                    group_ids[index(pos.x, pos.y)] = Some(group_id);
+                }
+
+                has_moved_this_step = true;
+            }
+
+            if !has_moved_this_step { break; }
+            has_moved = true;
+        }
+
+        has_moved
+    }
+
+    pub fn try_clear_lines_sticky(&mut self) -> bool {
+        if self.remove_full_lines() == 0 { return false; }
+        self.apply_sticky_gravity();
+        true
+    }
+
+    // Cascade: sticky gravity is applied until there are no more lines to clear. Returns the amount
+    // of lines cleared in each chain (line clears after the first one)
+    pub fn try_clear_lines_cascade(&mut self) -> Option<Vec<u8>> {
+        if self.remove_full_lines() == 0 { return None; }
+
+        let mut chains = Vec::new();
+        loop {
+            self.apply_sticky_gravity();
+
+            let removed_lines = self.remove_full_lines();
+            if removed_lines == 0 { break; }
+            chains.push(removed_lines);
+        }
+
+        Some(chains)
+    }
 }
 
 impl ImDraw for Playfield {
@@ -157,3 +313,60 @@ impl ImDraw for Playfield {
         });
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // row 2: . . . X
+    // row 1: X X X X
+    // row 0: X X X .
+    fn new_test_playfield() -> Playfield {
+        let mut playfield = Playfield::new(Vec2i { x: 4, y: 6 }, 6);
+        for x in 0..3 { playfield.set_block(x, 0, PieceVariant::O); }
+        for x in 0..4 { playfield.set_block(x, 1, PieceVariant::I); }
+        playfield.set_block(3, 2, PieceVariant::T);
+        playfield
+    }
+
+    #[test]
+    fn test_clear_lines_naive() {
+        let mut playfield = new_test_playfield();
+        assert!(playfield.try_clear_lines_naive());
+        assert!(playfield.block(3, 1).is_some());
+        assert!(playfield.block(3, 0).is_none());
+    }
+
+    #[test]
+    fn test_clear_lines_sticky() {
+        let mut playfield = new_test_playfield();
+        assert!(playfield.try_clear_lines_sticky());
+        assert!(playfield.block(3, 1).is_none());
+        assert!(playfield.is_line_full(0));
+    }
+
+    #[test]
+    fn test_clear_lines_cascade() {
+        let mut playfield = new_test_playfield();
+        assert_eq!(playfield.try_clear_lines_cascade(), Some(vec![1]));
+        assert!(playfield.is_perfect_clear(&[]));
+
+        assert_eq!(playfield.try_clear_lines_cascade(), None);
+    }
+
+    #[test]
+    fn test_add_garbage_rows() {
+        let mut playfield = new_test_playfield();
+        playfield.add_garbage_rows(&[2, 0]);
+
+        assert!(playfield.block(0, 0).is_none());
+        assert!(matches!(playfield.block(1, 0), Some(BlockType::Garbage)));
+        assert!(playfield.block(2, 1).is_none());
+        assert!(matches!(playfield.block(0, 2), Some(BlockType::Piece(PieceVariant::O))));
+        assert!(matches!(playfield.block(3, 4), Some(BlockType::Piece(PieceVariant::T))));
+
+        // garbage rows can be cleared
+        playfield.set_block(2, 1, PieceVariant::I);
+        assert_eq!(playfield.get_lines_to_clear(), vec![1, 3]);
+    }
+}
@@ -0,0 +1,156 @@
+// @TODO abstract PCG
+use rand_core::RngCore;
+
+use crate::app::ImDraw;
+use crate::game::pieces::{
+    PieceVariant,
+    PIECES
+};
+use super::RandomizerTrait;
+
+// https://tetris.fandom.com/wiki/Random_Generator
+// Deals all pieces of a shuffled bag before shuffling a new one. The bag has a number of copies of
+// all pieces of the piece set plus some extra random pieces:
+//   7-bag:   1 copy,  0 extra
+//   14-bag:  2 copies, 0 extra
+//   7+1 bag: 1 copy,  1 extra
+#[derive(Clone, Debug, ImDraw)]
+pub struct RandomizerBag {
+    rng: rand_pcg::Pcg32,
+    seed: u64,
+    pieces: Vec<PieceVariant>,
+    copies: u8,
+    extra_pieces: u8,
+    sequence: Vec<u8>, // indices of pieces
+    index: usize,
+
+    // TGM ACE: the first bag doesn't start with S, Z or O
+    has_first_piece_rule: bool,
+    is_first_bag: bool,
+}
+
+impl RandomizerBag {
+    pub fn new(seed: u64, pieces: Vec<PieceVariant>, copies: u8, extra_pieces: u8) -> Self {
+        assert!(pieces.len() > 0 && pieces.len() <= u8::MAX as usize);
+        let bag_size = pieces.len() * copies as usize + extra_pieces as usize;
+        assert!(bag_size > 0);
+
+        let piece_count = pieces.len();
+        Self {
+            rng: rand_pcg::Pcg32::new(seed, 0xa02bdbf7bb3c0a7),
+            seed,
+            pieces,
+            copies,
+            extra_pieces,
+            sequence: (0..bag_size).map(|i| (i % piece_count) as u8).collect(),
+            index: bag_size,
+            has_first_piece_rule: false,
+            is_first_bag: true,
+        }
+    }
+
+    pub fn new_7bag(seed: u64) -> Self {
+        Self::new(seed, PIECES.to_vec(), 1, 0)
+    }
+
+    pub fn new_tgm_ace(seed: u64) -> Self {
+        Self {
+            has_first_piece_rule: true,
+            ..Self::new_7bag(seed)
+        }
+    }
+
+    // The previous bag is shuffled again, so the 7-bag deals the same sequences as before the
+    // generic bag. Only the extra pieces need to be picked again
+    fn fill_bag(&mut self) {
+        if self.extra_pieces > 0 {
+            let piece_count = self.pieces.len();
+            let copies = piece_count * self.copies as usize;
+            for i in 0..copies {
+                self.sequence[i] = (i % piece_count) as u8;
+            }
+            for i in copies..self.sequence.len() {
+                self.sequence[i] = (self.rng.next_u32() % piece_count as u32) as u8;
+            }
+        }
+
+        let bag_size = self.sequence.len();
+        for i in 0..bag_size {
+            let j = (self.rng.next_u32() % bag_size as u32) as usize;
+            self.sequence.swap(i, j);
+        }
+    }
+}
+
+impl RandomizerTrait for RandomizerBag {
+    fn reset(&mut self) {
+        self.index = self.sequence.len();
+        self.is_first_bag = true;
+    }
+
+    fn next_piece(&mut self) -> PieceVariant {
+        if self.index == self.sequence.len() {
+            self.fill_bag();
+            self.index = 0;
+
+            if self.has_first_piece_rule && self.is_first_bag {
+                // Swap the first piece with the first one that isn't S, Z or O
+                let pieces = &self.pieces;
+                let is_szo = |piece_id: u8| matches!(
+                    pieces[piece_id as usize],
+                    PieceVariant::S | PieceVariant::Z | PieceVariant::O
+                );
+                if let Some(first) = self.sequence.iter().position(|&piece_id| !is_szo(piece_id)) {
+                    self.sequence.swap(0, first);
+                }
+            }
+            self.is_first_bag = false;
+        }
+
+        let piece_id = self.sequence[self.index] as usize;
+        self.index += 1;
+        self.pieces[piece_id]
+    }
+
+    fn seed(&self) -> u64 { self.seed }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bag_randomizer() {
+        // Every bag has all pieces the same amount of times, plus the extra ones
+        for (copies, extra_pieces) in [(1, 0), (2, 0), (1, 1), (1, 3)] {
+            let mut randomizer = RandomizerBag::new(3, PIECES.to_vec(), copies, extra_pieces);
+            let bag_size = 7 * copies as usize + extra_pieces as usize;
+
+            for _ in 0..10 {
+                let bag: Vec<PieceVariant> = (0..bag_size).map(|_| randomizer.next_piece()).collect();
+                for piece in PIECES.iter() {
+                    let count = bag.iter().filter(|&p| p == piece).count();
+                    assert!(count >= copies as usize && count <= (copies + extra_pieces) as usize);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_7bag_sequence() {
+        // Same sequence as the old Randomizer7Bag, so the seeds keep dealing the same pieces
+        let mut rng = rand_pcg::Pcg32::new(42, 0xa02bdbf7bb3c0a7);
+        let mut sequence = [0, 1, 2, 3, 4, 5, 6];
+        let mut randomizer = RandomizerBag::new_7bag(42);
+
+        for _ in 0..10 {
+            for i in 0..7 {
+                let j = (rng.next_u32() % 7) as usize;
+                sequence.swap(i, j);
+            }
+            for &piece_id in sequence.iter() {
+                assert_eq!(randomizer.next_piece(), PIECES[piece_id]);
+            }
+        }
+    }
+}
@@ -2,20 +2,23 @@
 use rand_core::RngCore;
 
 use crate::app::ImDraw;
-use crate::game::pieces::{PIECES, PieceVariant};
+use crate::game::pieces::PieceVariant;
 use super::RandomizerTrait;
 
 #[derive(Clone, Debug, ImDraw)]
 pub struct RandomizerFullRandom {
     rng: rand_pcg::Pcg32,
     seed: u64,
+    pieces: Vec<PieceVariant>,
 }
 
 impl RandomizerFullRandom {
-    pub fn new(seed: u64) -> Self {
+    pub fn new(seed: u64, pieces: Vec<PieceVariant>) -> Self {
+        assert!(pieces.len() > 0);
         Self {
             rng: rand_pcg::Pcg32::new(seed, 0xa02bdbf7bb3c0a7),
             seed,
+            pieces,
         }
     }
 }
@@ -24,8 +27,8 @@ impl RandomizerTrait for RandomizerFullRandom {
     fn reset(&mut self) {}
 
     fn next_piece(&mut self) -> PieceVariant {
-        let piece_id = (self.rng.next_u32() % 7) as usize;
-        PIECES[piece_id]
+        let piece_id = (self.rng.next_u32() % self.pieces.len() as u32) as usize;
+        self.pieces[piece_id]
     }
 
     fn seed(&self) -> u64 { self.seed }
@@ -1,43 +1,86 @@
 use enum_dispatch::*;
 use crate::app::ImDraw;
-use super::pieces::PieceVariant;
+use super::pieces::{PieceVariant, is_tetromino_set};
 
+mod bag;
 mod defined_sequence;
 mod fullrandom;
-mod random7bag;
+mod nes;
 mod sequential;
+mod tgm;
+mod tgm3;
 
+pub use bag::RandomizerBag;
 pub use defined_sequence::RandomizerDefinedSequence;
 pub use fullrandom::RandomizerFullRandom;
-pub use random7bag::Randomizer7Bag;
+pub use nes::RandomizerNES;
 pub use sequential::RandomizerSequential;
+pub use tgm::RandomizerTGM;
+pub use tgm3::RandomizerTGM3;
 
 #[derive(Copy, Clone, Debug, ImDraw)]
 pub enum RandomizerType {
     Sequential,
     FullRandom,
+    NES, // 16-bit LFSR, rerolls once on repeats
 
     // https://tetris.fandom.com/wiki/Random_Generator
     // @TODO @Rename: rename this! it's not matching all other randomizers
     Random7Bag, // Random Generator, with a bag of all 7 pieces
+    Bag { copies: u8, extra_pieces: u8 }, // 14-bag, 7+1 bag, etc. (see RandomizerBag)
     TGMACE,     // Random Generator + not dealing SZO initially
 
     // https://tetris.fandom.com/wiki/TGM_randomizer
-    TGM1, // 4 history, 4 tries, 2 variants: history with ZZZZ or ZZSS
-    TGM,  // 4 history, 6 tries
-    //TGMCustom { history: u8, retries: u8, },
-
-    // @TODO TGM3 35-piece bag?
+    TGM1, // 4 history, 4 tries, history starts with ZZZZ
+    TGM,  // 4 history, 6 tries, history starts with ZSSZ
+    TGMCustom { history: u8, retries: u8, },
+    TGM3, // 35-piece pool with drought, 4 history, 6 tries, history starts with SZSZ
 }
 
 impl RandomizerType {
-    pub fn build(self, seed: u64) -> Randomizer {
-        match self {
-            RandomizerType::FullRandom => Randomizer::RandomizerFullRandom(RandomizerFullRandom::new(seed)),
-            RandomizerType::Sequential => Randomizer::RandomizerSequential(RandomizerSequential::new()),
-            RandomizerType::Random7Bag => Randomizer::Randomizer7Bag(Randomizer7Bag::new(seed)),
+    // Builds a randomizer dealing the pieces of the piece set (see pieces::get_piece_set)
+    pub fn build(self, seed: u64, pieces: &[PieceVariant]) -> Randomizer {
+        // Randomizers tied to the tetrominoes (TGM, NES) can't deal other piece sets, so they use a
+        // bag with the piece set instead
+        // @Maybe generalize TGM history randomizers to any piece set
+        let randomizer_type = match self {
+            RandomizerType::FullRandom
+            | RandomizerType::Sequential
+            | RandomizerType::Random7Bag
+            | RandomizerType::Bag { .. }
+            => self,
+
+            _ if is_tetromino_set(pieces) => self,
+            _ => RandomizerType::Random7Bag,
+        };
 
-            _ => unimplemented!("Randomizer type not yet supported"),
+        match randomizer_type {
+            RandomizerType::FullRandom => {
+                Randomizer::RandomizerFullRandom(RandomizerFullRandom::new(seed, pieces.to_vec()))
+            }
+            RandomizerType::Sequential => {
+                Randomizer::RandomizerSequential(RandomizerSequential::new(pieces.to_vec()))
+            }
+            RandomizerType::Random7Bag => {
+                Randomizer::RandomizerBag(RandomizerBag::new(seed, pieces.to_vec(), 1, 0))
+            }
+            RandomizerType::Bag { copies, extra_pieces } => {
+                // Empty bags are not allowed
+                let copies = if extra_pieces == 0 { copies.max(1) } else { copies };
+                Randomizer::RandomizerBag(RandomizerBag::new(seed, pieces.to_vec(), copies, extra_pieces))
+            }
+            RandomizerType::TGMACE     => Randomizer::RandomizerBag(RandomizerBag::new_tgm_ace(seed)),
+            RandomizerType::TGM1       => Randomizer::RandomizerTGM(RandomizerTGM::new_tgm1(seed)),
+            RandomizerType::TGM        => Randomizer::RandomizerTGM(RandomizerTGM::new_tgm2(seed)),
+            RandomizerType::TGMCustom { history, retries } => {
+                Randomizer::RandomizerTGM(RandomizerTGM::new(
+                    seed,
+                    tgm::tgm_initial_history(history as usize),
+                    retries.max(1)
+                ))
+            }
+            RandomizerType::TGM3       => Randomizer::RandomizerTGM3(RandomizerTGM3::new(seed)),
+            RandomizerType::NES        => Randomizer::RandomizerNES(RandomizerNES::new(seed)),
         }
     }
 }
@@ -48,6 +91,9 @@ pub trait RandomizerTrait {
     fn reset(&mut self);
     fn next_piece(&mut self) -> PieceVariant;
     fn seed(&self) -> u64;
+
+    // Elapsed game time, for the randomizers that depend on it (NES)
+    fn update(&mut self, _dt: u64) {}
 }
 
 #[enum_dispatch(RandomizerTrait)]
@@ -56,7 +102,21 @@ pub enum Randomizer {
     RandomizerDefinedSequence,
     RandomizerSequential,
     RandomizerFullRandom,
-    Randomizer7Bag,
+    RandomizerBag,
+    RandomizerTGM,
+    RandomizerTGM3,
+    RandomizerNES,
+}
+
+impl Randomizer {
+    // Generates the next pieces without changing the randomizer, so the upcoming pieces can be
+    // inspected beyond the next pieces preview
+    // @XXX the NES randomizer changes over time, so its peeked pieces are only right if they spawn
+    //      now
+    pub fn peek(&self, count: usize) -> Vec<PieceVariant> {
+        let mut randomizer = self.clone();
+        (0..count).map(|_| randomizer.next_piece()).collect()
+    }
 }
 
 impl_imdraw_todo!(rand_pcg::Pcg32);
@@ -0,0 +1,125 @@
+use crate::app::ImDraw;
+use crate::game::pieces::PieceVariant;
+use super::RandomizerTrait;
+
+// Power on value of the NES random number generator
+pub const NES_DEFAULT_SEED: u16 = 0x8988;
+
+// The NES runs at 60.0988 frames per second. The elapsed time is counted in integers, so the frames
+// don't drift over a long game
+const NES_FRAME_RATE: u64 = 600_988;        // frames per 10000 seconds
+const NES_FRAME_TIME: u64 = 10_000_000_000; // 10000 seconds in us
+
+// Pieces in the order of the NES spawn table, with their spawn orientation ids
+const NES_PIECES: [(PieceVariant, u8); 7] = [
+    (PieceVariant::T, 0x02),
+    (PieceVariant::J, 0x07),
+    (PieceVariant::Z, 0x08),
+    (PieceVariant::O, 0x0A),
+    (PieceVariant::S, 0x0B),
+    (PieceVariant::L, 0x0E),
+    (PieceVariant::I, 0x12),
+];
+
+// https://meatfighter.com/nintendotetrisai/#Picking_Tetriminos
+// 16-bit Fibonacci LFSR. A piece is picked from the high byte of the generator plus the spawn
+// count. If it's the same as the last piece (or an invalid index), the generator is advanced once
+// and the new piece is picked from it plus the last spawn id, and it's kept even if it repeats.
+// The generator is also advanced once every NES frame of game time (see update), so the pieces
+// depend on when they spawn, like in the original
+#[derive(Clone, Debug, ImDraw)]
+pub struct RandomizerNES {
+    seed: u64,
+    rng: u16,
+    spawn_count: u8,
+    spawn_id: u8,
+    frame_timer: u64, // elapsed time not advanced yet, in us * NES_FRAME_RATE
+}
+
+impl RandomizerNES {
+    pub fn new(seed: u64) -> Self {
+        let mut randomizer = Self {
+            seed,
+            rng: 0,
+            spawn_count: 0,
+            spawn_id: 0,
+            frame_timer: 0,
+        };
+        randomizer.reset();
+        randomizer
+    }
+
+    fn advance(&mut self) {
+        let bit = ((self.rng >> 9) ^ (self.rng >> 1)) & 1;
+        self.rng = (self.rng >> 1) | (bit << 15);
+    }
+}
+
+impl RandomizerTrait for RandomizerNES {
+    fn reset(&mut self) {
+        // A zero state would never change
+        self.rng = match self.seed as u16 {
+            0 => NES_DEFAULT_SEED,
+            seed => seed,
+        };
+        self.spawn_count = 0;
+        self.spawn_id = 0;
+        self.frame_timer = 0;
+    }
+
+    fn update(&mut self, dt: u64) {
+        self.frame_timer += dt * NES_FRAME_RATE;
+        while self.frame_timer >= NES_FRAME_TIME {
+            self.frame_timer -= NES_FRAME_TIME;
+            self.advance();
+        }
+    }
+
+    fn next_piece(&mut self) -> PieceVariant {
+        self.spawn_count = self.spawn_count.wrapping_add(1);
+
+        let index = ((self.rng >> 8) as u8).wrapping_add(self.spawn_count) & 7;
+        let index = if index == 7 || NES_PIECES[index as usize].1 == self.spawn_id {
+            self.advance();
+            (((self.rng >> 8) as u8 & 7) + self.spawn_id) % 7
+        } else {
+            index
+        };
+
+        let (piece, spawn_id) = NES_PIECES[index as usize];
+        self.spawn_id = spawn_id;
+        piece
+    }
+
+    fn seed(&self) -> u64 { self.seed }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_nes_randomizer() {
+        let mut randomizer = RandomizerNES::new(NES_DEFAULT_SEED as u64);
+
+        randomizer.advance();
+        assert_eq!(randomizer.rng, 0x44C4);
+        randomizer.reset();
+
+        // Advanced once per frame, 60.0988 frames in a second
+        let mut advanced = randomizer.clone();
+        for _ in 0..60 { advanced.advance(); }
+        randomizer.update(1_000_000);
+        assert_eq!(randomizer.rng, advanced.rng);
+        randomizer.reset();
+
+        // Rerolling once makes repeats rare, but possible
+        let pieces: Vec<PieceVariant> = (0..1000).map(|_| randomizer.next_piece()).collect();
+        let repeats = pieces.windows(2).filter(|window| window[0] == window[1]).count();
+        assert!(repeats > 0 && repeats < 1000 / 7);
+
+        // Same seed, same sequence
+        randomizer.reset();
+        assert_eq!(randomizer.next_piece(), pieces[0]);
+    }
+}
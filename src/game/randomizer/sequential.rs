@@ -1,29 +1,32 @@
 use crate::app::ImDraw;
-use crate::game::pieces::{PIECES, PieceVariant};
+use crate::game::pieces::PieceVariant;
 use super::RandomizerTrait;
 
 #[derive(Clone, Debug, ImDraw)]
 pub struct RandomizerSequential {
-    current: u8
+    current: usize,
+    pieces: Vec<PieceVariant>,
 }
 
 impl RandomizerSequential {
-    pub fn new() -> Self {
+    pub fn new(pieces: Vec<PieceVariant>) -> Self {
+        assert!(pieces.len() > 0);
         Self {
-            current: 7,
+            current: pieces.len(),
+            pieces,
         }
     }
 }
 
 impl RandomizerTrait for RandomizerSequential {
     fn reset(&mut self) {
-        self.current = 7;
+        self.current = self.pieces.len();
     }
 
     fn next_piece(&mut self) -> PieceVariant{
         self.current += 1;
-        if self.current >= 7 { self.current = 0; }
-        PIECES[self.current as usize]
+        if self.current >= self.pieces.len() { self.current = 0; }
+        self.pieces[self.current]
     }
 
     fn seed(&self) -> u64 { 0 }
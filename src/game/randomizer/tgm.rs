@@ -0,0 +1,117 @@
+// @TODO abstract PCG
+use rand_core::RngCore;
+
+use crate::app::ImDraw;
+use crate::game::pieces::{PIECES, PieceVariant};
+use super::RandomizerTrait;
+
+// https://tetris.fandom.com/wiki/TGM_randomizer
+// Keeps a history of the last dealt pieces and rerolls a piece that is in the history, up to a
+// number of tries. The last try is dealt even if it's in the history.
+// The first piece is never S, Z or O.
+#[derive(Clone, Debug, ImDraw)]
+pub struct RandomizerTGM {
+    rng: rand_pcg::Pcg32,
+    seed: u64,
+    initial_history: Vec<PieceVariant>,
+    history: Vec<PieceVariant>, // oldest first
+    retries: u8,
+    is_first_piece: bool,
+}
+
+impl RandomizerTGM {
+    pub fn new(seed: u64, initial_history: Vec<PieceVariant>, retries: u8) -> Self {
+        assert!(retries > 0);
+        Self {
+            rng: rand_pcg::Pcg32::new(seed, 0xa02bdbf7bb3c0a7),
+            seed,
+            history: initial_history.clone(),
+            initial_history,
+            retries,
+            is_first_piece: true,
+        }
+    }
+
+    // TGM1: 4 history (starting as ZZZZ), 4 tries
+    pub fn new_tgm1(seed: u64) -> Self {
+        Self::new(seed, vec![PieceVariant::Z; 4], 4)
+    }
+
+    // TGM2: 4 history (starting as ZSSZ), 6 tries
+    pub fn new_tgm2(seed: u64) -> Self {
+        Self::new(seed, tgm_initial_history(4), 6)
+    }
+
+    fn push_history(&mut self, piece: PieceVariant) {
+        if !self.history.is_empty() {
+            self.history.remove(0);
+            self.history.push(piece);
+        }
+    }
+}
+
+// Z S S Z repeated to fill the history
+pub(super) fn tgm_initial_history(size: usize) -> Vec<PieceVariant> {
+    const PATTERN: [PieceVariant; 4] = [PieceVariant::Z, PieceVariant::S, PieceVariant::S, PieceVariant::Z];
+    (0..size).map(|i| PATTERN[i % PATTERN.len()]).collect()
+}
+
+// The first piece is one of I, J, L, T
+pub(super) fn tgm_first_piece(rng: &mut rand_pcg::Pcg32) -> PieceVariant {
+    const FIRST_PIECES: [PieceVariant; 4] =
+        [PieceVariant::I, PieceVariant::J, PieceVariant::L, PieceVariant::T];
+    FIRST_PIECES[(rng.next_u32() % 4) as usize]
+}
+
+impl RandomizerTrait for RandomizerTGM {
+    fn reset(&mut self) {
+        self.history = self.initial_history.clone();
+        self.is_first_piece = true;
+    }
+
+    fn next_piece(&mut self) -> PieceVariant {
+        let piece = if self.is_first_piece {
+            self.is_first_piece = false;
+            tgm_first_piece(&mut self.rng)
+        } else {
+            let mut piece = PIECES[(self.rng.next_u32() % 7) as usize];
+            for _ in 1..self.retries {
+                if !self.history.contains(&piece) { break; }
+                piece = PIECES[(self.rng.next_u32() % 7) as usize];
+            }
+            piece
+        };
+
+        self.push_history(piece);
+        piece
+    }
+
+    fn seed(&self) -> u64 { self.seed }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tgm_randomizer() {
+        let mut randomizer = RandomizerTGM::new_tgm2(0);
+
+        let first_piece = randomizer.next_piece();
+        assert!(!matches!(first_piece, PieceVariant::S | PieceVariant::Z | PieceVariant::O));
+
+        // With enough retries, pieces are never repeated while in the history
+        let mut randomizer = RandomizerTGM::new(1, tgm_initial_history(4), 100);
+        let pieces: Vec<PieceVariant> = (0..100).map(|_| randomizer.next_piece()).collect();
+        for window in pieces.windows(5) {
+            assert!(!window[..4].contains(&window[4]));
+        }
+
+        // Same seed, same sequence
+        let mut a = RandomizerTGM::new_tgm1(42);
+        let mut b = RandomizerTGM::new_tgm1(42);
+        for _ in 0..20 {
+            assert_eq!(a.next_piece(), b.next_piece());
+        }
+    }
+}
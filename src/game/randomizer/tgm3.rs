@@ -0,0 +1,133 @@
+// @TODO abstract PCG
+use rand_core::RngCore;
+
+use crate::app::ImDraw;
+use crate::game::pieces::PieceVariant;
+use super::RandomizerTrait;
+use super::tgm::tgm_first_piece;
+
+const POOL_SIZE: usize = 35;
+const TRIES: u8 = 6;
+
+// Unlike TGM and TGM2 (Z S S Z)
+const INITIAL_HISTORY: [PieceVariant; 4] = [PieceVariant::S, PieceVariant::Z, PieceVariant::S, PieceVariant::Z];
+
+// https://tetris.fandom.com/wiki/TGM_randomizer#TGM3
+// Pieces are drawn from a pool of 35 pieces (5 of each) with a history of 4 pieces and 6 tries.
+// Every time a piece is drawn, its place in the pool is taken by the piece that hasn't been dealt
+// for the longest time (drought), so pieces that haven't appeared become more likely.
+#[derive(Clone, Debug, ImDraw)]
+pub struct RandomizerTGM3 {
+    rng: rand_pcg::Pcg32,
+    seed: u64,
+    pool: [PieceVariant; POOL_SIZE],
+    history: [PieceVariant; 4], // oldest first
+    drought_order: [PieceVariant; 7], // most droughted first
+    is_first_piece: bool,
+}
+
+impl RandomizerTGM3 {
+    pub fn new(seed: u64) -> Self {
+        let mut randomizer = Self {
+            rng: rand_pcg::Pcg32::new(seed, 0xa02bdbf7bb3c0a7),
+            seed,
+            pool: [PieceVariant::I; POOL_SIZE],
+            history: [PieceVariant::I; 4],
+            drought_order: [PieceVariant::I; 7],
+            is_first_piece: true,
+        };
+        randomizer.reset();
+        randomizer
+    }
+
+    fn update_drought_order(&mut self, piece: PieceVariant) {
+        let index = self.drought_order.iter().position(|&p| p == piece).unwrap();
+        self.drought_order[index..].rotate_left(1);
+    }
+
+    fn push_history(&mut self, piece: PieceVariant) {
+        self.history.rotate_left(1);
+        self.history[3] = piece;
+    }
+}
+
+impl RandomizerTrait for RandomizerTGM3 {
+    fn reset(&mut self) {
+        const POOL_PIECES: [PieceVariant; 7] = [
+            PieceVariant::I, PieceVariant::J, PieceVariant::L, PieceVariant::O,
+            PieceVariant::S, PieceVariant::T, PieceVariant::Z,
+        ];
+
+        for (i, piece) in self.pool.iter_mut().enumerate() {
+            *piece = POOL_PIECES[i / 5];
+        }
+
+        self.history = INITIAL_HISTORY;
+
+        self.drought_order = [
+            PieceVariant::J, PieceVariant::I, PieceVariant::Z, PieceVariant::L,
+            PieceVariant::O, PieceVariant::T, PieceVariant::S,
+        ];
+
+        self.is_first_piece = true;
+    }
+
+    fn next_piece(&mut self) -> PieceVariant {
+        // The first piece doesn't change the pool
+        if self.is_first_piece {
+            self.is_first_piece = false;
+
+            let piece = tgm_first_piece(&mut self.rng);
+            self.update_drought_order(piece);
+            self.push_history(piece);
+            return piece;
+        }
+
+        let mut index = 0;
+        let mut piece = PieceVariant::I;
+        for roll in 0..TRIES {
+            index = (self.rng.next_u32() % POOL_SIZE as u32) as usize;
+            piece = self.pool[index];
+            if !self.history.contains(&piece) || roll == TRIES - 1 {
+                break;
+            }
+
+            // A rerolled piece is replaced by the most droughted piece
+            self.pool[index] = self.drought_order[0];
+        }
+
+        self.update_drought_order(piece);
+        self.pool[index] = self.drought_order[0];
+        self.push_history(piece);
+
+        piece
+    }
+
+    fn seed(&self) -> u64 { self.seed }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tgm3_randomizer() {
+        let mut randomizer = RandomizerTGM3::new(7);
+
+        let first_piece = randomizer.next_piece();
+        assert!(!matches!(first_piece, PieceVariant::S | PieceVariant::Z | PieceVariant::O));
+
+        // Droughted pieces fill the pool, so every piece is eventually dealt
+        let pieces: Vec<PieceVariant> = (0..350).map(|_| randomizer.next_piece()).collect();
+        for piece in crate::game::pieces::PIECES.iter() {
+            assert!(pieces.contains(piece));
+        }
+
+        // Same seed, same sequence
+        let mut a = RandomizerTGM3::new(42);
+        let mut b = RandomizerTGM3::new(42);
+        for _ in 0..100 {
+            assert_eq!(a.next_piece(), b.next_piece());
+        }
+    }
+}
@@ -9,7 +9,7 @@ use crate::linalg::*;
 
 use crate::game::{
     pieces::{get_piece_variant_color, Piece},
-    playfield::Playfield,
+    playfield::{BlockType, Playfield},
     rules::{
         RotationSystem,
         line_clear::LineClearAnimationType,
@@ -253,12 +253,12 @@ pub fn draw_playfield(
             // @Refactor cache playfield/draw to framebuffer
             for row in 0..playfield.visible_height as i32 {
                 for col in 0..playfield.grid_size.x {
-                    if let Some(piece_type) = playfield.block(col, row) {
+                    if let Some(block_type) = playfield.block(col, row) {
                         draw_block_in_playfield(
                             Vec2i { x: col, y: row },
                             Vec2::new(),
                             // @Refactor style
-                            get_piece_variant_color(piece_type, rotation_system),
+                            get_block_color(block_type, rotation_system),
                             playfield,
                             pos,
                             pixel_scale,
@@ -281,7 +281,7 @@ pub fn draw_playfield(
                 // if it's a line to be cleared, we have to apply the animation to it
                 if current_line_to_clear.is_some() && *current_line_to_clear.unwrap() == row as u8 {
                     for col in 0..playfield.grid_size.x {
-                        let piece_type = playfield.block(col, row).unwrap();
+                        let block_type = playfield.block(col, row).unwrap();
 
                         if line_clear_animation_should_draw_block(
                             col as u8,
@@ -296,7 +296,7 @@ pub fn draw_playfield(
                                 Vec2i { x: col, y: row },
                                 Vec2::new(),
                                 // @Refactor style
-                                get_piece_variant_color(piece_type, rotation_system),
+                                get_block_color(block_type, rotation_system),
                                 playfield,
                                 pos,
                                 pixel_scale,
@@ -311,12 +311,12 @@ pub fn draw_playfield(
                 } else {
                     // otherwise, we just draw the blocks
                     for col in 0..playfield.grid_size.x {
-                        if let Some(piece_type) = playfield.block(col, row) {
+                        if let Some(block_type) = playfield.block(col, row) {
                             draw_block_in_playfield(
                                 Vec2i { x: col, y: row },
                                 Vec2::new(),
                                 // @Refactor style
-                                get_piece_variant_color(piece_type, rotation_system),
+                                get_block_color(block_type, rotation_system),
                                 playfield,
                                 pos,
                                 pixel_scale,
@@ -440,6 +440,14 @@ pub fn draw_piece_window(
     }
 }
 
+fn get_block_color(block_type: BlockType, rotation_system: RotationSystem) -> Color {
+    match block_type {
+        BlockType::Piece(piece_type) => get_piece_variant_color(piece_type, rotation_system),
+        BlockType::Garbage => color::GRAY,
+        BlockType::Empty => color::TRANSPARENT,
+    }
+}
+
 fn line_clear_animation_should_draw_block(
     block_col: u8,
     animation_type: LineClearAnimationType,
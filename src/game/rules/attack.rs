@@ -0,0 +1,110 @@
+use crate::app::ImDraw;
+use super::*;
+use scoring::ScoringState;
+
+// @TODO macro to generate this from the enum
+pub const ATTACK_TABLE_NAMES: &[&str] = &["GUIDELINE", "TETR.IO", "CLASSIC"];
+
+// Amount of garbage rows sent to the opponents by a line clear
+#[derive(Copy, Clone, Debug, PartialEq, Eq, ImDraw)]
+pub enum AttackTable {
+    Guideline, // fixed table with back-to-back, combo and perfect clear bonuses
+    TetrIo,    // combos multiply the attack instead of adding to it
+    Classic,   // n-1 lines, but a Tetris sends all 4 lines (Game Boy)
+}
+
+// https://tetris.wiki/Combo
+const GUIDELINE_COMBO_ATTACK: [u32; 12] = [0, 0, 1, 1, 2, 2, 3, 3, 4, 4, 4, 5];
+
+const PERFECT_CLEAR_ATTACK: u32 = 10;
+
+impl Rules {
+    // Expects the scoring state already updated with the locked piece. The attack should cancel
+    // pending garbage before being sent
+    // @TODO cascade chains
+    pub fn get_attack(
+        &self,
+        lock_piece_result: LockedPieceResult,
+        scoring_state: ScoringState,
+        is_perfect_clear: bool,
+    ) -> u32 {
+        let lines = lock_piece_result.get_lines_to_clear_slice().len() as u32;
+        if lines == 0 { return 0; }
+
+        if self.attack_table == AttackTable::Classic {
+            return if lines >= 4 { lines } else { lines - 1 };
+        }
+
+        let base = match lock_piece_result {
+            LockedPieceResult::Nothing => 0,
+
+            LockedPieceResult::Single(_) => 0,
+            LockedPieceResult::Double(_) => 1,
+            LockedPieceResult::Triple(_) => 2,
+            LockedPieceResult::Tetris(_) => 4,
+            LockedPieceResult::MultiLine { count, .. } => count as u32,
+
+            LockedPieceResult::MiniTSpin          => 0,
+            LockedPieceResult::MiniTSpinSingle(_) => 0,
+            LockedPieceResult::MiniTSpinDouble(_) => 1,
+            LockedPieceResult::TSpin              => 0,
+            LockedPieceResult::TSpinSingle(_)     => 2,
+            LockedPieceResult::TSpinDouble(_)     => 4,
+            LockedPieceResult::TSpinTriple(_)     => 6,
+        };
+
+        let back_to_back = scoring_state.is_back_to_back_line_clear(lock_piece_result) as u32;
+        let combo = scoring_state.combo.unwrap_or(0);
+        let perfect_clear = if is_perfect_clear { PERFECT_CLEAR_ATTACK } else { 0 };
+
+        match self.attack_table {
+            AttackTable::Guideline => {
+                let combo_index = (combo as usize).min(GUIDELINE_COMBO_ATTACK.len() - 1);
+                base + back_to_back + GUIDELINE_COMBO_ATTACK[combo_index] + perfect_clear
+            }
+
+            AttackTable::TetrIo => {
+                let base = base + back_to_back;
+                let attack = if base > 0 {
+                    base as f32 * (1.0 + 0.25 * combo as f32)
+                } else {
+                    // Combos of singles still send something
+                    (1.0 + 1.25 * combo as f32).ln()
+                };
+
+                attack as u32 + perfect_clear
+            }
+
+            AttackTable::Classic => unreachable!(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_attack_tables() {
+        let mut rules: Rules = RotationSystem::SRS.into();
+        let no_combo = ScoringState { combo: Some(0), back_to_back: Some(0) };
+        let combo = ScoringState { combo: Some(4), back_to_back: Some(1) };
+        let double = LockedPieceResult::Double([0, 1]);
+        let tetris = LockedPieceResult::Tetris([0, 1, 2, 3]);
+
+        rules.attack_table = AttackTable::Guideline;
+        assert_eq!(rules.get_attack(double, no_combo, false), 1);
+        assert_eq!(rules.get_attack(tetris, combo, false), 4 + 1 + 2);
+        assert_eq!(rules.get_attack(tetris, no_combo, true), 14);
+
+        rules.attack_table = AttackTable::TetrIo;
+        assert_eq!(rules.get_attack(tetris, combo, false), 10);
+        assert_eq!(rules.get_attack(LockedPieceResult::Single([0]), combo, false), 1);
+
+        rules.attack_table = AttackTable::Classic;
+        assert_eq!(rules.get_attack(LockedPieceResult::Single([0]), combo, false), 0);
+        assert_eq!(rules.get_attack(double, combo, true), 1);
+        assert_eq!(rules.get_attack(tetris, combo, false), 4);
+        assert_eq!(rules.get_attack(LockedPieceResult::Nothing, combo, false), 0);
+    }
+}
@@ -0,0 +1,135 @@
+use rand_core::RngCore;
+use crate::app::ImDraw;
+
+// @TODO macro to generate this from the enum
+pub const GARBAGE_HOLE_RULE_NAMES: &[&str] = &["CLEAN", "MESSY", "PER ROW"];
+
+// How the hole columns of the received garbage rows are chosen
+#[derive(Copy, Clone, Debug, PartialEq, Eq, ImDraw)]
+pub enum GarbageHoleRule {
+    Clean,                       // all rows of an attack have the same hole
+    Messy { change_chance: u8 }, // each row has a chance (percentage) of moving the hole
+    PerRow,                      // every row has a random hole (aka cheese)
+}
+
+// Garbage received but not yet inserted in the playfield
+#[derive(Clone, Debug, ImDraw)]
+pub struct PendingGarbage {
+    pub holes: Vec<u8>, // hole column of each row, in insertion order
+    pub timestamp: u64, // when the garbage was received
+}
+
+#[derive(Clone, Debug)]
+pub struct GarbageQueue {
+    rng: rand_pcg::Pcg32,
+    last_hole: Option<u8>,
+    pub pending: Vec<PendingGarbage>,
+}
+
+impl_imdraw_todo!(GarbageQueue);
+
+impl GarbageQueue {
+    pub fn new(seed: u64) -> Self {
+        Self {
+            // different stream from the randomizers, so the holes don't follow the pieces
+            rng: rand_pcg::Pcg32::new(seed, 0xda3e39cb94b95bdb),
+            last_hole: None,
+            pending: Vec::new(),
+        }
+    }
+
+    // Queues an attack of garbage rows, choosing the holes with the given rule
+    pub fn push(&mut self, rows: u8, timestamp: u64, width: u8, rule: GarbageHoleRule) {
+        if rows == 0 || width == 0 { return; }
+
+        let mut holes = Vec::with_capacity(rows as usize);
+        let mut hole = self.new_hole(width);
+
+        for _ in 0..rows {
+            match rule {
+                GarbageHoleRule::Clean => {},
+                GarbageHoleRule::Messy { change_chance } => {
+                    if (self.rng.next_u32() % 100) < change_chance as u32 {
+                        hole = self.new_hole(width);
+                    }
+                }
+                GarbageHoleRule::PerRow => hole = self.new_hole(width),
+            }
+            holes.push(hole);
+        }
+
+        self.pending.push(PendingGarbage { holes, timestamp });
+    }
+
+    // Cancels pending garbage rows, oldest first. Returns the amount of rows that had nothing to
+    // cancel
+    pub fn cancel(&mut self, rows: u32) -> u32 {
+        let mut rows = rows;
+        while rows > 0 && !self.pending.is_empty() {
+            let garbage = &mut self.pending[0];
+            let cancelled = rows.min(garbage.holes.len() as u32);
+            garbage.holes.truncate(garbage.holes.len() - cancelled as usize);
+            rows -= cancelled;
+
+            if garbage.holes.is_empty() {
+                self.pending.remove(0);
+            }
+        }
+        rows
+    }
+
+    pub fn pending_rows(&self) -> u32 {
+        self.pending.iter().map(|garbage| garbage.holes.len() as u32).sum()
+    }
+
+    // Removes the garbage received at least `delay` ago and returns its holes, in insertion order
+    pub fn take_ready(&mut self, timestamp: u64, delay: u64) -> Vec<u8> {
+        let ready_count = self.pending.iter()
+            .take_while(|garbage| garbage.timestamp + delay <= timestamp)
+            .count();
+
+        self.pending.drain(..ready_count)
+            .flat_map(|garbage| garbage.holes)
+            .collect()
+    }
+
+    fn new_hole(&mut self, width: u8) -> u8 {
+        // a new hole should be in a different column from the last one
+        let hole = match self.last_hole {
+            Some(last_hole) if width > 1 => {
+                let hole = (self.rng.next_u32() % (width as u32 - 1)) as u8;
+                if hole >= last_hole { hole + 1 } else { hole }
+            }
+            _ => (self.rng.next_u32() % width as u32) as u8,
+        };
+
+        self.last_hole = Some(hole);
+        hole
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_garbage_queue() {
+        let mut queue = GarbageQueue::new(42);
+        queue.push(3, 0, 10, GarbageHoleRule::Clean);
+        queue.push(2, 100, 10, GarbageHoleRule::PerRow);
+        assert_eq!(queue.pending_rows(), 5);
+
+        let holes = &queue.pending[0].holes;
+        assert!(holes.iter().all(|&hole| hole == holes[0] && hole < 10));
+        assert_ne!(queue.pending[1].holes[0], holes[0]);
+
+        // cancelling takes from the oldest garbage first
+        assert_eq!(queue.cancel(4), 0);
+        assert_eq!(queue.pending.len(), 1);
+        assert_eq!(queue.pending_rows(), 1);
+
+        assert!(queue.take_ready(50, 0).is_empty());
+        assert_eq!(queue.take_ready(150, 50).len(), 1);
+        assert_eq!(queue.cancel(3), 3);
+    }
+}
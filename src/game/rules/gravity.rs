@@ -0,0 +1,141 @@
+use crate::app::ImDraw;
+
+use super::*;
+
+// https://tetris.fandom.com/wiki/Drop#Gravity
+// Gravity speed in rows per frame (G), as a fixed point number. A frame here is always 1/60 of a
+// second, independently of the update rate, so 1/64G is one row every ~1.07s and 20G is the
+// piece moving instantly to the stack.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, ImDraw)]
+pub struct Gravity(pub u64);
+
+impl Gravity {
+    pub const ONE_G: u64 = 1 << 24;
+    pub const MAX: Gravity = Gravity(20 * Self::ONE_G);
+
+    // Accumulated gravity (rows * ONE_G * microseconds * frames per second) needed to move one row
+    pub const ROW: u64 = Self::ONE_G * 1_000_000;
+
+    pub fn from_g(numerator: u64, denominator: u64) -> Self {
+        assert!(denominator > 0);
+        Self((numerator * Self::ONE_G / denominator).min(Self::MAX.0))
+    }
+
+    // Time, in microseconds, to move one row
+    pub fn from_interval(interval: u64) -> Self {
+        let interval = interval.max(1);
+        Self((Self::ROW / (60 * interval)).min(Self::MAX.0))
+    }
+
+    // 20G: the piece should always be on the stack
+    pub fn is_instant(&self) -> bool {
+        *self >= Self::MAX
+    }
+
+    // Amount to add to the gravity accumulator after dt microseconds
+    pub fn accumulate(&self, dt: u64) -> u64 {
+        self.0 * dt * 60
+    }
+}
+
+#[derive(Copy, Clone, Debug, ImDraw)]
+pub enum GravityCurve {
+    //Original,
+    Classic,
+    Guideline,
+    //Tetris99,
+
+    NoGravity,
+    Fixed(u64), // interval (in microseconds) to move one row
+    FixedG(Gravity),
+}
+
+impl Rules {
+    pub fn get_gravity(&self, level: u32) -> Option<Gravity> {
+        match self.gravity_curve {
+            // https://tetris.fandom.com/wiki/Tetris_(NES,_Nintendo)
+            // Frames per row, at 60.0988 Hz
+            GravityCurve::Classic => {
+                let frames_per_row = match level {
+                    0       => 48,
+                    1       => 43,
+                    2       => 38,
+                    3       => 33,
+                    4       => 28,
+                    5       => 23,
+                    6       => 18,
+                    7       => 13,
+                    8       => 8,
+                    9       => 6,
+                    10..=12 => 5,
+                    13..=15 => 4,
+                    16..=18 => 3,
+                    19..=28 => 2,
+                    _ => 1,
+                };
+
+                Some(Gravity::from_g(600_988, 600_000 * frames_per_row))
+            },
+
+            // https://tetris.wiki/Marathon#Gravity
+            // The formula gives the time, in seconds, to move one row. After level 15 the piece
+            // moves more than one row per frame and it reaches 20G at level 19
+            GravityCurve::Guideline => {
+                let level = level.clamp(1, 20) as f64;
+                let seconds_per_row = (0.8 - (level - 1.0) * 0.007).powf(level - 1.0);
+                let gravity = Gravity::ONE_G as f64 / (60.0 * seconds_per_row);
+                Some(Gravity((gravity as u64).min(Gravity::MAX.0)))
+            },
+
+            GravityCurve::NoGravity => None,
+            GravityCurve::Fixed(interval) => Some(Gravity::from_interval(interval)),
+            GravityCurve::FixedG(gravity) => Some(gravity),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rows_after(gravity: Gravity, dt: u64, updates: u64) -> u64 {
+        let mut accumulator = 0;
+        let mut rows = 0;
+        for _ in 0..updates {
+            accumulator += gravity.accumulate(dt);
+            rows += accumulator / Gravity::ROW;
+            accumulator %= Gravity::ROW;
+        }
+        rows
+    }
+
+    #[test]
+    fn test_gravity_accumulation() {
+        // 1G: one row per frame, independently of the update rate
+        assert_eq!(rows_after(Gravity::from_g(1, 1), 16_667, 60), 60);
+        assert_eq!(rows_after(Gravity::from_g(1, 1), 8_334, 120), 60);
+
+        // 5G
+        assert_eq!(rows_after(Gravity::from_g(5, 1), 16_667, 60), 300);
+
+        // 1/64G
+        assert_eq!(rows_after(Gravity::from_g(1, 64), 16_667, 64 * 10), 10);
+
+        // 1 row per second
+        assert_eq!(rows_after(Gravity::from_interval(1_000_000), 16_667, 600), 10);
+
+        assert!(Gravity::from_g(20, 1).is_instant());
+        assert!(!Gravity::from_g(19, 1).is_instant());
+    }
+
+    #[test]
+    fn test_guideline_gravity() {
+        let mut rules: Rules = RotationSystem::SRS.into();
+        rules.gravity_curve = GravityCurve::Guideline;
+
+        // level 1: 1 row per second
+        assert_eq!(rows_after(rules.get_gravity(1).unwrap(), 16_667, 600), 10);
+        assert!(!rules.get_gravity(15).unwrap().is_instant());
+        assert!(rules.get_gravity(20).unwrap().is_instant());
+    }
+}
@@ -19,13 +19,36 @@ pub enum LineClearAnimationType {
     Classic,
 }
 
+// Result of a line clear
+#[derive(Clone, Debug, Default)]
+pub struct LineClear {
+    // Lines cleared in each chain, after the first line clear. Only the cascade rule has chains
+    pub chains: Vec<u8>,
+}
+
 impl Rules {
     // @TODO is this really a try? I think it's always called correctly
-    //pub fn try_clear_lines(&self, playfield: &mut Playfield) -> Option<LineClear> {
-    pub fn try_clear_lines(&self, playfield: &mut Playfield) -> bool {
+    pub fn try_clear_lines(&self, playfield: &mut Playfield) -> Option<LineClear> {
         match self.line_clear_rule {
-            LineClearRule::Naive => playfield.try_clear_lines_naive(),
-            _ => unimplemented!("line clear rule not implemented!"),
+            LineClearRule::Naive => {
+                if playfield.try_clear_lines_naive() {
+                    Some(LineClear::default())
+                } else {
+                    None
+                }
+            }
+
+            LineClearRule::Sticky => {
+                if playfield.try_clear_lines_sticky() {
+                    Some(LineClear::default())
+                } else {
+                    None
+                }
+            }
+
+            LineClearRule::Cascade => {
+                playfield.try_clear_lines_cascade().map(|chains| LineClear { chains })
+            }
         }
     }
 }
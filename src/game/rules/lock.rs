@@ -1,10 +1,11 @@
 use crate::linalg::Vec2i;
 use crate::game::{
-    pieces::Piece,
+    pieces::{Piece, PieceVariant, MAX_PIECE_HEIGHT},
     playfield::Playfield,
 };
 
 use super::*;
+use super::rotation::RotationDirection;
 
 #[derive(Copy, Clone, Debug, ImDraw)]
 pub enum LockedPieceResult {
@@ -14,14 +15,16 @@ pub enum LockedPieceResult {
     Double([u8; 2]),
     Triple([u8; 3]),
     Tetris([u8; 4]),
+    // More than 4 lines, only possible with custom pieces (pentominoes, ...)
+    MultiLine { count: u8, lines: [u8; MAX_PIECE_HEIGHT] },
 
     MiniTSpin,
-    MiniTSpinSingle,
-    MiniTSpinDouble,
-    TSpin, // ?
-    TSpinSingle,
-    TSpinDouble,
-    TSpinTriple,
+    MiniTSpinSingle([u8; 1]),
+    MiniTSpinDouble([u8; 2]),
+    TSpin,
+    TSpinSingle([u8; 1]),
+    TSpinDouble([u8; 2]),
+    TSpinTriple([u8; 3]),
 }
 
 impl LockedPieceResult {
@@ -31,9 +34,87 @@ impl LockedPieceResult {
             LockedPieceResult::Double(s) => s,
             LockedPieceResult::Triple(s) => s,
             LockedPieceResult::Tetris(s) => s,
+            LockedPieceResult::MultiLine { count, lines } => &lines[..*count as usize],
+
+            LockedPieceResult::MiniTSpinSingle(s) => s,
+            LockedPieceResult::MiniTSpinDouble(s) => s,
+            LockedPieceResult::TSpinSingle(s) => s,
+            LockedPieceResult::TSpinDouble(s) => s,
+            LockedPieceResult::TSpinTriple(s) => s,
+
             _ => &[],
         }
     }
+
+    pub fn has_lines_to_clear(&self) -> bool {
+        !self.get_lines_to_clear_slice().is_empty()
+    }
+
+    pub fn is_t_spin(&self) -> bool {
+        match self {
+            | LockedPieceResult::MiniTSpin
+            | LockedPieceResult::MiniTSpinSingle(_)
+            | LockedPieceResult::MiniTSpinDouble(_)
+            | LockedPieceResult::TSpin
+            | LockedPieceResult::TSpinSingle(_)
+            | LockedPieceResult::TSpinDouble(_)
+            | LockedPieceResult::TSpinTriple(_)
+            => true,
+
+            _ => false,
+        }
+    }
+
+    // Line clears that keep the back-to-back chain
+    pub fn is_difficult_line_clear(&self) -> bool {
+        match self {
+            LockedPieceResult::Tetris(_) | LockedPieceResult::MultiLine { .. } => true,
+            _ => self.is_t_spin() && self.has_lines_to_clear(),
+        }
+    }
+
+    pub fn is_mini_t_spin(&self) -> bool {
+        match self {
+            | LockedPieceResult::MiniTSpin
+            | LockedPieceResult::MiniTSpinSingle(_)
+            | LockedPieceResult::MiniTSpinDouble(_)
+            => true,
+
+            _ => false,
+        }
+    }
+
+    pub fn new(t_spin: Option<TSpinType>, lines_to_clear: &[u8]) -> Self {
+        let l = lines_to_clear;
+        match (t_spin, lines_to_clear.len()) {
+            (None, 0) => LockedPieceResult::Nothing,
+            (None, 1) => LockedPieceResult::Single([l[0]]),
+            (None, 2) => LockedPieceResult::Double([l[0], l[1]]),
+            (None, 3) => LockedPieceResult::Triple([l[0], l[1], l[2]]),
+            (None, 4) => LockedPieceResult::Tetris([l[0], l[1], l[2], l[3]]),
+            (None, count) => {
+                assert!(count <= MAX_PIECE_HEIGHT);
+                let mut lines = [0; MAX_PIECE_HEIGHT];
+                lines[..count].copy_from_slice(l);
+                LockedPieceResult::MultiLine { count: count as u8, lines }
+            }
+
+            (Some(TSpinType::Mini), 0) => LockedPieceResult::MiniTSpin,
+            (Some(TSpinType::Mini), 1) => LockedPieceResult::MiniTSpinSingle([l[0]]),
+            (Some(TSpinType::Mini), 2) => LockedPieceResult::MiniTSpinDouble([l[0], l[1]]),
+
+            (Some(TSpinType::Full), 0) => LockedPieceResult::TSpin,
+            (Some(TSpinType::Full), 1) => LockedPieceResult::TSpinSingle([l[0]]),
+            (Some(TSpinType::Full), 2) => LockedPieceResult::TSpinDouble([l[0], l[1]]),
+
+            // There's no mini T-Spin triple: a T piece can only clear 3 lines in a T-Spin position
+            // with the last kick, which always upgrades it to a full T-Spin
+            (Some(_), 3) => LockedPieceResult::TSpinTriple([l[0], l[1], l[2]]),
+
+            // T pieces always have 4 blocks (custom rotation systems too), so they can't clear more
+            _ => unreachable!(),
+        }
+    }
 }
 
 // T-Spin explanation and examples: http://harddrop.com/fumen/?m115@sgg0Aeg0QeAtAeAtreF811AyoSTASo78A2no2ACD5r?DlsCSASI/MESGNXEzoo2AJG98AQ51JEBD98AQo7aEJPONEO?BAAAvhGFcutAyoSTASoTABEoo2AUoo2Aw+kkDloo2ApN98A?Q5bkDJmZTASYlNE3CaoDTBAAAFcuxACD5rDFbcRATG88AwU?jXEuICbEFbMLEuoo2AiSg/DFbMLEmoo2AUoo2Aw+kkDFBAA?AFcueAzno2Aj3UNEyoSTASIPrDMj0TAS4wrDxQWXEFnBAAF?curAmXyTAS4wrDnAFeDyoo2AUEzPEJG98AwWyTASo93Du+8?8AQemsCwOxCAFcuzAyno2AyoSTASIPrDMj0TAS4wrDxQWXE?F388AQZjXEFbUVEl7gDEBM98AQemsCwOxCAFcusAV2krDzN?98AQemsCwOpTASYlWEJ5krDFbs9DpViTASIE2DplbTASosa?EFcu0Ayno2AynNbEFbEmDvjpTASo78A2no2Axno2Aj3khEN?G98AQurTASY91Dloo2AzuSrDsggHAegHQeAPAeAPreAAtjA?yYZhEsCyTASYttAzI2JEFbEBEJGVTASI3CElCCbElsKBAMh?H8CeH8AeE8JedruAAMhgWQeAPAeAPNeFrfMhglQeAtAeAtN?edrfvhAFrfMhAPQegWAeAPNeNrfHhC8BeAtQeglAeAtNetl?fMhgWQeAPAeAPNeFrf3gB8IeA8GeA8AeglIeA8GeAtAeAtN?e1gfKhAPAegWQeAPAegWNedrfKhAtAeglQeAPAeglNe1gfK?hAPAegWSegWNedru2AP2EvEFb85AFbUVEF388Aw08CEsoo2?AiA3TASIbeEJzkTAS4wrDnAFeDyoo2AUEzPEJ2BAAvhAdru?lAiYwdD1NVTASICvDFbEwCtMN5Duoo2A0LmQEs488AwAukD?LBAAAKhAtAeglIeAAGeA8AeglNe1guAAvhBdrfFrfKhgWAe?APSeAPNeNrfKhglAeAtQeAAAeAtNe1gfvhBdrfFrfKhgWAe?APSeAPNeNrfhgB8IeA8FeB8AeA8BeE8BeB8AeD8AeBAgHA8?AtFeAAC8FeAAB8AtNeNQfvhC1VfdgfdlfAhgWQeAPAeAPZe?FlfhgBAFeB8AeAAGeAAAeGABeA8glIeA8GeAPA8AtZeVbf9?ggWAeAPQegWAeAPaetkfXgB8IeA8IeG8CeG8glA8APHeA8H?eglAeAPaeNLfvhA1QfqgAPAegWQeAPAegWtedbfXgBAIeAA?FeAAAPAegHFAAeB8GAAeAPAegHFAAeAAAeGAKeAAA8QeFgf?HhgWSegWAeAPQetpu2AP2EvEFb85AFbUVEF388Aw08CEsoo?2AiA3TASIbeEJzkTAS4wrDnAFeDyoo2AUEzPEJ2BAAvhAtp?ulAiYwdD1NVTASICvDFbEwCtMN5Duoo2A0LmQEs488AwAuk?DLBAAAkgB8IeA8FeB8AeD8AeC8CeC8glC8BeD8AeB8AeBAD?eglA8AtDAMeVXuQAmXyTASY91Dloo2As3cyEvhE9hf9rfVw?f1wfNrf3ggWAegWSeAPgelhuyAyYZhEsCyTASYttAzI2JEF?bcRASExrD2ICbEloo2AUEzPEJG98AQuR5DQDVTAylAAAkgB?AIeAACeDAgHAegHGACeGABeAPGABeGADeCAJeAAtgANOJ5D?FbEwCtMN5Duoo2AsOprDFbsiDs4DXEz4CwBkgB8IeA8CeE8?AeH8CeG8BeH8BeH8BeD8Je9huAA3ggWAegWSeAPgelhuYAP?2EvEFb8bDFbcYCJGeTASYttAzI2JE3gglAeglSeAtge9ruA?A3ggWAegWSeAPgelhuzAP2EvEFb8bDFbcYCJGeTASYttAzI?2JEFbcRASEYNEFbEwCyuVDEloo2Areg/DFr4AAvhBlhu4A0?LmQEs488AwAukDr4CwBFbU9AFbUVEvz0TASYBNEXmbfEwow?2BFb85AFbEcEvoo2AzuSrDlhu9Ayno2AynNbEFbEmDvjZ1A?VJ98AQo78AQurTASIT5Dk488Aw3K6BFb0HEvoo2A0LmQEs4?88AwAukDLBAAAkgBAIeAAFeAAgHAegHCAAeCACeCABeBABe?APCABeBAB8FeAAAeB8NeFguAAIhgWSegWAeAPPeNquYAP2E?vEFb8bDFbcYCJGeTASYttAzI2JEzgCAAeF8AAEeD8AeglB8?AeE8BeA8AeAAFeglA8APAAOe1guAAvhBdrfFhfKhAPAegWS?egWNedruYAP2EvEFb8bDFbcYCJGeTASYttAzI2JE3gFAFeG?AAPAeglIeAAGeA8AeglNedrujAtnceEFb0sDy4vhEF22TAS?o93Du+88AQemsCwO5aElsKBAMhAPQegWAegWNeVrfMhAPDA?EeA8GegHAegHNetqfWhAPXeVwflhC8g0A8g0D8VwujAFbew?DyHRKEkoo2AjHRKE0N98AwR0TAS414DMD9nDFr4AAHhC8Le?APDADeB8DeCAgHAAgHDAVrujAtnceEFb0sDy4vhEF22TASo?93Du+88AQemsCwO5aElsKBAJhgWSegWAeAPOetqfHhBAgHG?eCAGeAAAeglA8AtOe1puYABjkNEFbMmEPsyaEFbMLEuoo2A?3iMDEchAPReNpupAFbemEBzkTASI/MEV2GbEFb8bDzoo2Am?uMDEF388Aw08CEsN98AZAAAA/gB8HeA8IeA8GeA8AtAeAAP?eVrueABjkNEFbMmEPsyaEFbMmEJ/bTASIClEF87dDurBAAJ?hgWSegWAeAPOetqf
@@ -41,8 +122,14 @@ impl LockedPieceResult {
 #[derive(Copy, Clone, Debug, ImDraw)]
 pub enum LastPieceAction {
     Movement,
-    // @TODO we will need to know if the piece has wall kicked or not, and which kick, for T-Spins
-    Rotation,
+    // kick: index of the rotation test used (0 = no kick)
+    Rotation { direction: RotationDirection, kick: u8 },
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, ImDraw)]
+pub enum TSpinType {
+    Mini,
+    Full,
 }
 
 #[derive(Copy, Clone, Debug, ImDraw)]
@@ -85,3 +172,157 @@ pub fn is_piece_locking(
     false
 }
 
+
+// https://tetris.fandom.com/wiki/T-Spin
+// 3-corner T-Spin detection: a T piece that locks after a rotation with at least 3 of the 4
+// diagonal cells around its center occupied is a T-Spin. It's a full T-Spin if both corners in
+// front of the T (the side it's pointing to) are occupied, otherwise it's a mini T-Spin. In SRS,
+// the last kick test (index 4, the "TST kick") always upgrades a mini T-Spin into a full one.
+pub fn get_t_spin_type(
+    piece: &Piece,
+    pos: Vec2i,
+    playfield: &Playfield,
+    last_piece_action: LastPieceAction,
+) -> Option<TSpinType> {
+    let (direction, kick) = match last_piece_action {
+        LastPieceAction::Rotation { direction, kick } => (direction, kick),
+        LastPieceAction::Movement => return None,
+    };
+
+    if piece.variant != PieceVariant::T { return None; }
+
+    let blocks = piece.blocks();
+
+    // The center of the T is the only block adjacent to all the other 3 blocks. This way we don't
+    // depend on how each rotation system defines the piece blocks
+    let is_adjacent = |a: Vec2i, b: Vec2i| (a.x - b.x).abs() + (a.y - b.y).abs() == 1;
+    let center = *blocks.iter()
+        .find(|&&block| blocks.iter().filter(|&&other| is_adjacent(block, other)).count() == 3)?;
+
+    // The T points to the opposite side of the only empty orthogonal neighbour of the center
+    let back = [
+        Vec2i { x:  1, y:  0 },
+        Vec2i { x: -1, y:  0 },
+        Vec2i { x:  0, y:  1 },
+        Vec2i { x:  0, y: -1 },
+    ]
+        .iter()
+        .copied()
+        .find(|&dir| !blocks.iter().any(|&block| block == center + dir))?;
+
+    let front = Vec2i { x: -back.x, y: -back.y };
+    let side  = Vec2i { x: front.y, y: front.x };
+
+    let center = pos + center;
+    let is_occupied = |p: Vec2i| playfield.block(p.x, p.y).is_some();
+
+    let front_corners =
+        is_occupied(center + front + side) as u8 +
+        is_occupied(center + front - side) as u8;
+    let back_corners =
+        is_occupied(center + back + side) as u8 +
+        is_occupied(center + back - side) as u8;
+
+    if front_corners + back_corners < 3 { return None; }
+
+    // The SRS last kick of a 90 degree rotation (TST and fin kicks) is always a full T-Spin. 180
+    // degree rotations have their own kick tables, so their last kick means nothing
+    let is_srs_last_kick = kick == 4
+        && direction != RotationDirection::Half
+        && piece.rotation_system == RotationSystem::SRS;
+
+    if front_corners == 2 || is_srs_last_kick {
+        Some(TSpinType::Full)
+    } else {
+        Some(TSpinType::Mini)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn t_piece(rot: i32) -> Piece {
+        Piece { variant: PieceVariant::T, rot, rotation_system: RotationSystem::SRS }
+    }
+
+    fn rotation(kick: u8) -> LastPieceAction {
+        LastPieceAction::Rotation { direction: RotationDirection::Clockwise, kick }
+    }
+
+    #[test]
+    fn test_t_spin_double() {
+        let mut playfield = Playfield::new(Vec2i { x: 10, y: 40 }, 20);
+
+        // T-Spin Double slot at column 4
+        for x in 0..10 {
+            if x != 4 { playfield.set_block(x, 0, PieceVariant::I); }
+            if x < 3 || x > 5 { playfield.set_block(x, 1, PieceVariant::I); }
+        }
+        playfield.set_block(3, 2, PieceVariant::I);
+
+        // pointing down, center at (4, 1)
+        let piece = t_piece(2);
+        let pos = Vec2i { x: 3, y: 0 };
+
+        assert_eq!(
+            get_t_spin_type(&piece, pos, &playfield, rotation(0)),
+            Some(TSpinType::Full)
+        );
+        assert_eq!(get_t_spin_type(&piece, pos, &playfield, LastPieceAction::Movement), None);
+
+        lock_piece(&piece, pos, &mut playfield);
+        let lines_to_clear = playfield.get_lines_to_clear();
+        let result = LockedPieceResult::new(Some(TSpinType::Full), &lines_to_clear);
+        assert!(result.is_t_spin());
+        assert_eq!(result.get_lines_to_clear_slice(), &[0, 1]);
+    }
+
+    #[test]
+    fn test_mini_t_spin() {
+        let mut playfield = Playfield::new(Vec2i { x: 10, y: 40 }, 20);
+        playfield.set_block(0, 1, PieceVariant::I);
+
+        // pointing up on the floor, center at (1, 0): back corners are out of the playfield
+        let piece = t_piece(0);
+        let pos = Vec2i { x: 0, y: -1 };
+
+        assert_eq!(
+            get_t_spin_type(&piece, pos, &playfield, rotation(1)),
+            Some(TSpinType::Mini)
+        );
+
+        // SRS last kick upgrades to a full T-Spin
+        assert_eq!(
+            get_t_spin_type(&piece, pos, &playfield, rotation(4)),
+            Some(TSpinType::Full)
+        );
+
+        // but not the last kick of a 180 rotation
+        let half_rotation = LastPieceAction::Rotation { direction: RotationDirection::Half, kick: 4 };
+        assert_eq!(
+            get_t_spin_type(&piece, pos, &playfield, half_rotation),
+            Some(TSpinType::Mini)
+        );
+
+        playfield.reset_block(0, 1);
+        assert_eq!(
+            get_t_spin_type(&piece, pos, &playfield, rotation(1)),
+            None
+        );
+    }
+
+    #[test]
+    fn test_multi_line_clear() {
+        let mut playfield = Playfield::new(Vec2i { x: 4, y: 40 }, 20);
+        for y in 0..5 {
+            for x in 0..4 { playfield.set_block(x, y, PieceVariant::Custom(0)); }
+        }
+
+        let lines_to_clear = playfield.get_lines_to_clear();
+        let result = LockedPieceResult::new(None, &lines_to_clear);
+        assert!(matches!(result, LockedPieceResult::MultiLine { count: 5, .. }));
+        assert_eq!(result.get_lines_to_clear_slice(), &[0, 1, 2, 3, 4]);
+        assert!(result.is_difficult_line_clear());
+    }
+}
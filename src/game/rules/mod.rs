@@ -1,15 +1,25 @@
 use crate::app::ImDraw;
 use super::randomizer::RandomizerType;
 
+pub mod attack;
+pub mod garbage;
+pub mod gravity;
 pub mod line_clear;
 pub mod lock;
 pub mod movement;
 pub mod rotation;
 pub mod scoring;
+pub mod timing;
 pub mod topout;
 
+pub use gravity::{Gravity, GravityCurve};
+pub use timing::TimingCurve;
+
+use attack::AttackTable;
+use garbage::GarbageHoleRule;
 use lock::{LockedPieceResult, LockedPiece};
 use line_clear::{LineClearAnimationType, LineClearRule};
+use rotation::Rotation180Kicks;
 use scoring::ScoringRule;
 use topout::TopOutRule;
 
@@ -20,12 +30,9 @@ pub struct Rules {
     // @TODO use bitfields
     // gameplay rules
 
-    // @TODO HardDropRule, SoftDropRule
     // https://tetris.fandom.com/wiki/Drop
-    pub has_hard_drop: bool,
-    pub has_hard_drop_lock: bool, // Firm drop = false
-    pub has_soft_drop: bool,
-    pub has_soft_drop_lock: bool, // Lock when soft dropping
+    pub hard_drop_rule: HardDropRule,
+    pub soft_drop_rule: SoftDropRule,
 
     pub has_ghost_piece: bool,
     pub has_hold_piece: bool,
@@ -36,19 +43,20 @@ pub struct Rules {
     pub has_initial_hold_system: bool,     // IHS
     pub spawn_drop: bool, // "Immediately drop one space if no existing Block is in its path"
 
-    // https://tetris.fandom.com/wiki/Infinity
-    //pub has_lock_delay_infinity: bool,
-
     pub spawn_row: u8,
     pub next_pieces_preview_count: u8,
 
+    // Rows above the visible height are hidden, but pieces can still be there (usually spawning)
+    pub playfield_width: u8,
+    pub playfield_height: u8,
+    pub playfield_visible_height: u8,
+
     pub line_clear_rule: LineClearRule,
 
     pub top_out_rule: TopOutRule,
 
-    // @TODO some games implement a progression for these intervals (faster levels = smaller ARE,
-    //       line clear delays and faster DAS). So we may need to accept a closure that receives the
-    //       level
+    // These timings can change with the level (see timing_curve). Use the Rules getters instead of
+    // the fields directly
     pub das_repeat_delay: u64, // DAS - Delayed Auto Shift (initial delay)
     pub das_repeat_interval: u64, // ARR - Auto-Repeat Rate (all other delays)
     pub soft_drop_interval: u64,
@@ -62,22 +70,21 @@ pub struct Rules {
     // THIS IS A CONSTANT (how can we do this better?)
     pub minimum_level: u8, // Some games start at 0, some start at 1
 
-    // @TODO depend on last lock height.
-    //       Tetris NES: ARE is 10~18 frames depending on the height at which the piece locked;
-    //                   pieces that lock in the bottom two rows are followed by 10 frames of entry
-    //                   delay, and each group of 4 rows above that has an entry delay 2 frames
-    //                   longer than the last;
     pub spawn_delay: u64, // aka ARE
 
     pub lock_delay: LockDelayRule,
 
+    pub timing_curve: TimingCurve,
+
     // Piece positioning rules
 
     // @Fix this is not the correct term. Rotation System defines spawn, rotation, wall kicks, etc.
     //      We should change to RotationRule (maybe allow disabling wall kicks?)
     pub rotation_system: RotationSystem,
-    //pub does_ceiling_prevents_rotation: bool, // Sega
-    //pub double_rotation: bool, // DTET
+    pub does_ceiling_prevents_rotation: bool, // Sega
+    pub has_floor_kicks: bool, // TGM3 (ARS only)
+    pub has_180_rotation: bool, // DTET, modern games
+    pub rotation_180_kicks: Rotation180Kicks,
     //pub has_wall_kicks: bool, // Disable/enable wall kicks
 
     // @Design this is part of the rotation system!
@@ -86,6 +93,12 @@ pub struct Rules {
     // randomizer
     pub randomizer_type: RandomizerType,
 
+    // garbage (sent to and received from other players)
+    pub attack_table: AttackTable,
+    pub garbage_hole_rule: GarbageHoleRule,
+    pub garbage_delay: u64, // time before the received garbage can enter the playfield
+    pub has_garbage_cancel: bool, // the attack cancels pending garbage before being sent
+
     // Animation
     pub has_movement_animation: bool,
     pub movement_animation_show_ghost: bool, // @Remove this is debug only
@@ -104,10 +117,8 @@ impl From<RotationSystem> for Rules {
         match rotation_system {
             RotationSystem::NRSR => {
                 Self {
-                    has_hard_drop: false,
-                    has_hard_drop_lock: false,
-                    has_soft_drop: true,
-                    has_soft_drop_lock: false,
+                    hard_drop_rule: HardDropRule::No,
+                    soft_drop_rule: SoftDropRule::SoftDrop,
                     has_hold_piece: false,
                     has_ghost_piece: false,
                     hold_piece_reset_rotation: true,
@@ -119,6 +130,10 @@ impl From<RotationSystem> for Rules {
                     spawn_row: 20u8,
                     next_pieces_preview_count: 1u8,
 
+                    playfield_width: 10,
+                    playfield_height: 40,
+                    playfield_visible_height: 20,
+
                     line_clear_rule: LineClearRule::Naive,
 
                     top_out_rule: TopOutRule::BLOCK_OUT,
@@ -136,10 +151,20 @@ impl From<RotationSystem> for Rules {
 
                     spawn_delay: 0,
                     lock_delay: LockDelayRule::NoDelay,
+                    timing_curve: TimingCurve::NES,
 
                     rotation_system: RotationSystem::NRSR,
+                    does_ceiling_prevents_rotation: false,
+                    has_floor_kicks: false,
+                    has_180_rotation: false,
+                    rotation_180_kicks: Rotation180Kicks::NoKicks,
 
-                    randomizer_type: RandomizerType::FullRandom,
+                    randomizer_type: RandomizerType::NES,
+
+                    attack_table: AttackTable::Classic,
+                    garbage_hole_rule: GarbageHoleRule::Clean,
+                    garbage_delay: 0,
+                    has_garbage_cancel: false,
 
                     // Animation
                     has_movement_animation: false,
@@ -155,10 +180,8 @@ impl From<RotationSystem> for Rules {
 
             RotationSystem::SRS => {
                 Self {
-                    has_hard_drop: true,
-                    has_hard_drop_lock: true,
-                    has_soft_drop: true,
-                    has_soft_drop_lock: false,
+                    hard_drop_rule: HardDropRule::HardDrop,
+                    soft_drop_rule: SoftDropRule::SoftDrop,
                     has_hold_piece: true,
                     has_ghost_piece: true,
                     hold_piece_reset_rotation: true,
@@ -170,6 +193,10 @@ impl From<RotationSystem> for Rules {
                     spawn_row: 22u8,
                     next_pieces_preview_count: 4u8,
 
+                    playfield_width: 10,
+                    playfield_height: 40,
+                    playfield_visible_height: 20,
+
                     line_clear_rule: LineClearRule::Naive,
 
                     top_out_rule: TopOutRule::BLOCK_OUT | TopOutRule::LOCK_OUT,
@@ -179,9 +206,9 @@ impl From<RotationSystem> for Rules {
                     soft_drop_interval: 33_279,  // 1/2G at 60.0988 Hz
                     line_clear_delay: 332_785,   // 20 frames at 60.0988 Hz
 
-                    gravity_curve: GravityCurve::Classic,
-                    scoring_curve: ScoringRule::Classic,
-                    level_curve: LevelCurve::Classic,
+                    gravity_curve: GravityCurve::Guideline,
+                    scoring_curve: ScoringRule::Guideline,
+                    level_curve: LevelCurve::Guideline,
                     start_level: 1,
                     minimum_level: 1,
 
@@ -191,11 +218,21 @@ impl From<RotationSystem> for Rules {
                         rotations: 5,
                         movements: 5,
                     },
+                    timing_curve: TimingCurve::Constant,
 
                     rotation_system: RotationSystem::SRS,
+                    does_ceiling_prevents_rotation: false,
+                    has_floor_kicks: false,
+                    has_180_rotation: false,
+                    rotation_180_kicks: Rotation180Kicks::NoKicks,
 
                     randomizer_type: RandomizerType::Random7Bag,
 
+                    attack_table: AttackTable::Guideline,
+                    garbage_hole_rule: GarbageHoleRule::Messy { change_chance: 30 },
+                    garbage_delay: 500_000,
+                    has_garbage_cancel: true,
+
                     // Animation
                     has_movement_animation: true,
                     movement_animation_show_ghost: false,
@@ -208,12 +245,77 @@ impl From<RotationSystem> for Rules {
                 }
             }
 
+            // https://tetris.wiki/Tetris_The_Absolute_The_Grand_Master_2#Master
+            RotationSystem::ARS => {
+                Self {
+                    hard_drop_rule: HardDropRule::FirmDrop, // sonic drop
+                    soft_drop_rule: SoftDropRule::SoftDropLock,
+
+                    has_hold_piece: false,
+                    has_ghost_piece: true,
+                    hold_piece_reset_rotation: true,
+                    spawn_drop: false,
+
+                    has_initial_rotation_system: true,
+                    has_initial_hold_system: false,
+
+                    spawn_row: 20u8,
+                    next_pieces_preview_count: 1u8,
+
+                    playfield_width: 10,
+                    playfield_height: 40,
+                    playfield_visible_height: 20,
+
+                    line_clear_rule: LineClearRule::Naive,
+
+                    top_out_rule: TopOutRule::BLOCK_OUT,
+
+                    // Level 0 timings, the timing curve changes them with the level
+                    das_repeat_delay: 233_338,   // 14 frames at 60 Hz
+                    das_repeat_interval: 16_667, // 1 frame at 60 Hz
+                    soft_drop_interval: 16_667,  // 1G at 60 Hz
+                    line_clear_delay: 666_680,   // 40 frames at 60 Hz
+
+                    // @TODO TGM gravity curve (internal gravity, 20G at level 500)
+                    gravity_curve: GravityCurve::Classic,
+                    scoring_curve: ScoringRule::Classic,
+                    level_curve: LevelCurve::TGM,
+                    start_level: 0,
+                    minimum_level: 0,
+
+                    spawn_delay: 416_675, // 25 frames at 60 Hz
+                    lock_delay: LockDelayRule::StepReset(500_010), // 30 frames at 60 Hz
+                    timing_curve: TimingCurve::TGM2Master,
+
+                    rotation_system: RotationSystem::ARS,
+                    does_ceiling_prevents_rotation: false,
+                    has_floor_kicks: false,
+                    has_180_rotation: false,
+                    rotation_180_kicks: Rotation180Kicks::NoKicks,
+
+                    randomizer_type: RandomizerType::TGM,
+
+                    attack_table: AttackTable::Classic,
+                    garbage_hole_rule: GarbageHoleRule::Clean,
+                    garbage_delay: 0,
+                    has_garbage_cancel: false,
+
+                    // Animation
+                    has_movement_animation: false,
+                    movement_animation_show_ghost: false,
+                    movement_animation_duration: 0,
+
+                    line_clear_animation_type: Some(LineClearAnimationType::Classic),
+
+                    has_locking_animation: false,
+                    locking_animation_duration: 0,
+                }
+            }
+
             _ => {
                 Self {
-                    has_hard_drop: true,
-                    has_hard_drop_lock: false,
-                    has_soft_drop: true,
-                    has_soft_drop_lock: false,
+                    hard_drop_rule: HardDropRule::FirmDrop,
+                    soft_drop_rule: SoftDropRule::SoftDrop,
 
                     has_hold_piece: true,
                     has_ghost_piece: true,
@@ -226,6 +328,10 @@ impl From<RotationSystem> for Rules {
                     spawn_row: 22u8,
                     next_pieces_preview_count: 4u8,
 
+                    playfield_width: 10,
+                    playfield_height: 40,
+                    playfield_visible_height: 20,
+
                     line_clear_rule: LineClearRule::Naive,
 
                     top_out_rule: TopOutRule::BLOCK_OUT | TopOutRule::LOCK_OUT,
@@ -247,11 +353,21 @@ impl From<RotationSystem> for Rules {
                         rotations: 5,
                         movements: 5,
                     },
+                    timing_curve: TimingCurve::Constant,
 
                     rotation_system: RotationSystem::SRS,
+                    does_ceiling_prevents_rotation: false,
+                    has_floor_kicks: false,
+                    has_180_rotation: false,
+                    rotation_180_kicks: Rotation180Kicks::NoKicks,
 
                     randomizer_type: RandomizerType::FullRandom,
 
+                    attack_table: AttackTable::Classic,
+                    garbage_hole_rule: GarbageHoleRule::Clean,
+                    garbage_delay: 500_000,
+                    has_garbage_cancel: true,
+
                     // Animation
                     has_movement_animation: true,
                     movement_animation_show_ghost: true,
@@ -267,29 +383,38 @@ impl From<RotationSystem> for Rules {
     }
 }
 
-/*
-// @TODO HardDropRule, SoftDropRule
+pub const HARD_DROP_RULE_NAMES: &[&str] = &["NO", "HARD DROP", "FIRM DROP"];
+pub const SOFT_DROP_RULE_NAMES: &[&str] = &["NO", "SOFT DROP", "SOFT DROP LOCK", "SONIC DROP"];
+
 // https://tetris.fandom.com/wiki/Drop
-#[derive(Copy, Clone, Debug)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, ImDraw)]
 pub enum HardDropRule {
     No,
-    HardDrop,
-    FirmDrop,
+    HardDrop, // Drops the piece to the bottom and locks it
+    FirmDrop, // Drops the piece to the bottom without locking it
 }
 
-#[derive(Copy, Clone, Debug)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, ImDraw)]
 pub enum SoftDropRule {
     No,
-    SoftDrop,
-    SoftDropLock,
+    SoftDrop,     // Moves the piece down one row
+    SoftDropLock, // Moves the piece down one row and locks it if it's on the stack (TGM)
+    SonicDrop,    // Drops the piece to the bottom without locking it
+}
+
+impl HardDropRule {
+    pub fn is_enabled(self) -> bool { self != HardDropRule::No }
+}
+
+impl SoftDropRule {
+    pub fn is_enabled(self) -> bool { self != SoftDropRule::No }
 }
-*/
 
 // @TODO macro this
 pub const ROTATION_SYSTEM_NAMES: &[&str] = &["ORIGINAL", "NRSL", "NRSR", "SEGA", "ARS", "SRS", "DTET"];
 
 // https://tetris.fandom.com/wiki/Category:Rotation_Systems
-#[derive(Copy, Clone, Debug, ImDraw)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, ImDraw)]
 pub enum RotationSystem {
     Original, // Original Rotation System
     NRSL,     // Nintendo Rotation System - Left Handed
@@ -299,100 +424,157 @@ pub enum RotationSystem {
     SRS,      // Super Rotation System
     DTET,
 
-    // TBRS, // https://tetris.fandom.com/wiki/Tetris_Best_rotation_system
-    // TRRS, // https://tetris.fandom.com/wiki/Tetris_Return_rotation_system
+    // Loaded from a file (see pieces::custom). TBRS, TRRS and house variants should be made this way
+    // https://tetris.fandom.com/wiki/Tetris_Best_rotation_system
+    // https://tetris.fandom.com/wiki/Tetris_Return_rotation_system
+    Custom(u8),
 }
 
+pub const LOCK_DELAY_RULE_NAMES: &[&str] = &[
+    "NO DELAY", "ENTRY RESET", "STEP RESET", "MOVE RESET", "EXTENDED PLACEMENT", "INFINITY"
+];
+
+// https://tetris.fandom.com/wiki/Lock_delay
+// https://tetris.fandom.com/wiki/Infinity
 #[derive(Copy, Clone, Debug, PartialEq, Eq, ImDraw)]
 pub enum LockDelayRule {
     NoDelay,
     EntryReset(u64),
-    StepReset(u64),
+    StepReset(u64), // TGM: resets when the piece reaches a new lowest row
     MoveReset { duration: u64, rotations: u8, movements: u8 },
-    //MoveResetInfinity(u64),
+    ExtendedPlacement { duration: u64, moves: u8 }, // Guideline: moves reset on a new lowest row
+    Infinity(u64), // Guideline infinite placement: every move resets, without limits
 }
 
-#[derive(Copy, Clone, Debug, ImDraw)]
-pub enum GravityCurve {
-    //Original,
-    Classic,
-    Guideline,
-    //Tetris99,
+impl LockDelayRule {
+    pub fn with_duration(self, duration: u64) -> Self {
+        match self {
+            LockDelayRule::NoDelay => LockDelayRule::NoDelay,
+            LockDelayRule::EntryReset(_) => LockDelayRule::EntryReset(duration),
+            LockDelayRule::StepReset(_)  => LockDelayRule::StepReset(duration),
+            LockDelayRule::MoveReset { rotations, movements, .. } => {
+                LockDelayRule::MoveReset { duration, rotations, movements }
+            }
+            LockDelayRule::ExtendedPlacement { moves, .. } => {
+                LockDelayRule::ExtendedPlacement { duration, moves }
+            }
+            LockDelayRule::Infinity(_) => LockDelayRule::Infinity(duration),
+        }
+    }
 
-    NoGravity,
-    Fixed(u64),
+    pub fn duration(self) -> u64 {
+        match self {
+            LockDelayRule::NoDelay => 0,
+            | LockDelayRule::EntryReset(duration)
+            | LockDelayRule::StepReset(duration)
+            | LockDelayRule::MoveReset { duration, .. }
+            | LockDelayRule::ExtendedPlacement { duration, .. }
+            | LockDelayRule::Infinity(duration)
+            => duration,
+        }
+    }
 }
 
 #[derive(Copy, Clone, Debug, ImDraw)]
 pub enum LevelCurve {
     //Original,
     Classic,
-    Guideline,
+    Guideline,             // Fixed goal: 10 lines per level
+    GuidelineVariableGoal, // 5 * level line clear awards per level
+    TGM,                   // 0-999, each piece and each line cleared is a level (see tgm_level_up)
 }
 
-impl Rules {
-    // @TODO move to gravity.rs
-    pub fn get_gravity_interval(&self, level: u32) -> Option<u64> {
-        match self.gravity_curve {
-            GravityCurve::Classic => {
-                let gravity = match level {
-                    0       => 48,
-                    1       => 43,
-                    2       => 38,
-                    3       => 33,
-                    4       => 28,
-                    5       => 23,
-                    6       => 18,
-                    7       => 13,
-                    8       => 8,
-                    9       => 6,
-                    10..=12 => 5,
-                    13..=15 => 4,
-                    16..=18 => 3,
-                    19..=28 => 2,
-                    _ => 1,
-                };
-
-                let frame_duration = (1_000_000.0 / 60.0988) as u64;
-                Some(gravity * frame_duration)
-            },
-
-            GravityCurve::NoGravity => None,
-            GravityCurve::Fixed(duration) => Some(duration),
-
-            _ => { Some(1_000_000) }
-        }
+// https://tetris.wiki/Tetris_The_Grand_Master#Level
+// Every piece spawned and every line cleared raises the level, up to 999. The last level of each
+// section (x99 and 998) can only be passed by clearing lines. Since the section stops depend on the
+// order of the level ups, the TGM level can't be computed from the totals and the game keeps it
+pub fn tgm_level_up(level: u32, lines_cleared: u32) -> u32 {
+    if lines_cleared > 0 {
+        (level + lines_cleared).min(999)
+    } else if level % 100 == 99 || level >= 998 {
+        level
+    } else {
+        level + 1
     }
+}
 
+impl Rules {
     // @TODO move somewhere else (level.rs? progress.rs? difficulty?)
     // @XXX this seems a very bad design. The player can't just start at any level, for example
-    pub fn get_level(&self, _score: u32, total_lines_cleared: u32) -> u32 {
+    pub fn get_level(
+        &self,
+        _score: u32,
+        total_lines_cleared: u32,
+        total_line_clear_awards: u32,
+        tgm_level: u32,
+    ) -> u32 {
         match self.level_curve {
+            // https://meatfighter.com/nintendotetrisai/#Lines_and_Statistics
+            // https://tetris.wiki/Tetris_(NES,_Nintendo)#Level_transition
+            // Because of how the NES compares the lines (BCD) with the level, the first level up
+            // happens after min(10 * start + 10, max(100, 10 * start - 50)) lines instead of
+            // 10 * start + 10. After that, every 10 lines
             LevelCurve::Classic => {
-                // @TODO implement the real classic bugged logic
-                //https://meatfighter.com/nintendotetrisai/#Lines_and_Statistics
-
-                let lines_to_next_level_from_start_level = [
-                    0, 10, 20, 30, 40, 50, 60, 70, 80, 90, 90, 90, 90, 90, 90, 90,
-                    100, 110, 120, 130, 140, 150, 160, 170, 180, 190, 190, 190
-                ];
-
-                // @Remove
-                if self.start_level as usize >= lines_to_next_level_from_start_level.len() {
-                    return 200;
+                let start_level = self.start_level as u32;
+                let lines_to_first_level_up = (10 * start_level + 10)
+                    .min(100.max((10 * start_level).saturating_sub(50)));
+
+                if total_lines_cleared < lines_to_first_level_up {
+                    start_level
+                } else {
+                    start_level + 1 + (total_lines_cleared - lines_to_first_level_up) / 10
                 }
+            }
 
-                let line_diff_from_start =
-                    total_lines_cleared.saturating_sub(
-                        lines_to_next_level_from_start_level[self.start_level as usize]
-                    );
+            // https://tetris.wiki/Marathon#Fixed_goal_system
+            LevelCurve::Guideline => {
+                let start_level = (self.start_level as u32).max(1);
+                start_level + total_lines_cleared / 10
+            }
 
-                self.start_level as u32 + (line_diff_from_start / 10)
+            // https://tetris.wiki/Marathon#Variable_goal_system
+            // Each level needs 5 * level line clear awards to be completed
+            LevelCurve::GuidelineVariableGoal => {
+                let mut level = (self.start_level as u32).max(1);
+                let mut remaining_awards = total_line_clear_awards;
+                while remaining_awards >= 5 * level {
+                    remaining_awards -= 5 * level;
+                    level += 1;
+                }
+                level
             }
 
-            _ => { 0 }
+            LevelCurve::TGM => tgm_level,
         }
     }
+
+    // Line clear awards, used by the variable goal level curve. Back-to-back line clears award
+    // 1.5 times the lines
+    pub fn get_line_clear_awards(
+        &self,
+        lock_piece_result: LockedPieceResult,
+        is_back_to_back: bool,
+    ) -> u32 {
+        let awards = match lock_piece_result {
+            LockedPieceResult::Nothing => 0,
+
+            LockedPieceResult::Single(_) => 1,
+            LockedPieceResult::Double(_) => 3,
+            LockedPieceResult::Triple(_) => 5,
+            LockedPieceResult::Tetris(_) => 8,
+            LockedPieceResult::MultiLine { count, .. } => 8 + 3 * (count as u32 - 4),
+
+            LockedPieceResult::MiniTSpin          => 1,
+            LockedPieceResult::MiniTSpinSingle(_) => 2,
+            LockedPieceResult::MiniTSpinDouble(_) => 4,
+            LockedPieceResult::TSpin              => 4,
+            LockedPieceResult::TSpinSingle(_)     => 8,
+            LockedPieceResult::TSpinDouble(_)     => 12,
+            LockedPieceResult::TSpinTriple(_)     => 16,
+        };
+
+        if is_back_to_back { awards * 3 / 2 } else { awards }
+    }
     /*
     enum LevelProgressData {
         Classic { total_lines_cleared: u32 },
@@ -401,3 +583,39 @@ impl Rules {
     */
 }
 
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_classic_level_transition() {
+        let mut rules: Rules = RotationSystem::NRSR.into();
+
+        let first_level_up = |rules: &Rules| {
+            (0..300).find(|&lines| rules.get_level(0, lines, 0, 0) > rules.start_level as u32)
+        };
+
+        for (start_level, lines) in [(0, 10), (9, 100), (12, 100), (15, 100), (18, 130), (29, 240)] {
+            rules.start_level = start_level;
+            assert_eq!(first_level_up(&rules), Some(lines));
+        }
+
+        // After the first transition, every 10 lines
+        rules.start_level = 18;
+        assert_eq!(rules.get_level(0, 139, 0, 0), 19);
+        assert_eq!(rules.get_level(0, 140, 0, 0), 20);
+    }
+
+    #[test]
+    fn test_tgm_level_up() {
+        assert_eq!(tgm_level_up(0, 0), 1);
+        assert_eq!(tgm_level_up(97, 4), 101);
+
+        // Section stops
+        assert_eq!(tgm_level_up(99, 0), 99);
+        assert_eq!(tgm_level_up(99, 1), 100);
+        assert_eq!(tgm_level_up(998, 0), 998);
+        assert_eq!(tgm_level_up(998, 3), 999);
+    }
+}
@@ -1,67 +1,129 @@
 use crate::linalg::Vec2i;
 use crate::game::{
-    pieces::{ Piece, PieceVariant },
+    pieces::{ Piece, PieceVariant, get_custom_rotation_system },
     playfield::Playfield,
 };
 
 use super::*;
 
+#[derive(Copy, Clone, Debug, PartialEq, Eq, ImDraw)]
+pub enum RotationDirection {
+    Clockwise,
+    Counterclockwise,
+    Half, // 180 degrees
+}
+
+impl RotationDirection {
+    pub fn delta_rot(self) -> i32 {
+        match self {
+            RotationDirection::Clockwise        =>  1,
+            RotationDirection::Counterclockwise => -1,
+            RotationDirection::Half             =>  2,
+        }
+    }
+}
+
+// Kick tests used by 180 rotations, independent of the rotation system
+pub const ROTATION_180_KICKS_NAMES: &[&str] = &["NO KICKS", "SRS+"];
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, ImDraw)]
+pub enum Rotation180Kicks {
+    NoKicks, // only rotates in place
+    SRSPlus, // TETR.IO
+}
+
 // Move this to rules
+// Returns the index of the rotation test (wall kick) used, if the rotation was successful
 pub fn try_rotate_piece(
     piece: &mut Piece,
     pos: &mut Vec2i,
-    is_clockwise: bool,
+    direction: RotationDirection,
     playfield: &Playfield,
     rules: &Rules
-) -> bool {
-    let delta_rot = if is_clockwise { 1 } else { -1 };
+) -> Option<u8> {
+    let delta_rot = direction.delta_rot();
+
+    let is_clockwise = match direction {
+        RotationDirection::Clockwise        => true,
+        RotationDirection::Counterclockwise => false,
+        RotationDirection::Half => {
+            if !rules.has_180_rotation { return None; }
 
-    match rules.rotation_system {
+            let tests = get_180_rotation_tests(piece, rules);
+            return try_rotate_piece_with_tests(piece, pos, delta_rot, tests, playfield, rules);
+        }
+    };
+
+    let tests: &[Vec2i] = match rules.rotation_system {
         | RotationSystem::Original
         | RotationSystem::NRSR
         | RotationSystem::NRSL
         | RotationSystem::Sega
+        => &NO_TESTS,
 
-        // @TODO these systems have wall kicks
-        | RotationSystem::ARS
-        | RotationSystem::DTET
-
-        => {
-            for block_pos in piece.blocks_with_rot(piece.rot + delta_rot) {
-                let x = pos.x + block_pos.x;
-                let y = pos.y + block_pos.y;
-                if playfield.block(x, y).is_some() {
-                    return false;
-                }
+        RotationSystem::ARS => {
+            // L, J and T can't kick if the rotation is blocked by the center column
+            if !can_rotate_with_offset(piece, piece.rot + delta_rot, *pos, Vec2i::new(), playfield, rules) &&
+                is_ars_center_column_blocked(piece, piece.rot + delta_rot, *pos, playfield)
+            {
+                return None;
             }
 
+            get_ars_rotation_tests(piece, delta_rot, rules)
+        }
+
+        RotationSystem::SRS  => get_srs_rotation_tests(piece, is_clockwise),
+        RotationSystem::DTET => get_dtet_rotation_tests(is_clockwise),
+
+        RotationSystem::Custom(index) => {
+            get_custom_rotation_system(index).rotation_tests(piece.variant, piece.rot, is_clockwise)
+        }
+    };
+
+    try_rotate_piece_with_tests(piece, pos, delta_rot, tests, playfield, rules)
+}
+
+fn try_rotate_piece_with_tests(
+    piece: &mut Piece,
+    pos: &mut Vec2i,
+    delta_rot: i32,
+    tests: &[Vec2i],
+    playfield: &Playfield,
+    rules: &Rules
+) -> Option<u8> {
+    for (kick, delta_pos) in tests.iter().enumerate() {
+        if can_rotate_with_offset(piece, piece.rot + delta_rot, *pos, *delta_pos, playfield, rules) {
             piece.rot += delta_rot;
-            true
-        },
+            *pos += *delta_pos;
+            return Some(kick as u8);
+        }
+    }
 
-        | RotationSystem::SRS
-        => {
-            for delta_pos in get_srs_rotation_tests(piece, is_clockwise) {
-                let can_rotate = piece
-                    .blocks_with_rot(piece.rot + delta_rot)
-                    .iter()
-                    .all(|block_pos| {
-                        let x = pos.x + block_pos.x + delta_pos.x;
-                        let y = pos.y + block_pos.y + delta_pos.y;
-
-                        playfield.block(x, y).is_none()
-                    });
-
-                if can_rotate {
-                    piece.rot += delta_rot;
-                    *pos += *delta_pos;
-                    return true;
-                }
+    None
+}
+
+fn can_rotate_with_offset(
+    piece: &Piece,
+    rot: i32,
+    pos: Vec2i,
+    delta_pos: Vec2i,
+    playfield: &Playfield,
+    rules: &Rules
+) -> bool {
+    piece
+        .blocks_with_rot(rot)
+        .iter()
+        .all(|block_pos| {
+            let x = pos.x + block_pos.x + delta_pos.x;
+            let y = pos.y + block_pos.y + delta_pos.y;
+
+            // Sega: pieces can't rotate into the area above the visible playfield
+            if rules.does_ceiling_prevents_rotation && y >= playfield.visible_height as i32 {
+                return false;
             }
 
-            false
-        },
-    }
+            playfield.block(x, y).is_none()
+        })
 }
 
 // https://tetris.fandom.com/wiki/Wall_kick
@@ -258,11 +320,211 @@ fn get_srs_rotation_tests(piece: &Piece, is_clockwise: bool) -> &'static [Vec2i]
 
         PieceVariant::I => { &I_TESTS[rot][dir] }
         PieceVariant::O => { &NO_TESTS }
+
+        // Custom pieces only exist in custom rotation systems, which have their own kick tests
+        PieceVariant::Custom(_) => { &NO_TESTS }
     }
 }
 
-// https://tetris.fandom.com/wiki/Original_Rotation_System
 // https://tetris.fandom.com/wiki/TGM_Rotation
-// https://tetris.fandom.com/wiki/TGM_Rotation
-// https://tetris.fandom.com/wiki/Tetris_DX
+// If the basic rotation fails, the piece tries to move one column to the right, then one to the
+// left. The I piece doesn't kick. In TGM3 the I and T pieces can also kick up from the floor
+const ARS_TESTS: [Vec2i; 3] = [
+    Vec2i { x:  0, y:  0 },
+    Vec2i { x:  1, y:  0 },
+    Vec2i { x: -1, y:  0 },
+];
+
+// @TODO TGM3 limits the amount of floor kicks per piece
+const ARS_I_FLOOR_KICK_TESTS: [Vec2i; 3] = [
+    Vec2i { x:  0, y:  0 },
+    Vec2i { x:  0, y:  1 },
+    Vec2i { x:  0, y:  2 },
+];
+
+const ARS_T_FLOOR_KICK_TESTS: [Vec2i; 4] = [
+    Vec2i { x:  0, y:  0 },
+    Vec2i { x:  1, y:  0 },
+    Vec2i { x: -1, y:  0 },
+    Vec2i { x:  0, y:  1 },
+];
+
+fn get_ars_rotation_tests(piece: &Piece, delta_rot: i32, rules: &Rules) -> &'static [Vec2i] {
+    let target_rot = (((piece.rot + delta_rot) % 4) + 4) % 4;
+    match piece.variant {
+        // Floor kicks only happen when rotating to vertical (I) or pointing up (T)
+        PieceVariant::I if rules.has_floor_kicks && target_rot % 2 == 1 => &ARS_I_FLOOR_KICK_TESTS,
+        PieceVariant::T if rules.has_floor_kicks && target_rot == 2     => &ARS_T_FLOOR_KICK_TESTS,
+
+        PieceVariant::I => &NO_TESTS,
+        _ => &ARS_TESTS,
+    }
+}
+
+// L, J and T pieces don't kick if the first blocked cell of the rotated piece, checking its 3x3
+// bounding box from the top left in reading order, is in the center column
+fn is_ars_center_column_blocked(
+    piece: &Piece,
+    rot: i32,
+    pos: Vec2i,
+    playfield: &Playfield,
+) -> bool {
+    match piece.variant {
+        PieceVariant::L | PieceVariant::J | PieceVariant::T => {}
+        _ => return false,
+    }
+
+    let blocks = piece.blocks_with_rot(rot);
+    for y in (1..4).rev() {
+        for x in 0..3 {
+            let is_piece_block = blocks.iter().any(|block_pos| block_pos.x == x && block_pos.y == y);
+            if is_piece_block && playfield.block(pos.x + x, pos.y + y).is_some() {
+                return x == 1;
+            }
+        }
+    }
+
+    false
+}
+
 // https://tetris.fandom.com/wiki/DTET
+// If the basic rotation fails, the piece tries to move to the side of the rotation, then to the
+// other side, then down, down to the side of the rotation and down to the other side
+const DTET_CW_TESTS: [Vec2i; 6] = [
+    Vec2i { x:  0, y:  0 },
+    Vec2i { x:  1, y:  0 },
+    Vec2i { x: -1, y:  0 },
+    Vec2i { x:  0, y: -1 },
+    Vec2i { x:  1, y: -1 },
+    Vec2i { x: -1, y: -1 },
+];
+
+const DTET_CCW_TESTS: [Vec2i; 6] = [
+    Vec2i { x:  0, y:  0 },
+    Vec2i { x: -1, y:  0 },
+    Vec2i { x:  1, y:  0 },
+    Vec2i { x:  0, y: -1 },
+    Vec2i { x: -1, y: -1 },
+    Vec2i { x:  1, y: -1 },
+];
+
+fn get_dtet_rotation_tests(is_clockwise: bool) -> &'static [Vec2i] {
+    if is_clockwise { &DTET_CW_TESTS } else { &DTET_CCW_TESTS }
+}
+
+// https://tetris.wiki/TETR.IO#SRS+
+// The I piece doesn't kick on 180 rotations
+const SRS_PLUS_180_TESTS: [[Vec2i; 6]; 4] = [
+    // 0 -> 2
+    [
+        Vec2i { x:  0, y:  0 },
+        Vec2i { x:  0, y:  1 },
+        Vec2i { x:  1, y:  1 },
+        Vec2i { x: -1, y:  1 },
+        Vec2i { x:  1, y:  0 },
+        Vec2i { x: -1, y:  0 },
+    ],
+
+    // 1 -> 3
+    [
+        Vec2i { x:  0, y:  0 },
+        Vec2i { x:  1, y:  0 },
+        Vec2i { x:  1, y:  2 },
+        Vec2i { x:  1, y:  1 },
+        Vec2i { x:  0, y:  2 },
+        Vec2i { x:  0, y:  1 },
+    ],
+
+    // 2 -> 0
+    [
+        Vec2i { x:  0, y:  0 },
+        Vec2i { x:  0, y: -1 },
+        Vec2i { x: -1, y: -1 },
+        Vec2i { x:  1, y: -1 },
+        Vec2i { x: -1, y:  0 },
+        Vec2i { x:  1, y:  0 },
+    ],
+
+    // 3 -> 1
+    [
+        Vec2i { x:  0, y:  0 },
+        Vec2i { x: -1, y:  0 },
+        Vec2i { x: -1, y:  2 },
+        Vec2i { x: -1, y:  1 },
+        Vec2i { x:  0, y:  2 },
+        Vec2i { x:  0, y:  1 },
+    ],
+];
+
+fn get_180_rotation_tests(piece: &Piece, rules: &Rules) -> &'static [Vec2i] {
+    let rot = (((piece.rot % 4) + 4) % 4) as usize;
+    match (rules.rotation_180_kicks, piece.variant) {
+        (Rotation180Kicks::NoKicks, _) => &NO_TESTS,
+        (Rotation180Kicks::SRSPlus, PieceVariant::I | PieceVariant::O) => &NO_TESTS,
+        (Rotation180Kicks::SRSPlus, _) => &SRS_PLUS_180_TESTS[rot],
+    }
+}
+
+// https://tetris.fandom.com/wiki/Original_Rotation_System
+// https://tetris.fandom.com/wiki/Tetris_DX
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ars_rules() -> Rules {
+        let mut rules: Rules = RotationSystem::ARS.into();
+        rules.rotation_system = RotationSystem::ARS;
+        rules
+    }
+
+    #[test]
+    fn test_ars_wall_kick() {
+        let rules = ars_rules();
+        let playfield = Playfield::new(Vec2i { x: 10, y: 40 }, 20);
+
+        // T pointing left against the left wall kicks one column to the right
+        let mut piece = Piece { variant: PieceVariant::T, rot: 3, rotation_system: RotationSystem::ARS };
+        let mut pos = Vec2i { x: -1, y: 0 };
+        assert_eq!(try_rotate_piece(&mut piece, &mut pos, RotationDirection::Clockwise, &playfield, &rules), Some(1));
+        assert_eq!(pos, Vec2i { x: 0, y: 0 });
+    }
+
+    #[test]
+    fn test_ars_center_column() {
+        let rules = ars_rules();
+        let mut playfield = Playfield::new(Vec2i { x: 10, y: 40 }, 20);
+
+        // The rotation is blocked by the center column, so the T can't kick
+        playfield.set_block(4, 3, PieceVariant::I);
+        let mut piece = Piece { variant: PieceVariant::T, rot: 0, rotation_system: RotationSystem::ARS };
+        let mut pos = Vec2i { x: 3, y: 0 };
+        assert_eq!(try_rotate_piece(&mut piece, &mut pos, RotationDirection::Clockwise, &playfield, &rules), None);
+        assert_eq!(piece.rot, 0);
+    }
+
+    #[test]
+    fn test_180_rotation() {
+        let mut rules: Rules = RotationSystem::SRS.into();
+        let mut playfield = Playfield::new(Vec2i { x: 10, y: 40 }, 20);
+
+        let mut piece = Piece { variant: PieceVariant::T, rot: 0, rotation_system: RotationSystem::SRS };
+        let mut pos = Vec2i { x: 3, y: 0 };
+
+        rules.has_180_rotation = false;
+        assert_eq!(try_rotate_piece(&mut piece, &mut pos, RotationDirection::Half, &playfield, &rules), None);
+
+        // The T pointing down doesn't fit in place, so it kicks one row up
+        rules.has_180_rotation = true;
+        rules.rotation_180_kicks = Rotation180Kicks::SRSPlus;
+        playfield.set_block(4, 0, PieceVariant::I);
+        assert_eq!(try_rotate_piece(&mut piece, &mut pos, RotationDirection::Half, &playfield, &rules), Some(1));
+        assert_eq!(piece.rot, 2);
+        assert_eq!(pos, Vec2i { x: 3, y: 1 });
+
+        rules.rotation_180_kicks = Rotation180Kicks::NoKicks;
+        piece.rot = 0;
+        pos = Vec2i { x: 3, y: 0 };
+        assert_eq!(try_rotate_piece(&mut piece, &mut pos, RotationDirection::Half, &playfield, &rules), None);
+    }
+}
@@ -8,10 +8,59 @@ pub enum ScoringRule {
     Guideline,
 }
 
-// @TODO T-spin, combo, back-to-back
+impl ScoringRule {
+    // The NES score counter has 6 BCD digits, so it stops at 999999 (max out)
+    pub fn max_score(self) -> u32 {
+        match self {
+            ScoringRule::Classic   => 999_999,
+            ScoringRule::Guideline => u32::MAX,
+        }
+    }
+}
+
+// Combo and back-to-back state, kept across locked pieces
+#[derive(Copy, Clone, Debug, Default, ImDraw)]
+pub struct ScoringState {
+    // Amount of consecutive pieces that cleared lines, minus one. None if the last piece didn't
+    // clear any lines
+    pub combo: Option<u32>,
+
+    // Amount of consecutive difficult line clears (Tetris or T-Spin line clears), minus one. None
+    // if the last line clear wasn't difficult. T-Spins without lines don't break the chain
+    pub back_to_back: Option<u32>,
+}
+
+impl ScoringState {
+    pub fn update(&mut self, lock_piece_result: LockedPieceResult) {
+        if lock_piece_result.has_lines_to_clear() {
+            self.combo = Some(self.combo.map_or(0, |combo| combo + 1));
+
+            if lock_piece_result.is_difficult_line_clear() {
+                self.back_to_back = Some(self.back_to_back.map_or(0, |b2b| b2b + 1));
+            } else {
+                self.back_to_back = None;
+            }
+        } else {
+            self.combo = None;
+        }
+    }
+
+    pub fn is_back_to_back(&self) -> bool {
+        matches!(self.back_to_back, Some(b2b) if b2b > 0)
+    }
+
+    // If the (already updated) state makes this line clear a back-to-back line clear
+    pub fn is_back_to_back_line_clear(&self, lock_piece_result: LockedPieceResult) -> bool {
+        self.is_back_to_back() && lock_piece_result.is_difficult_line_clear()
+    }
+}
+
+// Expects the scoring state already updated with the locked piece
 pub fn lock_piece_score(
     level: u32,
     locked_piece: LockedPiece,
+    scoring_state: ScoringState,
+    is_perfect_clear: bool,
     rules: &Rules
 ) -> u32 {
     match rules.scoring_curve {
@@ -21,13 +70,14 @@ pub fn lock_piece_score(
             let hard_drop_score = min(40, 2 * locked_piece.hard_drop_steps) as u32;
 
             // Line clear score
-            let clear_score = match locked_piece.lock_piece_result {
-                // @TODO add T-Spin by line clear amount
-                LockedPieceResult::Single(_) => 40,
-                LockedPieceResult::Double(_) => 100,
-                LockedPieceResult::Triple(_) => 300,
-                LockedPieceResult::Tetris(_) => 1200,
-                _ => 0,
+            // Classic scoring has no T-Spin bonus, so only the amount of lines cleared matters.
+            // Custom pieces clearing more than 4 lines score as a Tetris
+            let clear_score = match locked_piece.lock_piece_result.get_lines_to_clear_slice().len() {
+                0 => 0,
+                1 => 40,
+                2 => 100,
+                3 => 300,
+                _ => 1200,
             };
 
             let clear_score = clear_score * (level + 1);
@@ -35,6 +85,134 @@ pub fn lock_piece_score(
             clear_score + soft_drop_score + hard_drop_score
         },
 
-        _ => { 0 }
+        // https://tetris.wiki/Scoring#Recent_guideline_compatible_games
+        ScoringRule::Guideline => {
+            // Guideline levels start at 1
+            let level = level.max(1);
+
+            // Drops
+            let soft_drop_score = locked_piece.soft_drop_steps as u32;
+            let hard_drop_score = 2 * locked_piece.hard_drop_steps as u32;
+
+            // Line clear score
+            let clear_score = match locked_piece.lock_piece_result {
+                LockedPieceResult::Nothing => 0,
+
+                LockedPieceResult::Single(_) => 100,
+                LockedPieceResult::Double(_) => 300,
+                LockedPieceResult::Triple(_) => 500,
+                LockedPieceResult::Tetris(_) => 800,
+                // @TODO there's no guideline score for more than 4 lines, each extra line adds
+                //       the difference between a Tetris and a Triple
+                LockedPieceResult::MultiLine { count, .. } => 800 + 300 * (count as u32 - 4),
+
+                LockedPieceResult::MiniTSpin          => 100,
+                LockedPieceResult::MiniTSpinSingle(_) => 200,
+                LockedPieceResult::MiniTSpinDouble(_) => 400,
+                LockedPieceResult::TSpin              => 400,
+                LockedPieceResult::TSpinSingle(_)     => 800,
+                LockedPieceResult::TSpinDouble(_)     => 1200,
+                LockedPieceResult::TSpinTriple(_)     => 1600,
+            };
+
+            let is_back_to_back =
+                scoring_state.is_back_to_back_line_clear(locked_piece.lock_piece_result);
+
+            let clear_score = clear_score * level;
+            let clear_score = if is_back_to_back { clear_score * 3 / 2 } else { clear_score };
+
+            // Combo (REN)
+            let combo_score = 50 * scoring_state.combo.unwrap_or(0) * level;
+
+            // Perfect clear
+            let perfect_clear_score = if is_perfect_clear {
+                let lines_cleared = locked_piece.lock_piece_result.get_lines_to_clear_slice().len();
+                match lines_cleared {
+                    0 => 0,
+                    1 => 800,
+                    2 => 1200,
+                    3 => 1800,
+                    _ if is_back_to_back => 3200,
+                    _ => 2000,
+                }
+            } else {
+                0
+            };
+
+            let perfect_clear_score = perfect_clear_score * level;
+
+            clear_score + combo_score + perfect_clear_score + soft_drop_score + hard_drop_score
+        },
+    }
+}
+
+// Cascade chains score their lines as a regular line clear, multiplied by the chain number + 1
+pub fn cascade_chain_score(
+    level: u32,
+    chain: u32, // starts at 1
+    lines_cleared: u8,
+    rules: &Rules
+) -> u32 {
+    let lines_cleared = min(4, lines_cleared) as usize;
+    match rules.scoring_curve {
+        ScoringRule::Classic => {
+            [0, 40, 100, 300, 1200][lines_cleared] * (level + 1) * (chain + 1)
+        }
+
+        ScoringRule::Guideline => {
+            [0, 100, 300, 500, 800][lines_cleared] * level.max(1) * (chain + 1)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::linalg::Vec2i;
+    use crate::game::pieces::{Piece, PieceVariant};
+    use super::super::lock::LastPieceAction;
+
+    fn locked_piece(lock_piece_result: LockedPieceResult) -> LockedPiece {
+        LockedPiece {
+            piece: Piece { variant: PieceVariant::I, rot: 0, rotation_system: RotationSystem::SRS },
+            pos: Vec2i::new(),
+            soft_drop_steps: 0,
+            hard_drop_steps: 0,
+            last_piece_action: LastPieceAction::Movement,
+            lock_piece_result,
+        }
+    }
+
+    #[test]
+    fn test_guideline_back_to_back_and_combo() {
+        let mut rules: Rules = RotationSystem::SRS.into();
+        rules.scoring_curve = ScoringRule::Guideline;
+
+        let mut state = ScoringState::default();
+
+        let tetris = locked_piece(LockedPieceResult::Tetris([0, 1, 2, 3]));
+        state.update(tetris.lock_piece_result);
+        assert_eq!(lock_piece_score(2, tetris, state, false, &rules), 1600);
+
+        // back-to-back Tetris, second piece in a combo
+        state.update(tetris.lock_piece_result);
+        assert_eq!(state.combo, Some(1));
+        assert_eq!(lock_piece_score(2, tetris, state, false, &rules), 2400 + 100);
+
+        // T-Spin without lines breaks the combo but keeps the back-to-back
+        let t_spin = locked_piece(LockedPieceResult::TSpin);
+        state.update(t_spin.lock_piece_result);
+        assert_eq!(state.combo, None);
+        assert_eq!(lock_piece_score(1, t_spin, state, false, &rules), 400);
+
+        let t_spin_double = locked_piece(LockedPieceResult::TSpinDouble([0, 1]));
+        state.update(t_spin_double.lock_piece_result);
+        assert_eq!(lock_piece_score(1, t_spin_double, state, false, &rules), 1800);
+
+        // Single breaks the back-to-back
+        let single = locked_piece(LockedPieceResult::Single([0]));
+        state.update(single.lock_piece_result);
+        assert_eq!(state.back_to_back, None);
+        assert_eq!(lock_piece_score(1, single, state, true, &rules), 100 + 50 + 800);
     }
 }
@@ -0,0 +1,139 @@
+use crate::app::ImDraw;
+
+use super::*;
+
+// Some games change the timings with the level (faster levels = smaller ARE, line clear delays and
+// faster DAS) or with the height the last piece locked. The timing curve selects the timings for
+// each level, falling back to the Rules timings.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, ImDraw)]
+pub enum TimingCurve {
+    Constant,   // Rules timings for all levels
+    NES,        // ARE depends on the height the last piece locked
+    TGM2Master, // Levels are expected to be TGM levels (0-999)
+}
+
+const NES_FRAME_DURATION: u64 = 16_639; // 60.0988 Hz
+const TGM_FRAME_DURATION: u64 = 16_667; // 60 Hz
+
+struct TimingSection {
+    level: u32,      // first level of the section
+    spawn_delay: u64, // frames
+    line_spawn_delay: u64, // frames. ARE after a line clear
+    das_repeat_delay: u64, // frames
+    lock_delay: u64, // frames
+    line_clear_delay: u64, // frames
+}
+
+// https://tetris.wiki/Tetris_The_Absolute_The_Grand_Master_2#Master
+const TGM2_MASTER_TIMINGS: &[TimingSection] = &[
+    TimingSection { level: 0,   spawn_delay: 25, line_spawn_delay: 25, das_repeat_delay: 14, lock_delay: 30, line_clear_delay: 40 },
+    TimingSection { level: 500, spawn_delay: 25, line_spawn_delay: 25, das_repeat_delay: 8,  lock_delay: 30, line_clear_delay: 25 },
+    TimingSection { level: 600, spawn_delay: 25, line_spawn_delay: 16, das_repeat_delay: 8,  lock_delay: 30, line_clear_delay: 16 },
+    TimingSection { level: 700, spawn_delay: 16, line_spawn_delay: 12, das_repeat_delay: 8,  lock_delay: 30, line_clear_delay: 12 },
+    TimingSection { level: 800, spawn_delay: 12, line_spawn_delay: 6,  das_repeat_delay: 8,  lock_delay: 30, line_clear_delay: 6  },
+    TimingSection { level: 900, spawn_delay: 12, line_spawn_delay: 6,  das_repeat_delay: 6,  lock_delay: 17, line_clear_delay: 6  },
+];
+
+fn get_timing_section(timings: &'static [TimingSection], level: u32) -> &'static TimingSection {
+    timings.iter()
+        .rev()
+        .find(|section| level >= section.level)
+        .unwrap_or(&timings[0])
+}
+
+impl Rules {
+    // lock_row: lowest row of the last locked piece, if any
+    pub fn get_spawn_delay(&self, level: u32, lock_row: Option<i32>, has_cleared_lines: bool) -> u64 {
+        match self.timing_curve {
+            TimingCurve::Constant => self.spawn_delay,
+
+            // https://tetris.wiki/Tetris_(NES,_Nintendo)
+            // Pieces that lock in the bottom two rows are followed by 10 frames of entry delay, and
+            // each group of 4 rows above that has an entry delay 2 frames longer than the last
+            TimingCurve::NES => {
+                match lock_row {
+                    Some(lock_row) => {
+                        let row_group = ((lock_row.max(0) as u64 + 2) / 4).min(4);
+                        (10 + 2 * row_group) * NES_FRAME_DURATION
+                    }
+                    None => self.spawn_delay,
+                }
+            }
+
+            TimingCurve::TGM2Master => {
+                let section = get_timing_section(TGM2_MASTER_TIMINGS, level);
+                if has_cleared_lines {
+                    section.line_spawn_delay * TGM_FRAME_DURATION
+                } else {
+                    section.spawn_delay * TGM_FRAME_DURATION
+                }
+            }
+        }
+    }
+
+    pub fn get_line_clear_delay(&self, level: u32) -> u64 {
+        match self.timing_curve {
+            TimingCurve::Constant | TimingCurve::NES => self.line_clear_delay,
+            TimingCurve::TGM2Master => {
+                get_timing_section(TGM2_MASTER_TIMINGS, level).line_clear_delay * TGM_FRAME_DURATION
+            }
+        }
+    }
+
+    // Keeps the lock delay rule, only changing its duration
+    pub fn get_lock_delay(&self, level: u32) -> LockDelayRule {
+        match self.timing_curve {
+            TimingCurve::Constant | TimingCurve::NES => self.lock_delay,
+            TimingCurve::TGM2Master => {
+                let duration =
+                    get_timing_section(TGM2_MASTER_TIMINGS, level).lock_delay * TGM_FRAME_DURATION;
+                self.lock_delay.with_duration(duration)
+            }
+        }
+    }
+
+    pub fn get_das_repeat_delay(&self, level: u32) -> u64 {
+        match self.timing_curve {
+            TimingCurve::Constant | TimingCurve::NES => self.das_repeat_delay,
+            TimingCurve::TGM2Master => {
+                get_timing_section(TGM2_MASTER_TIMINGS, level).das_repeat_delay * TGM_FRAME_DURATION
+            }
+        }
+    }
+
+    pub fn get_das_repeat_interval(&self, _level: u32) -> u64 {
+        match self.timing_curve {
+            TimingCurve::Constant | TimingCurve::NES => self.das_repeat_interval,
+            TimingCurve::TGM2Master => TGM_FRAME_DURATION,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_nes_spawn_delay() {
+        let mut rules: Rules = RotationSystem::NRSR.into();
+        rules.timing_curve = TimingCurve::NES;
+
+        assert_eq!(rules.get_spawn_delay(0, Some(0),  false), 10 * NES_FRAME_DURATION);
+        assert_eq!(rules.get_spawn_delay(0, Some(1),  false), 10 * NES_FRAME_DURATION);
+        assert_eq!(rules.get_spawn_delay(0, Some(2),  false), 12 * NES_FRAME_DURATION);
+        assert_eq!(rules.get_spawn_delay(0, Some(13), false), 16 * NES_FRAME_DURATION);
+        assert_eq!(rules.get_spawn_delay(0, Some(19), false), 18 * NES_FRAME_DURATION);
+    }
+
+    #[test]
+    fn test_tgm2_master_timings() {
+        let mut rules: Rules = RotationSystem::ARS.into();
+        rules.timing_curve = TimingCurve::TGM2Master;
+
+        assert_eq!(rules.get_line_clear_delay(0),   40 * TGM_FRAME_DURATION);
+        assert_eq!(rules.get_line_clear_delay(499), 40 * TGM_FRAME_DURATION);
+        assert_eq!(rules.get_line_clear_delay(999), 6 * TGM_FRAME_DURATION);
+        assert_eq!(rules.get_spawn_delay(650, None, true), 16 * TGM_FRAME_DURATION);
+        assert_eq!(rules.get_das_repeat_delay(950), 6 * TGM_FRAME_DURATION);
+    }
+}
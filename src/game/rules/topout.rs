@@ -22,6 +22,25 @@ bitflags! {
 
 impl_imdraw_todo!(TopOutRule);
 
+#[derive(Copy, Clone, Debug, PartialEq, Eq, ImDraw)]
+pub enum TopOutReason {
+    BlockOut,
+    LockOut,
+    PartialLockOut,
+    GarbageOut,
+}
+
+impl TopOutReason {
+    pub fn name(self) -> &'static str {
+        match self {
+            TopOutReason::BlockOut       => "block out",
+            TopOutReason::LockOut        => "lock out",
+            TopOutReason::PartialLockOut => "partial lock out",
+            TopOutReason::GarbageOut     => "garbage out",
+        }
+    }
+}
+
 // @TODO Rules method?
 pub fn blocked_out(
     piece: &Piece,
@@ -35,22 +54,43 @@ pub fn blocked_out(
     !try_move_piece(piece, &mut pos, playfield, 0, 0)
 }
 
+// Lock out: the piece locked completely above the visible playfield.
+// Partial lock out: the piece locked with any block above the visible playfield.
 pub fn locked_out(
     piece: &Piece,
     pos: Vec2i,
     playfield_visible_height: u8,
     rules: &Rules
-) -> bool {
-    if rules.top_out_rule.intersects(TopOutRule::LOCK_OUT | TopOutRule::PARTIAL_LOCK_OUT) {
-        let blocks_locked_out = blocks_out_of_playfield(piece, pos, playfield_visible_height);
-        if rules.top_out_rule.contains(TopOutRule::LOCK_OUT) {
-            return blocks_locked_out == 4;
-        } else {
-            return blocks_locked_out > 0;
-        }
+) -> Option<TopOutReason> {
+    let blocks_locked_out = blocks_out_of_playfield(piece, pos, playfield_visible_height);
+    if blocks_locked_out == 0 { return None; }
+
+    if rules.top_out_rule.contains(TopOutRule::LOCK_OUT) &&
+        blocks_locked_out as usize == piece.blocks().len()
+    {
+        return Some(TopOutReason::LockOut);
+    }
+
+    if rules.top_out_rule.contains(TopOutRule::PARTIAL_LOCK_OUT) {
+        return Some(TopOutReason::PartialLockOut);
     }
 
-    false
+    None
+}
+
+// Garbage out: the garbage rows push blocks of the stack above the top of the playfield grid
+pub fn garbaged_out(
+    playfield: &Playfield,
+    garbage_rows: u8,
+    rules: &Rules
+) -> bool {
+    if !rules.top_out_rule.contains(TopOutRule::GARBAGE_OUT) { return false; }
+
+    let grid_size = playfield.grid_size;
+    let first_row = (grid_size.y - garbage_rows as i32).max(0);
+    (first_row..grid_size.y).any(|y| {
+        (0..grid_size.x).any(|x| playfield.block(x, y).is_some())
+    })
 }
 
 pub fn blocks_out_of_playfield(
@@ -63,3 +103,31 @@ pub fn blocks_out_of_playfield(
             acc + (pos.y + block_pos.y >= playfield_visible_height as i32) as u8
         })
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::{
+        pieces::PieceVariant,
+        rules::RotationSystem,
+    };
+
+    #[test]
+    fn test_locked_out() {
+        let mut rules: Rules = RotationSystem::SRS.into();
+        let piece = Piece { variant: PieceVariant::O, rot: 0, rotation_system: RotationSystem::SRS };
+
+        // Find a position with only part of the piece above the visible playfield
+        let mut pos = Vec2i { x: 3, y: 20 };
+        while blocks_out_of_playfield(&piece, pos, 20) != 2 { pos.y -= 1; }
+
+        rules.top_out_rule = TopOutRule::LOCK_OUT;
+        assert_eq!(locked_out(&piece, pos, 20, &rules), None);
+
+        rules.top_out_rule = TopOutRule::LOCK_OUT | TopOutRule::PARTIAL_LOCK_OUT;
+        assert_eq!(locked_out(&piece, pos, 20, &rules), Some(TopOutReason::PartialLockOut));
+
+        pos.y += 1;
+        assert_eq!(locked_out(&piece, pos, 20, &rules), Some(TopOutReason::LockOut));
+    }
+}
@@ -3,7 +3,7 @@ use crate::app::*;
 use crate::linalg::*;
 
 use crate::game::{
-    pieces::{ Piece, PIECES },
+    pieces::{ Piece, get_custom_rotation_system, get_custom_rotation_systems, get_piece_set },
     rules::RotationSystem,
 };
 
@@ -39,8 +39,13 @@ impl SceneTrait for DebugPiecesScene {
         app: &mut App,
         persistent: &mut Self::PersistentData
     ) {
+        let rotation_system_name = match self.rotation_system {
+            RotationSystem::Custom(index) => get_custom_rotation_system(index).name.clone(),
+            rotation_system => format!("{:?}", rotation_system),
+        };
+
         app.queue_draw_text(
-            &format!("{:?}", self.rotation_system),
+            &rotation_system_name,
             TransformBuilder::new().pos_xy(10.0, 42.0).layer(1000).build(),
             32.,
             WHITE,
@@ -57,115 +62,26 @@ impl SceneTrait for DebugPiecesScene {
             None,
         );
 
-        let x = 100;
-        let y = 100;
-
-        draw_piece_window(
-            Vec2i { x, y },
-            Piece {
-                variant: PIECES[0],
-                rot: self.rot,
-                rotation_system: self.rotation_system,
-            },
-            persistent.pixel_scale,
-            self.is_centered,
-            self.has_grid,
-            &mut app.batch(),
-            persistent
-        );
-
+        // 4 pieces per row
         let pixel_scale = persistent.pixel_scale as i32;
-
-        let x = x + pixel_scale * BLOCK_SCALE as i32 * 6;
-        draw_piece_window(
-            Vec2i { x , y },
-            Piece {
-                variant: PIECES[1],
-                rot: self.rot,
-                rotation_system: self.rotation_system,
-            },
-            persistent.pixel_scale,
-            self.is_centered,
-            self.has_grid,
-            &mut app.batch(),
-            persistent
-        );
-
-        let x = x + pixel_scale * BLOCK_SCALE as i32 * 6;
-        draw_piece_window(
-            Vec2i { x , y },
-            Piece {
-                variant: PIECES[2],
-                rot: self.rot,
-                rotation_system: self.rotation_system,
-            },
-            persistent.pixel_scale,
-            self.is_centered,
-            self.has_grid,
-            &mut app.batch(),
-            persistent
-        );
-
-        let x = x + pixel_scale * BLOCK_SCALE as i32 * 6;
-        draw_piece_window(
-            Vec2i { x , y },
-            Piece {
-                variant: PIECES[3],
-                rot: self.rot,
-                rotation_system: self.rotation_system,
-            },
-            persistent.pixel_scale,
-            self.is_centered,
-            self.has_grid,
-            &mut app.batch(),
-            persistent
-        );
-
-        let x = 100;
-        let y = y + pixel_scale * BLOCK_SCALE as i32 * 6;
-        draw_piece_window(
-            Vec2i { x , y },
-            Piece {
-                variant: PIECES[4],
-                rot: self.rot,
-                rotation_system: self.rotation_system,
-            },
-            persistent.pixel_scale,
-            self.is_centered,
-            self.has_grid,
-            &mut app.batch(),
-            persistent
-        );
-
-        let x = x + pixel_scale * BLOCK_SCALE as i32 * 6;
-        draw_piece_window(
-            Vec2i { x , y },
-            Piece {
-                variant: PIECES[5],
-                rot: self.rot,
-                rotation_system: self.rotation_system,
-            },
-            persistent.pixel_scale,
-            self.is_centered,
-            self.has_grid,
-            &mut app.batch(),
-            persistent
-        );
-
-        let x = x + pixel_scale * BLOCK_SCALE as i32 * 6;
-        draw_piece_window(
-            Vec2i { x , y },
-            Piece {
-                variant: PIECES[6],
-                rot: self.rot,
-                rotation_system: self.rotation_system,
-            },
-            persistent.pixel_scale,
-            self.is_centered,
-            self.has_grid,
-            &mut app.batch(),
-            persistent
-        );
+        let piece_window_size = pixel_scale * BLOCK_SCALE as i32 * 6;
+        for (i, &variant) in get_piece_set(self.rotation_system).iter().enumerate() {
+            let x = 100 + piece_window_size * (i % 4) as i32;
+            let y = 100 + piece_window_size * (i / 4) as i32;
+            draw_piece_window(
+                Vec2i { x, y },
+                Piece {
+                    variant,
+                    rot: self.rot,
+                    rotation_system: self.rotation_system,
+                },
+                persistent.pixel_scale,
+                self.is_centered,
+                self.has_grid,
+                &mut app.batch(),
+                persistent
+            );
+        }
     }
 
     fn handle_input(
@@ -254,19 +170,31 @@ impl DebugPiecesScene {
             RotationSystem::Sega     => RotationSystem::ARS,
             RotationSystem::ARS      => RotationSystem::SRS,
             RotationSystem::SRS      => RotationSystem::DTET,
-            RotationSystem::DTET     => RotationSystem::Original,
+            RotationSystem::DTET     => {
+                get_custom_rotation_systems().first().copied().unwrap_or(RotationSystem::Original)
+            }
+            RotationSystem::Custom(index) => {
+                get_custom_rotation_systems()
+                    .get(index as usize + 1)
+                    .copied()
+                    .unwrap_or(RotationSystem::Original)
+            }
         };
     }
 
     fn previous_rotation_systems(&mut self) {
         self.rotation_system = match self.rotation_system {
-            RotationSystem::Original => RotationSystem::DTET,
+            RotationSystem::Original => {
+                get_custom_rotation_systems().last().copied().unwrap_or(RotationSystem::DTET)
+            }
             RotationSystem::NRSR     => RotationSystem::Original,
             RotationSystem::NRSL     => RotationSystem::NRSR,
             RotationSystem::Sega     => RotationSystem::NRSL,
             RotationSystem::ARS      => RotationSystem::Sega,
             RotationSystem::SRS      => RotationSystem::ARS,
             RotationSystem::DTET     => RotationSystem::SRS,
+            RotationSystem::Custom(0) => RotationSystem::DTET,
+            RotationSystem::Custom(index) => RotationSystem::Custom(index - 1),
         };
     }
 }
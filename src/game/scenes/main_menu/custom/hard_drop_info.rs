@@ -2,18 +2,25 @@ use super::*;
 use crate::game::{
     input::*,
     pieces::PieceVariant,
-    rules::GravityCurve,
+    rules::{GravityCurve, HardDropRule},
 };
 
 pub struct HardDropPreview;
 
 impl HardDropPreview {
     // @TODO copy current rules from the menu
-    pub fn new() -> PlayfieldAnimation {
+    pub fn new(hard_drop_rule: HardDropRule) -> PlayfieldAnimation {
         // @Maybe using only SRS for previews for now, maybe we should show the rotation system the
         //        player has chosen?
         let mut rules: Rules = RotationSystem::SRS.into();
         rules.gravity_curve = GravityCurve::NoGravity;
+        rules.hard_drop_rule = hard_drop_rule;
+
+        // A firm dropped piece only locks after the lock delay
+        let lock_delay = match hard_drop_rule {
+            HardDropRule::FirmDrop => 500_000,
+            _ => 0,
+        };
 
         let playfield = Playfield::new(Vec2i { x: 5, y: 40 }, 8);
 
@@ -33,12 +40,14 @@ impl HardDropPreview {
             .click(KEY_RIGHT)
             .wait(300_000)
             .click(KEY_HARD_DROP)
+            .wait(lock_delay)
 
             // S
             .wait(300_000)
             .click(KEY_LEFT)
             .wait(300_000)
             .click(KEY_HARD_DROP)
+            .wait(lock_delay)
 
             .wait(rules.line_clear_delay)
 
@@ -51,6 +60,7 @@ impl HardDropPreview {
             .click(KEY_RIGHT)
             .wait(300_000)
             .click(KEY_HARD_DROP)
+            .wait(lock_delay)
 
             // L
             .wait(300_000)
@@ -61,6 +71,7 @@ impl HardDropPreview {
             .click(KEY_LEFT)
             .wait(300_000)
             .click(KEY_HARD_DROP)
+            .wait(lock_delay)
 
             .wait(rules.line_clear_delay)
 
@@ -69,6 +80,7 @@ impl HardDropPreview {
             .click(KEY_LEFT)
             .wait(300_000)
             .click(KEY_HARD_DROP)
+            .wait(lock_delay)
 
             .wait(rules.line_clear_delay)
 
@@ -97,6 +109,11 @@ pub fn show_custom_rules_info_hard_drop(
         rotated afterwards. It is a higher scoring move than a soft drop.";
     ui::Text::builder(text).multiline(true).build(app);
 
+    let text =
+        "A firm drop also drops the Tetromino instantly, but it doesn't lock it, \
+        so it can still be moved and rotated until the lock delay ends.";
+    ui::Text::builder(text).multiline(true).build(app);
+
     // Render example playfield
     let mut batch = Batch::new();
 
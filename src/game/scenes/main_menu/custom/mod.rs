@@ -19,12 +19,13 @@ pub use playfield_animation::*;
 #[derive(Debug, ImDraw)]
 pub enum FocusedRule {
     RotationSystem,
+    CeilingPreventsRotation,
+    FloorKicks,
+    Rotation180,
+    Rotation180Kicks,
 
     HardDrop(PlayfieldAnimation),
-    HardDropLock,
-
     SoftDrop(PlayfieldAnimation),
-    SoftDropLock,
     SoftDropInterval(PlayfieldAnimation),
 
     LockDelayRule,
@@ -34,6 +35,10 @@ pub enum FocusedRule {
 
     Ghost(PlayfieldAnimation),
 
+    PlayfieldWidth,
+    PlayfieldHeight,
+    PlayfieldVisibleHeight,
+
     SpawnRow,
     SpawnDrop,
     SpawnDelay,
@@ -50,6 +55,11 @@ pub enum FocusedRule {
     TopOutPartialLockOut,
     TopOutGarbageOut,
 
+    AttackTable,
+    GarbageHoleRule,
+    GarbageDelay,
+    GarbageCancel,
+
     DelayedAutoShift,
     AutoRepeatRate,
 
@@ -135,8 +145,19 @@ impl MainMenuScene {
                 RotationSystem::ARS      => 4,
                 RotationSystem::SRS      => 5,
                 RotationSystem::DTET     => 6,
+                RotationSystem::Custom(index) => ROTATION_SYSTEM_NAMES.len() + index as usize,
             };
-            let state = ui::Combobox::builder("ROTATION SYSTEM", ROTATION_SYSTEM_NAMES)
+
+            // Rotation systems loaded from files go after the builtin ones
+            let rotation_system_names: Vec<&str> = ROTATION_SYSTEM_NAMES.iter()
+                .copied()
+                .chain(get_custom_rotation_systems().into_iter().map(|rotation_system| match rotation_system {
+                    RotationSystem::Custom(index) => get_custom_rotation_system(index).name.as_str(),
+                    _ => unreachable!(),
+                }))
+                .collect();
+
+            let state = ui::Combobox::builder("ROTATION SYSTEM", &rotation_system_names)
                 .build_with_placer(&mut rotation_system, &mut rules_box_placer, app);
             self.custom_rules.rotation_system = match rotation_system {
                 0 => RotationSystem::Original,
@@ -145,40 +166,84 @@ impl MainMenuScene {
                 3 => RotationSystem::Sega,
                 4 => RotationSystem::ARS,
                 5 => RotationSystem::SRS,
-                _ => RotationSystem::DTET,
+                6 => RotationSystem::DTET,
+                index => RotationSystem::Custom((index - ROTATION_SYSTEM_NAMES.len()) as u8),
             };
 
             change_rule_info!(self, state, RotationSystem);
 
-            // Hard drop
+            let state = ui::Checkbox::builder("  CEILING PREVENTS ROTATION")
+                .build_with_placer(&mut self.custom_rules.does_ceiling_prevents_rotation, &mut rules_box_placer, app);
 
-            let state = ui::Checkbox::builder("HARD DROP")
-                .build_with_placer(&mut self.custom_rules.has_hard_drop, &mut rules_box_placer, app);
+            change_rule_info!(self, state, CeilingPreventsRotation);
 
-            change_rule_info!(self, state, HardDrop, HardDropPreview::new());
+            let state = ui::Checkbox::builder("  FLOOR KICKS")
+                .disabled(self.custom_rules.rotation_system != RotationSystem::ARS)
+                .build_with_placer(&mut self.custom_rules.has_floor_kicks, &mut rules_box_placer, app);
 
-            let state = ui::Checkbox::builder("  FIRM DROP")
-                //.disabled(!self.custom_rules.has_hard_drop)
-                .disabled(true)
-                .build_with_placer(&mut self.custom_rules.has_hard_drop_lock, &mut rules_box_placer, app);
+            change_rule_info!(self, state, FloorKicks);
 
-            change_rule_info!(self, state, HardDropLock);
+            let state = ui::Checkbox::builder("180 ROTATION")
+                .build_with_placer(&mut self.custom_rules.has_180_rotation, &mut rules_box_placer, app);
 
-            // Soft drop
+            change_rule_info!(self, state, Rotation180);
 
-            let state = ui::Checkbox::builder("SOFT DROP")
-                .build_with_placer(&mut self.custom_rules.has_soft_drop, &mut rules_box_placer, app);
+            let mut rotation_180_kicks = match self.custom_rules.rotation_180_kicks {
+                Rotation180Kicks::NoKicks => 0,
+                Rotation180Kicks::SRSPlus => 1,
+            };
+            let state = ui::Combobox::builder("  180 KICKS", ROTATION_180_KICKS_NAMES)
+                .disabled(!self.custom_rules.has_180_rotation)
+                .build_with_placer(&mut rotation_180_kicks, &mut rules_box_placer, app);
+            self.custom_rules.rotation_180_kicks = match rotation_180_kicks {
+                0 => Rotation180Kicks::NoKicks,
+                _ => Rotation180Kicks::SRSPlus,
+            };
+
+            change_rule_info!(self, state, Rotation180Kicks);
+
+            // Hard drop
+
+            let mut hard_drop = match self.custom_rules.hard_drop_rule {
+                HardDropRule::No       => 0,
+                HardDropRule::HardDrop => 1,
+                HardDropRule::FirmDrop => 2,
+            };
+            let state = ui::Combobox::builder("HARD DROP", HARD_DROP_RULE_NAMES)
+                .build_with_placer(&mut hard_drop, &mut rules_box_placer, app);
+            self.custom_rules.hard_drop_rule = match hard_drop {
+                0 => HardDropRule::No,
+                1 => HardDropRule::HardDrop,
+                _ => HardDropRule::FirmDrop,
+            };
+
+            change_rule_info_on_change!(
+                self, state, HardDrop, HardDropPreview::new(self.custom_rules.hard_drop_rule)
+            );
 
-            change_rule_info!(self, state, SoftDrop, SoftDropPreview::new());
+            // Soft drop
 
-            let state = ui::Checkbox::builder("  SOFT DROP LOCK")
-                //.disabled(!self.custom_rules.has_soft_drop)
-                .disabled(true)
-                .build_with_placer(&mut self.custom_rules.has_soft_drop_lock, &mut rules_box_placer, app);
+            let mut soft_drop = match self.custom_rules.soft_drop_rule {
+                SoftDropRule::No           => 0,
+                SoftDropRule::SoftDrop     => 1,
+                SoftDropRule::SoftDropLock => 2,
+                SoftDropRule::SonicDrop    => 3,
+            };
+            let state = ui::Combobox::builder("SOFT DROP", SOFT_DROP_RULE_NAMES)
+                .build_with_placer(&mut soft_drop, &mut rules_box_placer, app);
+            self.custom_rules.soft_drop_rule = match soft_drop {
+                0 => SoftDropRule::No,
+                1 => SoftDropRule::SoftDrop,
+                2 => SoftDropRule::SoftDropLock,
+                _ => SoftDropRule::SonicDrop,
+            };
 
-            change_rule_info!(self, state, SoftDropLock);
+            change_rule_info_on_change!(
+                self, state, SoftDrop, SoftDropPreview::new(self.custom_rules.soft_drop_rule)
+            );
 
             let state = ui::SliderU64::builder("  INTERVAL", 0, 500_000)
+                .disabled(!self.custom_rules.soft_drop_rule.is_enabled())
                 .build_with_placer(&mut self.custom_rules.soft_drop_interval, &mut rules_box_placer, app);
 
             change_rule_info_on_change!(
@@ -207,9 +272,31 @@ impl MainMenuScene {
                 self, state, Ghost, GhostPreview::new(self.custom_rules.has_ghost_piece)
             );
 
+            // Playfield
+
+            let state = ui::SliderU8::builder("PLAYFIELD WIDTH", 4, 20)
+                .build_with_placer(&mut self.custom_rules.playfield_width, &mut rules_box_placer, app);
+
+            change_rule_info!(self, state, PlayfieldWidth);
+
+            let state = ui::SliderU8::builder("  HEIGHT", 8, 60)
+                .build_with_placer(&mut self.custom_rules.playfield_height, &mut rules_box_placer, app);
+
+            change_rule_info!(self, state, PlayfieldHeight);
+
+            let playfield_height = self.custom_rules.playfield_height;
+            self.custom_rules.playfield_visible_height =
+                self.custom_rules.playfield_visible_height.min(playfield_height);
+            self.custom_rules.spawn_row = self.custom_rules.spawn_row.min(playfield_height - 1);
+
+            let state = ui::SliderU8::builder("  VISIBLE ROWS", 4, playfield_height)
+                .build_with_placer(&mut self.custom_rules.playfield_visible_height, &mut rules_box_placer, app);
+
+            change_rule_info!(self, state, PlayfieldVisibleHeight);
+
             // Spawn
 
-            let state = ui::SliderU8::builder("SPAWN ROW", 0, 24)
+            let state = ui::SliderU8::builder("SPAWN ROW", 0, playfield_height - 1)
                 .build_with_placer(&mut self.custom_rules.spawn_row, &mut rules_box_placer, app);
 
             change_rule_info!(self, state, SpawnRow);
@@ -320,6 +407,58 @@ impl MainMenuScene {
 
             change_rule_info!(self, state, TopOutGarbageOut);
 
+            // Garbage
+
+            let mut attack_table = match self.custom_rules.attack_table {
+                AttackTable::Guideline => 0,
+                AttackTable::TetrIo    => 1,
+                AttackTable::Classic   => 2,
+            };
+            let state = ui::Combobox::builder("ATTACK", ATTACK_TABLE_NAMES)
+                .build_with_placer(&mut attack_table, &mut rules_box_placer, app);
+            self.custom_rules.attack_table = match attack_table {
+                0 => AttackTable::Guideline,
+                1 => AttackTable::TetrIo,
+                _ => AttackTable::Classic,
+            };
+
+            change_rule_info!(self, state, AttackTable);
+
+            let mut garbage_hole_rule = match self.custom_rules.garbage_hole_rule {
+                GarbageHoleRule::Clean         => 0,
+                GarbageHoleRule::Messy { .. }  => 1,
+                GarbageHoleRule::PerRow        => 2,
+            };
+            let state = ui::Combobox::builder("GARBAGE HOLES", GARBAGE_HOLE_RULE_NAMES)
+                .build_with_placer(&mut garbage_hole_rule, &mut rules_box_placer, app);
+
+            if state.changed {
+                self.custom_rules.garbage_hole_rule = match garbage_hole_rule {
+                    0 => GarbageHoleRule::Clean,
+                    1 => GarbageHoleRule::Messy { change_chance: 30 },
+                    _ => GarbageHoleRule::PerRow,
+                };
+            }
+
+            change_rule_info!(self, state, GarbageHoleRule);
+
+            if let GarbageHoleRule::Messy { ref mut change_chance } = self.custom_rules.garbage_hole_rule {
+                let state = ui::SliderU8::builder("  CHANGE CHANCE", 0, 100)
+                    .build_with_placer(change_chance, &mut rules_box_placer, app);
+
+                change_rule_info!(self, state, GarbageHoleRule);
+            }
+
+            let state = ui::SliderU64::builder("  DELAY", 0, 2_000_000)
+                .build_with_placer(&mut self.custom_rules.garbage_delay, &mut rules_box_placer, app);
+
+            change_rule_info!(self, state, GarbageDelay);
+
+            let state = ui::Checkbox::builder("  CANCEL")
+                .build_with_placer(&mut self.custom_rules.has_garbage_cancel, &mut rules_box_placer, app);
+
+            change_rule_info!(self, state, GarbageCancel);
+
             // DAS + ARR
 
             // @TODO ui for time values (in frames?)
@@ -347,7 +486,68 @@ impl MainMenuScene {
 
             change_rule_info!(self, state, AutoRepeatRate);
 
-            //pub lock_delay: LockDelayRule,
+            // Lock delay
+
+            let mut lock_delay = match self.custom_rules.lock_delay {
+                LockDelayRule::NoDelay                   => 0,
+                LockDelayRule::EntryReset(_)             => 1,
+                LockDelayRule::StepReset(_)              => 2,
+                LockDelayRule::MoveReset { .. }          => 3,
+                LockDelayRule::ExtendedPlacement { .. }  => 4,
+                LockDelayRule::Infinity(_)               => 5,
+            };
+            let state = ui::Combobox::builder("LOCK DELAY", LOCK_DELAY_RULE_NAMES)
+                .build_with_placer(&mut lock_delay, &mut rules_box_placer, app);
+
+            if state.changed {
+                // Keep the duration when changing between rules
+                let duration = match self.custom_rules.lock_delay.duration() {
+                    0 => 500_000,
+                    duration => duration,
+                };
+
+                self.custom_rules.lock_delay = match lock_delay {
+                    0 => LockDelayRule::NoDelay,
+                    1 => LockDelayRule::EntryReset(duration),
+                    2 => LockDelayRule::StepReset(duration),
+                    3 => LockDelayRule::MoveReset { duration, rotations: 15, movements: 15 },
+                    4 => LockDelayRule::ExtendedPlacement { duration, moves: 15 },
+                    _ => LockDelayRule::Infinity(duration),
+                };
+            }
+
+            change_rule_info!(self, state, LockDelayRule);
+
+            let mut duration = self.custom_rules.lock_delay.duration();
+            let state = ui::SliderU64::builder("  DURATION", 0, 1_000_000)
+                .disabled(self.custom_rules.lock_delay == LockDelayRule::NoDelay)
+                .build_with_placer(&mut duration, &mut rules_box_placer, app);
+            self.custom_rules.lock_delay = self.custom_rules.lock_delay.with_duration(duration);
+
+            change_rule_info!(self, state, LockDelayRule);
+
+            match self.custom_rules.lock_delay {
+                LockDelayRule::MoveReset { ref mut rotations, ref mut movements, .. } => {
+                    let state = ui::SliderU8::builder("  ROTATIONS", 0, 30)
+                        .build_with_placer(rotations, &mut rules_box_placer, app);
+
+                    change_rule_info!(self, state, LockDelayRule);
+
+                    let state = ui::SliderU8::builder("  MOVEMENTS", 0, 30)
+                        .build_with_placer(movements, &mut rules_box_placer, app);
+
+                    change_rule_info!(self, state, LockDelayRule);
+                }
+
+                LockDelayRule::ExtendedPlacement { ref mut moves, .. } => {
+                    let state = ui::SliderU8::builder("  MOVES", 0, 30)
+                        .build_with_placer(moves, &mut rules_box_placer, app);
+
+                    change_rule_info!(self, state, LockDelayRule);
+                }
+
+                _ => {}
+            }
 
             //pub randomizer_type: RandomizerType,
             //app.input_u64_stretch("seed", &mut self.seed);
@@ -19,6 +19,7 @@ impl SimulatedInputMapping {
         button_mapping.insert(KEY_RIGHT.to_owned(), SimulatedButton::new());
         button_mapping.insert(KEY_ROTATE_CW.to_owned(), SimulatedButton::new());
         button_mapping.insert(KEY_ROTATE_CCW.to_owned(), SimulatedButton::new());
+        button_mapping.insert(KEY_ROTATE_180.to_owned(), SimulatedButton::new());
         button_mapping.insert(KEY_HOLD.to_owned(), SimulatedButton::new());
         button_mapping.insert(KEY_SOFT_DROP.to_owned(), SimulatedButton::new());
         button_mapping.insert(KEY_HARD_DROP.to_owned(), SimulatedButton::new());
@@ -128,7 +129,7 @@ impl PlayfieldAnimation {
 
     pub fn update(&mut self, dt: u64, app: &mut App) -> bool {
         let events_updated   = self.update_events(dt);
-        let tetris_game_updated = self.tetris_game.update(dt, &self.input_mapping, app);
+        let tetris_game_updated = !self.tetris_game.update(dt, &self.input_mapping, app).is_empty();
 
         tetris_game_updated || events_updated
     }
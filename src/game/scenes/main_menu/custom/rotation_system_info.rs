@@ -16,6 +16,8 @@ pub fn show_custom_rules_info_rotation_system(
 
     let text =
         "A rotation system broadly represents where and how tetrominoes spawn, how they rotate, \
-        and what wall kicks they may perform.";
+        and what wall kicks they may perform. \
+        Rotation systems can also be loaded from text files in assets/rotation_systems, \
+        which can define other piece sets, like pentominoes.";
     ui::Text::builder(text).multiline(true).build(app);
 }
@@ -2,18 +2,19 @@ use super::*;
 use crate::game::{
     input::*,
     pieces::PieceVariant,
-    rules::GravityCurve,
+    rules::{GravityCurve, SoftDropRule},
 };
 
 pub struct SoftDropPreview;
 
 impl SoftDropPreview {
     // @TODO copy current rules from the menu
-    pub fn new() -> PlayfieldAnimation {
+    pub fn new(soft_drop_rule: SoftDropRule) -> PlayfieldAnimation {
         // @Maybe using only SRS for previews for now, maybe we should show the rotation system the
         //        player has chosen?
         let mut rules: Rules = RotationSystem::SRS.into();
         rules.gravity_curve = GravityCurve::NoGravity;
+        rules.soft_drop_rule = soft_drop_rule;
 
         let playfield = Playfield::new(Vec2i { x: 5, y: 40 }, 8);
 
@@ -133,6 +134,11 @@ pub fn show_custom_rules_info_soft_drop(
 
     ui::Text::builder(text).multiline(true).build(app);
 
+    let text =
+        "With soft drop lock, soft dropping a Tetromino that is on the stack locks it \
+        right away. A sonic drop drops the Tetromino instantly without locking it.";
+    ui::Text::builder(text).multiline(true).build(app);
+
     // Render example playfield
     let mut batch = Batch::new();
 
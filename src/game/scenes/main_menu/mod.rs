@@ -6,6 +6,7 @@ mod options;
 use crate::app::*;
 use crate::linalg::{ Vec2i, Vec2 };
 use crate::game::{
+    pieces::{ get_custom_rotation_system, get_custom_rotation_systems },
     playfield::Playfield,
     randomizer::RandomizerDefinedSequence,
     render::*,
@@ -13,7 +14,16 @@ use crate::game::{
         Rules,
         RotationSystem,
         ROTATION_SYSTEM_NAMES,
+        HardDropRule,
+        HARD_DROP_RULE_NAMES,
+        SoftDropRule,
+        SOFT_DROP_RULE_NAMES,
+        LockDelayRule,
+        LOCK_DELAY_RULE_NAMES,
+        attack::{ATTACK_TABLE_NAMES, AttackTable},
+        garbage::{GARBAGE_HOLE_RULE_NAMES, GarbageHoleRule},
         line_clear::{LINE_CLEAR_RULE_NAMES, LineClearRule},
+        rotation::{ROTATION_180_KICKS_NAMES, Rotation180Kicks},
         topout::TopOutRule,
     },
 };
@@ -175,6 +185,20 @@ impl SceneTrait for MainMenuScene {
                 )
             }
 
+            State::ClassicOnlineBattle => {
+                self.state = State::Main;
+
+                Some(
+                    SceneTransition::Push(
+                        MultiPlayerBattleScene::new(
+                            RotationSystem::NRSR.into(),
+                            app,
+                            persistent
+                        ).into()
+                    )
+                )
+            }
+
             State::ModernOnlineSolo => {
                 self.state = State::Main;
 
@@ -188,6 +212,20 @@ impl SceneTrait for MainMenuScene {
                 )
             }
 
+            State::ModernOnlineBattle => {
+                self.state = State::Main;
+
+                Some(
+                    SceneTransition::Push(
+                        MultiPlayerBattleScene::new(
+                            RotationSystem::SRS.into(),
+                            app,
+                            persistent
+                        ).into()
+                    )
+                )
+            }
+
             State::ModernOnlineSpectate => {
                 self.state = State::Main;
 
@@ -6,6 +6,7 @@ mod persistent_data;
 mod scene_manager;
 mod singleplayer;
 mod multiplayer;
+mod multiplayer_battle;
 mod multiplayer_spectate;
 
 pub use debug_pieces::*;
@@ -14,6 +15,7 @@ pub use persistent_data::*;
 pub use scene_manager::*;
 pub use singleplayer::*;
 pub use multiplayer::*;
+pub use multiplayer_battle::*;
 pub use multiplayer_spectate::*;
 
 pub trait SceneTrait: ImDraw {
@@ -47,6 +49,7 @@ pub enum Scene {
     MainMenuScene(MainMenuScene),
     SinglePlayerScene(SinglePlayerScene),
     MultiPlayerScene(MultiPlayerScene),
+    MultiPlayerBattleScene(MultiPlayerBattleScene),
     MultiPlayerSpectateScene(MultiPlayerSpectateScene),
     DebugPiecesScene(DebugPiecesScene),
 }
@@ -67,6 +70,7 @@ impl SceneTrait for Scene {
             Self::MainMenuScene(scene)     => scene.update(dt, app, persistent),
             Self::SinglePlayerScene(scene) => scene.update(dt, app, persistent),
             Self::MultiPlayerScene(scene)  => scene.update(dt, app, persistent),
+            Self::MultiPlayerBattleScene(scene) => scene.update(dt, app, persistent),
             Self::MultiPlayerSpectateScene(scene) => scene.update(dt, app, persistent),
             Self::DebugPiecesScene(scene)  => scene.update(dt, app, persistent),
         }
@@ -82,6 +86,7 @@ impl SceneTrait for Scene {
             Self::MainMenuScene(scene)     => scene.render(app, persistent),
             Self::SinglePlayerScene(scene) => scene.render(app, persistent),
             Self::MultiPlayerScene(scene)  => scene.render(app, persistent),
+            Self::MultiPlayerBattleScene(scene) => scene.render(app, persistent),
             Self::MultiPlayerSpectateScene(scene) => scene.render(app, persistent),
             Self::DebugPiecesScene(scene)  => scene.render(app, persistent),
         }
@@ -98,6 +103,7 @@ impl SceneTrait for Scene {
             Self::MainMenuScene(scene)     => scene.handle_input(event, app, persistent),
             Self::SinglePlayerScene(scene) => scene.handle_input(event, app, persistent),
             Self::MultiPlayerScene(scene)  => scene.handle_input(event, app, persistent),
+            Self::MultiPlayerBattleScene(scene) => scene.handle_input(event, app, persistent),
             Self::MultiPlayerSpectateScene(scene) => scene.handle_input(event, app, persistent),
             Self::DebugPiecesScene(scene)  => scene.handle_input(event, app, persistent),
         }
@@ -113,6 +119,7 @@ impl SceneTrait for Scene {
             Self::MainMenuScene(scene)     => scene.transition(app, persistent),
             Self::SinglePlayerScene(scene) => scene.transition(app, persistent),
             Self::MultiPlayerScene(scene)  => scene.transition(app, persistent),
+            Self::MultiPlayerBattleScene(scene) => scene.transition(app, persistent),
             Self::MultiPlayerSpectateScene(scene) => scene.transition(app, persistent),
             Self::DebugPiecesScene(scene)  => scene.transition(app, persistent),
         }
@@ -128,6 +135,7 @@ impl SceneTrait for Scene {
             Self::MainMenuScene(scene)     => scene.on_enter(app, persistent),
             Self::SinglePlayerScene(scene) => scene.on_enter(app, persistent),
             Self::MultiPlayerScene(scene)  => scene.on_enter(app, persistent),
+            Self::MultiPlayerBattleScene(scene) => scene.on_enter(app, persistent),
             Self::MultiPlayerSpectateScene(scene) => scene.on_enter(app, persistent),
             Self::DebugPiecesScene(scene)  => scene.on_enter(app, persistent),
         }
@@ -143,6 +151,7 @@ impl SceneTrait for Scene {
             Self::MainMenuScene(scene)     => scene.on_exit(app, persistent),
             Self::SinglePlayerScene(scene) => scene.on_exit(app, persistent),
             Self::MultiPlayerScene(scene)  => scene.on_exit(app, persistent),
+            Self::MultiPlayerBattleScene(scene) => scene.on_exit(app, persistent),
             Self::MultiPlayerSpectateScene(scene) => scene.on_exit(app, persistent),
             Self::DebugPiecesScene(scene)  => scene.on_exit(app, persistent),
         }
@@ -167,6 +176,12 @@ impl From<MultiPlayerScene> for Scene {
     }
 }
 
+impl From<MultiPlayerBattleScene> for Scene {
+    fn from(other: MultiPlayerBattleScene) -> Self {
+        Self::MultiPlayerBattleScene(other)
+    }
+}
+
 impl From<MultiPlayerSpectateScene> for Scene {
     fn from(other: MultiPlayerSpectateScene) -> Self {
         Self::MultiPlayerSpectateScene(other)
@@ -1,10 +1,19 @@
 use crate::app::*;
-use crate::linalg::Vec2i;
+use crate::linalg::{Vec2, Vec2i};
 
 use super::*;
 
 use crate::game::{
-    network::{MultiplayerMessages, Connect, Update},
+    input::TetrisInput,
+    network::{
+        MultiplayerMessages,
+        Checksum,
+        Connect,
+        InputFrame,
+        Inputs,
+        CHECKSUM_INTERVAL,
+        MAX_INPUT_FRAMES_PER_MESSAGE,
+    },
     render::*,
     rules::{
         RotationSystem,
@@ -13,12 +22,19 @@ use crate::game::{
     tetris_game::TetrisGame,
 };
 
+// Every live Inputs message repeats the last frames, so a lost packet doesn't stall the spectators
+const REDUNDANT_INPUT_FRAMES: u32 = 8;
+
 #[derive(Debug, ImDraw)]
 pub struct MultiPlayerScene {
     quit: bool,
     tetris_game: TetrisGame,
     server: Server,
 
+    // Lockstep: the spectators simulate the game from the seed and the inputs of every tick
+    seed: u64,
+    input_history: Vec<InputFrame>,
+
     playfield_pos: Vec2i,
     hold_piece_window_pos: Vec2i,
     next_pieces_preview_window_pos: Vec2i,
@@ -44,14 +60,26 @@ impl SceneTrait for MultiPlayerScene {
                     match e {
                         ServerEvent::ClientConnect(client_id) => {
                             let connect = Connect {
-                                timestamp: app.game_timestamp(),
-                                tetris_game: self.tetris_game.to_network(),
+                                seed: self.seed,
                                 rotation_system: self.tetris_game.rules().rotation_system,
-                                randomizer: self.tetris_game.randomizer().clone(),
                             };
 
                             let message = MultiplayerMessages::Connect(connect);
-                            self.server.send(client_id, message).unwrap();
+                            self.server.send(client_id, Channel::ReliableOrdered, message).unwrap();
+
+                            // The spectator requests the rest of the history when it gets this
+                            self.send_input_frames(client_id, 0);
+                        }
+
+                        ServerEvent::Data(client_id, _, data_payload) => {
+                            match MultiplayerMessages::parse(data_payload.data()) {
+                                Ok(MultiplayerMessages::InputRequest(request)) => {
+                                    self.send_input_frames(client_id, request.tick);
+                                }
+
+                                Ok(_) => {}
+                                Err(err) => println!("[game][scenes][multiplayer] invalid message: {:?}", err),
+                            }
                         }
 
                         _ => {}
@@ -72,15 +100,34 @@ impl SceneTrait for MultiPlayerScene {
         }
 
         if !app.is_paused() {
-            let has_updated = self.tetris_game.update(dt, &persistent.input_mapping, app);
-            if has_updated {
-                let update = Update {
-                    timestamp: app.game_timestamp(),
-                    tetris_game: self.tetris_game.to_network(),
-                };
+            // The spectators only get the truncated dt, so we have to simulate with it too
+            let dt = dt.min(u32::MAX as u64) as u32;
+            let input = TetrisInput::from_input_mapping(
+                &persistent.input_mapping,
+                self.tetris_game.rules(),
+                self.tetris_game.level(),
+                app
+            );
+
+            let frame = InputFrame { dt, input, garbage: 0 };
+            self.tetris_game.update_with_input_frame(frame);
+            self.input_history.push(frame);
+
+            let latest_tick = self.input_history.len() as u32;
+            let first_tick = latest_tick.saturating_sub(REDUNDANT_INPUT_FRAMES);
+            let inputs = Inputs {
+                first_tick,
+                latest_tick,
+                frames: self.input_history[first_tick as usize..].to_vec(),
+            };
+            self.server.broadcast(Channel::Unreliable, MultiplayerMessages::Inputs(inputs)).unwrap();
 
-                let message = MultiplayerMessages::Update(update);
-                self.server.broadcast(message).unwrap();
+            if latest_tick % CHECKSUM_INTERVAL == 0 {
+                let checksum = Checksum {
+                    tick: latest_tick,
+                    checksum: self.tetris_game.checksum(),
+                };
+                self.server.broadcast(Channel::Unreliable, MultiplayerMessages::Checksum(checksum)).unwrap();
             }
         }
     }
@@ -163,6 +210,10 @@ impl SceneTrait for MultiPlayerScene {
             None,
             None,
         );
+
+        self.tetris_game.render_combo_and_back_to_back(Vec2 { x: 10.0, y: 252.0 }, app);
+
+        self.tetris_game.render_top_out_reason(Vec2 { x: 10.0, y: 336.0 }, app);
     }
 
     fn handle_input(
@@ -247,9 +298,27 @@ impl MultiPlayerScene {
 
             server,
 
+            seed,
+            input_history: Vec::new(),
+
             playfield_pos,
             hold_piece_window_pos,
             next_pieces_preview_window_pos,
         }
     }
+
+    // Sends a chunk of the input history, starting at the given tick
+    fn send_input_frames(&mut self, client_id: ClientId, first_tick: u32) {
+        let latest_tick = self.input_history.len() as u32;
+        let first_tick = first_tick.min(latest_tick);
+        let last_tick = latest_tick.min(first_tick + MAX_INPUT_FRAMES_PER_MESSAGE as u32);
+
+        let inputs = Inputs {
+            first_tick,
+            latest_tick,
+            frames: self.input_history[first_tick as usize..last_tick as usize].to_vec(),
+        };
+
+        self.server.send(client_id, Channel::Unreliable, MultiplayerMessages::Inputs(inputs)).unwrap();
+    }
 }
@@ -0,0 +1,960 @@
+use crate::app::*;
+use crate::linalg::{Vec2, Vec2i};
+
+use super::*;
+
+use crate::game::{
+    input::TetrisInput,
+    network::{
+        Attack,
+        Battle,
+        BattleEnd,
+        BattleStart,
+        InputFrame,
+        MultiplayerMessages,
+        PlayerInputRequest,
+        PlayerInputs,
+        RollbackConfig,
+        RollbackGame,
+        ToppedOut,
+        MAX_INPUT_FRAMES_PER_MESSAGE,
+    },
+    render::*,
+    rules::{
+        Rules,
+        topout::TopOutRule,
+    },
+    tetris_game::{TetrisEvent, TetrisGame},
+};
+
+// Guests use random ids, so the host can be any fixed id
+const HOST_PLAYER_ID: ClientId = 0;
+
+// Every PlayerInputs message repeats the last frames, so a lost packet doesn't cause a rollback.
+// Longer losses stall the opponent game until the missing frames are requested
+const REDUNDANT_INPUT_FRAMES: usize = 8;
+
+// Time an opponent game can be stalled before requesting its missing frames, and between requests
+const INPUT_REQUEST_INTERVAL: u64 = 200_000;
+
+// Battles are simulated on a fixed timestep (60 Hz), so every machine runs the same ticks whatever
+// its frame rate, and the predicted dts of the opponent games are always right
+const TICK_DT: u64 = 16_667;
+
+#[derive(Debug, ImDraw)]
+enum State {
+    Menu,
+    Connecting,
+    Lobby,
+    Playing,
+    Finished { winner_id: ClientId },
+    Quitting,
+}
+
+// The host plays and runs the server, the guests connect to it
+#[derive(Debug, ImDraw)]
+enum Connection {
+    None,
+    Host(Server),
+    Guest(Client),
+}
+
+// The opponent games are simulated locally with their inputs
+#[derive(Debug, ImDraw)]
+struct Opponent {
+    player_id: ClientId,
+    game: RollbackGame,
+    has_topped_out: bool,
+    input_request_timer: u64,
+}
+
+#[derive(Debug, Default, ImDraw)]
+struct RollbackStats {
+    timer: u64,
+    last_rollback_count: u32,
+    rollbacks_per_second: u32,
+}
+
+#[derive(Debug, ImDraw)]
+pub struct MultiPlayerBattleScene {
+    state: State,
+    show_menu: bool,
+    menu_server_ip: String,
+
+    connection: Connection,
+    player_id: ClientId,
+    battle: Option<Battle>, // host only
+
+    rules: Rules,
+    tetris_game: TetrisGame,
+    opponents: Vec<Opponent>,
+
+    // Rollback
+    seed: u64,
+    tick: u32,
+    tick_timer: u64, // time not simulated yet, less than TICK_DT after every update
+    pending_input: TetrisInput, // sampled in updates that didn't simulate a tick
+    local_frames: Vec<InputFrame>, // includes the frames waiting for the input delay
+    received_garbage: u8, // goes in the next local frame
+    rollback_config: RollbackConfig,
+    rollback_stats: RollbackStats,
+    show_rollback_overlay: bool,
+
+    playfield_pos: Vec2i,
+    hold_piece_window_pos: Vec2i,
+    next_pieces_preview_window_pos: Vec2i,
+    opponents_pos: Vec2i,
+}
+
+impl SceneTrait for MultiPlayerBattleScene {
+    type Scene = Scene;
+    type PersistentData = PersistentData;
+
+    fn update(
+        &mut self,
+        dt: u64,
+        app: &mut App,
+        persistent: &mut Self::PersistentData
+    ) {
+        // Networking
+        match self.connection {
+            Connection::Host(_)  => self.update_host(),
+            Connection::Guest(_) => self.update_guest(),
+            Connection::None     => {},
+        }
+
+        // Battles can't be paused, the menu is shown over the game
+        let options_button = persistent.input_mapping.button("options".to_string());
+        if options_button.pressed() {
+            self.show_menu = !self.show_menu;
+        }
+
+        if let State::Playing = self.state {
+            self.update_game(dt, app, persistent);
+        }
+    }
+
+    fn render(
+        &mut self,
+        app: &mut App,
+        persistent: &mut Self::PersistentData
+    ) {
+        // UI
+        let window_size = app.window_size();
+        let window_size = Vec2i { x: window_size.0 as i32, y: window_size.1 as i32 };
+        let menu_size = Vec2i { x: 600, y: 300 };
+
+        // Ui
+        let window_layout = ui::Layout {
+            pos: Vec2i {
+                x: (window_size.x - menu_size.x) / 2,
+                y: (window_size.y - menu_size.y) / 2,
+            },
+            size: menu_size
+        };
+
+        match self.state {
+            State::Menu => {
+                ui::Ui::builder(window_layout).build(app);
+
+                ui::Text::new("ONLINE BATTLE", app);
+                if ui::Button::new("HOST", app).pressed {
+                    self.host();
+                }
+
+                ui::Input::builder("Server").build(&mut self.menu_server_ip, app);
+                if ui::Button::new("JOIN", app).pressed {
+                    self.join(persistent);
+                }
+
+                if ui::Button::new("QUIT", app).pressed {
+                    self.state = State::Quitting;
+                }
+            },
+
+            State::Connecting => {
+                ui::Ui::builder(window_layout).build(app);
+                ui::Text::new("CONNECTING...", app);
+
+                if ui::Button::new("CANCEL", app).pressed {
+                    self.disconnect();
+                    self.state = State::Menu;
+                }
+
+                if ui::Button::new("QUIT", app).pressed {
+                    self.state = State::Quitting;
+                }
+            },
+
+            State::Lobby => {
+                ui::Ui::builder(window_layout).build(app);
+
+                if let Connection::Host(server) = &self.connection {
+                    ui::Text::new("WAITING FOR PLAYERS", app);
+                    ui::Text::new(&format!("IP: {}", server.addr()), app);
+                    ui::Text::new(&format!("PLAYERS: {}", self.opponents.len() + 1), app);
+
+                    let start_state = ui::Button::builder("START")
+                        .disabled(self.opponents.is_empty())
+                        .build(app);
+                    if start_state.pressed {
+                        self.start_battle(persistent);
+                    }
+                } else {
+                    ui::Text::new("WAITING FOR HOST", app);
+                }
+
+                if ui::Button::new("QUIT", app).pressed {
+                    self.state = State::Quitting;
+                }
+            },
+
+            State::Finished { winner_id } => {
+                ui::Ui::builder(window_layout).build(app);
+
+                if winner_id == self.player_id {
+                    ui::Text::new("YOU WIN", app);
+                } else {
+                    ui::Text::new("YOU LOSE", app);
+                }
+
+                if ui::Button::new("QUIT", app).pressed {
+                    self.state = State::Quitting;
+                }
+            },
+
+            State::Playing if self.show_menu => {
+                ui::Ui::builder(window_layout).build(app);
+
+                ui::Text::new("BATTLE", app);
+                if ui::Button::new("RESUME", app).pressed {
+                    self.show_menu = false;
+                }
+
+                if ui::Button::new("QUIT", app).pressed {
+                    self.state = State::Quitting;
+                }
+            },
+
+            _ => {}
+        }
+
+        self.tetris_game.update_animations();
+        self.tetris_game.render_playfield(self.playfield_pos, true, &mut app.batch(), persistent);
+        self.tetris_game.render_hold_piece(self.hold_piece_window_pos, true, &mut app.batch(), persistent);
+        self.tetris_game.render_next_pieces_preview(self.next_pieces_preview_window_pos, 0, persistent.pixel_scale, true, &mut app.batch(), persistent);
+
+        // Opponents
+        let opponent_pixel_scale = (persistent.pixel_scale / 2).max(1);
+        let mut opponent_pos = self.opponents_pos;
+        for opponent in self.opponents.iter() {
+            draw_playfield(
+                opponent.game.game().playfield(),
+                opponent_pos,
+                opponent_pixel_scale,
+                true,
+                None,
+                self.tetris_game.rules().rotation_system,
+                &mut app.batch(),
+                persistent
+            );
+
+            if opponent.has_topped_out {
+                app.queue_draw_text(
+                    "K.O.",
+                    TransformBuilder::new()
+                        .pos_xy(opponent_pos.x as f32 + 10.0, opponent_pos.y as f32 + 10.0)
+                        .layer(800)
+                        .build(),
+                    32.,
+                    WHITE,
+                    None,
+                    None,
+                );
+            }
+
+            let playfield_draw_size = get_draw_playfield_size(
+                opponent.game.game().playfield(),
+                opponent_pixel_scale,
+                true,
+            );
+            opponent_pos.x += playfield_draw_size.x + 20;
+        }
+
+        if self.show_rollback_overlay {
+            let max_prediction = self.opponents.iter()
+                .map(|opponent| opponent.game.tick() - opponent.game.confirmed_tick())
+                .max()
+                .unwrap_or(0);
+
+            app.queue_draw_text(
+                &format!(
+                    "rollbacks/s: {} prediction: {} delay: {}",
+                    self.rollback_stats.rollbacks_per_second,
+                    max_prediction,
+                    self.rollback_config.input_delay,
+                ),
+                TransformBuilder::new().pos_xy(10.0, window_size.y as f32 - 42.0).layer(800).build(),
+                32.,
+                WHITE,
+                None,
+                None,
+            );
+        }
+
+        app.queue_draw_text(
+            &format!("time: {:.2}", to_seconds(self.tetris_game.timestamp())),
+            TransformBuilder::new().pos_xy(10.0, 84.0).layer(800).build(),
+            32.,
+            WHITE,
+            None,
+            None,
+        );
+
+        app.queue_draw_text(
+            &format!("lines: {}", self.tetris_game.total_lines_cleared()),
+            TransformBuilder::new().pos_xy(10.0, 126.0).layer(800).build(),
+            32.,
+            WHITE,
+            None,
+            None,
+        );
+
+        app.queue_draw_text(
+            &format!("garbage: {}", self.tetris_game.pending_garbage_rows()),
+            TransformBuilder::new().pos_xy(10.0, 168.0).layer(800).build(),
+            32.,
+            WHITE,
+            None,
+            None,
+        );
+
+        self.tetris_game.render_combo_and_back_to_back(Vec2 { x: 10.0, y: 210.0 }, app);
+
+        self.tetris_game.render_top_out_reason(Vec2 { x: 10.0, y: 294.0 }, app);
+    }
+
+    fn handle_input(
+        &mut self,
+        event: &sdl2::event::Event,
+        _app: &mut App,
+        _persistent: &mut Self::PersistentData,
+    ) -> bool {
+        use sdl2::event::Event;
+        use sdl2::keyboard::Scancode;
+
+        match event {
+            Event::KeyDown { scancode: Some(Scancode::F2), .. } => {
+                self.show_rollback_overlay = !self.show_rollback_overlay;
+            }
+
+            _ => {}
+        }
+
+        false
+    }
+
+    fn transition(
+        &mut self,
+        _app: &mut App,
+        _persistent: &mut Self::PersistentData
+    ) -> Option<SceneTransition<Self::Scene>> {
+        if let State::Quitting = self.state {
+            Some(SceneTransition::Pop)
+        } else {
+            None
+        }
+    }
+
+    fn on_enter(&mut self, app: &mut App, _persistent: &mut Self::PersistentData) {
+        app.restart_time_system();
+    }
+
+    fn on_exit(&mut self, _app: &mut App, _persistent: &mut Self::PersistentData) {
+        self.disconnect();
+    }
+}
+
+impl MultiPlayerBattleScene {
+    pub fn new(
+        rules: Rules,
+        app: &mut App,
+        persistent: &mut PersistentData
+    ) -> Self {
+        // Placeholder game until the battle starts
+        let tetris_game = TetrisGame::new(rules.clone(), 0);
+
+        let mut scene = Self {
+            state: State::Menu,
+            show_menu: false,
+            menu_server_ip: "127.0.0.1:42042".to_owned(),
+
+            connection: Connection::None,
+            player_id: HOST_PLAYER_ID,
+            battle: None,
+
+            rules,
+            tetris_game,
+            opponents: Vec::new(),
+
+            seed: 0,
+            tick: 0,
+            tick_timer: 0,
+            pending_input: TetrisInput::default(),
+            local_frames: Vec::new(),
+            received_garbage: 0,
+            rollback_config: RollbackConfig::default(),
+            rollback_stats: RollbackStats::default(),
+            show_rollback_overlay: false,
+
+            playfield_pos: Vec2i::new(),
+            hold_piece_window_pos: Vec2i::new(),
+            next_pieces_preview_window_pos: Vec2i::new(),
+            opponents_pos: Vec2i::new(),
+        };
+
+        scene.update_layout(app, persistent);
+        scene
+    }
+
+    fn update_layout(&mut self, app: &mut App, persistent: &mut PersistentData) {
+        // @Refactor use InstanceStyle
+        // Playfield rendering
+        let playfield_draw_size = get_draw_playfield_size(
+            &self.tetris_game.playfield(),
+            persistent.pixel_scale,
+            true,
+        );
+
+        // The player is in the left half, the opponents in the right half
+        let window_size = app.window_size();
+        self.playfield_pos = Vec2i {
+            x: (window_size.0 as i32 / 2 - playfield_draw_size.x) / 2,
+            y: (window_size.1 as i32 - playfield_draw_size.y) / 2,
+        };
+
+        let hold_window_size = self.tetris_game.hold_piece_window_size(true, persistent);
+        self.hold_piece_window_pos =
+            self.playfield_pos +
+            Vec2i { x: -20, y: 0 } +
+            Vec2i { x: -hold_window_size.x, y: 0 };
+
+        self.next_pieces_preview_window_pos =
+            self.playfield_pos +
+            Vec2i { x: 20, y: 0 } +
+            Vec2i { x: playfield_draw_size.x, y: 0 };
+
+        self.opponents_pos = Vec2i {
+            x: window_size.0 as i32 / 2 + 20,
+            y: self.playfield_pos.y,
+        };
+    }
+
+    fn host(&mut self) {
+        match Server::new("127.0.0.1:42042") {
+            Ok(server) => {
+                self.connection = Connection::Host(server);
+                self.player_id = HOST_PLAYER_ID;
+                self.state = State::Lobby;
+            }
+            Err(err) => println!("[game][scenes][multiplayer_battle] host problem: {:?}", err),
+        }
+    }
+
+    fn join(&mut self, persistent: &mut PersistentData) {
+        let player_id = persistent.rng.next_u64();
+        let mut client = match Client::new(player_id) {
+            Ok(client) => client,
+            Err(err) => {
+                println!("[game][scenes][multiplayer_battle] client problem: {:?}", err);
+                return;
+            }
+        };
+
+        match client.connect(self.menu_server_ip.clone()) {
+            Ok(_) => {
+                self.connection = Connection::Guest(client);
+                self.player_id = player_id;
+                self.state = State::Connecting;
+            }
+            Err(err) => println!("[game][scenes][multiplayer_battle] connect problem: {:?}", err),
+        }
+    }
+
+    fn disconnect(&mut self) {
+        let result = match std::mem::replace(&mut self.connection, Connection::None) {
+            Connection::Host(mut server) => server.disconnect_all(),
+            Connection::Guest(mut client) => client.disconnect(),
+            Connection::None => Ok(()),
+        };
+
+        if let Err(err) = result {
+            println!("[game][scenes][multiplayer_battle] disconnect problem: {:?}", err);
+        }
+
+        self.battle = None;
+        self.opponents.clear();
+    }
+
+    fn start_battle(&mut self, persistent: &mut PersistentData) {
+        let server = match &mut self.connection {
+            Connection::Host(server) => server,
+            _ => unreachable!(),
+        };
+
+        let seed = persistent.rng.next_u64();
+        let message = MultiplayerMessages::BattleStart(BattleStart {
+            seed,
+            rotation_system: self.rules.rotation_system,
+        });
+        if let Err(err) = server.broadcast(Channel::ReliableOrdered, message) {
+            println!("[game][scenes][multiplayer_battle] start problem: {:?}", err);
+        }
+
+        let mut player_ids = vec![self.player_id];
+        player_ids.extend(self.opponents.iter().map(|opponent| opponent.player_id));
+        self.battle = Some(Battle::new(&player_ids));
+
+        self.start_game(self.rules.clone(), seed);
+    }
+
+    fn start_game(&mut self, mut rules: Rules, seed: u64) {
+        // Garbage pushing the stack out of the playfield is the main way of losing a battle
+        rules.top_out_rule |= TopOutRule::GARBAGE_OUT;
+
+        self.tetris_game = TetrisGame::new(rules, seed);
+        self.show_menu = false;
+        self.state = State::Playing;
+
+        // The first ticks have no input, they are the input delay of the first frame
+        self.seed = seed;
+        self.tick = 0;
+        self.tick_timer = 0;
+        self.pending_input = TetrisInput::default();
+        self.local_frames = vec![InputFrame::default(); self.rollback_config.input_delay as usize];
+        self.received_garbage = 0;
+        self.rollback_stats = RollbackStats::default();
+
+        for i in 0..self.opponents.len() {
+            self.opponents[i].game = self.new_opponent_game();
+            self.opponents[i].has_topped_out = false;
+            self.opponents[i].input_request_timer = INPUT_REQUEST_INTERVAL;
+        }
+    }
+
+    // @XXX the opponents use the rules of the host, but the host may have custom rules while the
+    //      guests only get the rotation system
+    fn new_opponent_game(&self) -> RollbackGame {
+        let game = TetrisGame::new(self.tetris_game.rules().clone(), self.seed);
+        RollbackGame::new(game, self.rollback_config.max_rollback_frames)
+    }
+
+    fn update_game(&mut self, dt: u64, app: &mut App, persistent: &mut PersistentData) {
+        let input = TetrisInput::from_input_mapping(
+            &persistent.input_mapping,
+            self.tetris_game.rules(),
+            self.tetris_game.level(),
+            app
+        );
+        self.pending_input = self.pending_input.merge(&input);
+
+        // Slow frames simulate many ticks, only the first one gets the new actions
+        self.tick_timer += dt;
+        while self.tick_timer >= TICK_DT && matches!(self.state, State::Playing) {
+            self.tick_timer -= TICK_DT;
+
+            let input = self.pending_input;
+            self.pending_input = input.held();
+            self.update_tick(input);
+        }
+
+        let rollback_count = self.opponents.iter().map(|opponent| opponent.game.rollback_count()).sum();
+        self.rollback_stats.update(dt, rollback_count);
+    }
+
+    fn update_tick(&mut self, input: TetrisInput) {
+        let garbage = std::mem::take(&mut self.received_garbage);
+        self.local_frames.push(InputFrame { dt: TICK_DT as u32, input, garbage });
+        self.send_inputs();
+
+        // With input delay, the frame simulated now was sampled some ticks ago
+        let frame = self.local_frames[self.tick as usize];
+        let events = self.tetris_game.update_with_input_frame(frame).to_vec();
+        self.tick += 1;
+
+        let mut input_requests = Vec::new();
+        for opponent in self.opponents.iter_mut() {
+            opponent.game.update(self.tick, TICK_DT);
+
+            if !opponent.game.is_stalled() || opponent.has_topped_out {
+                opponent.input_request_timer = INPUT_REQUEST_INTERVAL;
+                continue;
+            }
+
+            opponent.input_request_timer = opponent.input_request_timer.saturating_sub(TICK_DT);
+            if opponent.input_request_timer == 0 {
+                input_requests.push((opponent.player_id, opponent.game.confirmed_tick()));
+                opponent.input_request_timer = INPUT_REQUEST_INTERVAL;
+            }
+        }
+
+        for (player_id, tick) in input_requests {
+            self.request_inputs(player_id, tick);
+        }
+
+        self.handle_game_events(&events);
+    }
+
+    fn handle_game_events(&mut self, events: &[TetrisEvent]) {
+        for event in events {
+            match *event {
+                TetrisEvent::Attack { rows } => self.send_attack(self.player_id, rows),
+                TetrisEvent::ToppedOut { .. } => self.player_topped_out(self.player_id),
+                _ => {}
+            }
+        }
+    }
+
+    fn send_inputs(&mut self) {
+        let first_tick = self.local_frames.len().saturating_sub(REDUNDANT_INPUT_FRAMES);
+        let message = self.input_frames_message(first_tick);
+
+        let result = match &mut self.connection {
+            Connection::Host(server) => server.broadcast(Channel::Unreliable, message),
+            Connection::Guest(client) => client.send(Channel::Unreliable, message),
+            Connection::None => Ok(()),
+        };
+
+        if let Err(err) = result {
+            println!("[game][scenes][multiplayer_battle] inputs problem: {:?}", err);
+        }
+    }
+
+    // Local frames starting at first_tick, as many as fit in a message
+    fn input_frames_message(&self, first_tick: usize) -> MultiplayerMessages {
+        let first_tick = first_tick.min(self.local_frames.len());
+        let last_tick = (first_tick + MAX_INPUT_FRAMES_PER_MESSAGE).min(self.local_frames.len());
+
+        MultiplayerMessages::PlayerInputs(PlayerInputs {
+            player_id: self.player_id,
+            first_tick: first_tick as u32,
+            frames: self.local_frames[first_tick..last_tick].to_vec(),
+        })
+    }
+
+    // The host asks the player directly, the guests ask the host to forward the request
+    fn request_inputs(&mut self, player_id: ClientId, tick: u32) {
+        let message = MultiplayerMessages::PlayerInputRequest(PlayerInputRequest { player_id, tick });
+
+        let result = match &mut self.connection {
+            Connection::Host(server) => server.send(player_id, Channel::Unreliable, message),
+            Connection::Guest(client) => client.send(Channel::Unreliable, message),
+            Connection::None => Ok(()),
+        };
+
+        if let Err(err) = result {
+            println!("[game][scenes][multiplayer_battle] input request problem: {:?}", err);
+        }
+    }
+
+    fn send_attack(&mut self, attacker_id: ClientId, rows: u8) {
+        let result = match &mut self.connection {
+            // The host chooses who receives the garbage
+            Connection::Host(server) => {
+                let battle = self.battle.as_mut().unwrap();
+                match battle.next_target(attacker_id) {
+                    Some(target_id) if target_id == self.player_id => {
+                        self.received_garbage = self.received_garbage.saturating_add(rows);
+                        Ok(())
+                    }
+
+                    Some(target_id) => {
+                        let message = MultiplayerMessages::Attack(Attack { player_id: attacker_id, rows });
+                        server.send(target_id, Channel::ReliableOrdered, message)
+                    }
+
+                    None => Ok(()),
+                }
+            }
+
+            Connection::Guest(client) => {
+                let message = MultiplayerMessages::Attack(Attack { player_id: attacker_id, rows });
+                client.send(Channel::ReliableOrdered, message)
+            }
+
+            Connection::None => Ok(()),
+        };
+
+        if let Err(err) = result {
+            println!("[game][scenes][multiplayer_battle] attack problem: {:?}", err);
+        }
+    }
+
+    fn player_topped_out(&mut self, player_id: ClientId) {
+        if let Some(opponent) = self.opponents.iter_mut().find(|opponent| opponent.player_id == player_id) {
+            opponent.has_topped_out = true;
+        }
+
+        let result = match &mut self.connection {
+            Connection::Host(server) => {
+                let message = MultiplayerMessages::ToppedOut(ToppedOut { player_id });
+                let mut result = server.broadcast(Channel::ReliableOrdered, message);
+
+                if let Some(winner_id) = self.battle.as_mut().and_then(|battle| battle.top_out(player_id)) {
+                    let message = MultiplayerMessages::BattleEnd(BattleEnd { winner_id });
+                    result = result.and(server.broadcast(Channel::ReliableOrdered, message));
+                    self.state = State::Finished { winner_id };
+                }
+
+                result
+            }
+
+            // Guests only report their own top out, the host tells the others
+            Connection::Guest(client) if player_id == self.player_id => {
+                let message = MultiplayerMessages::ToppedOut(ToppedOut { player_id });
+                client.send(Channel::ReliableOrdered, message)
+            }
+
+            _ => Ok(()),
+        };
+
+        if let Err(err) = result {
+            println!("[game][scenes][multiplayer_battle] top out problem: {:?}", err);
+        }
+    }
+
+    fn add_opponent(&mut self, player_id: ClientId) {
+        if !self.opponents.iter().any(|opponent| opponent.player_id == player_id) {
+            let game = self.new_opponent_game();
+            self.opponents.push(Opponent {
+                player_id,
+                game,
+                has_topped_out: false,
+                input_request_timer: INPUT_REQUEST_INTERVAL,
+            });
+        }
+    }
+
+    fn add_opponent_inputs(&mut self, player_id: ClientId, first_tick: u32, frames: &[InputFrame]) {
+        if player_id == self.player_id { return; }
+
+        self.add_opponent(player_id);
+        if let Some(opponent) = self.opponents.iter_mut().find(|opponent| opponent.player_id == player_id) {
+            opponent.game.add_remote_frames(first_tick, frames);
+        }
+    }
+
+    fn update_host(&mut self) {
+        loop {
+            let event = match &mut self.connection {
+                Connection::Host(server) => server.next_event(),
+                _ => return,
+            };
+
+            match event {
+                Ok(None) => {},
+                Ok(Some(event)) => {
+                    self.handle_server_event(event);
+                    continue;
+                },
+                Err(err) => println!("server event error: {:?}", err),
+            }
+            break;
+        }
+    }
+
+    fn handle_server_event(&mut self, event: ServerEvent) {
+        match event {
+            ServerEvent::ClientConnect(client_id) => {
+                if let State::Lobby = self.state {
+                    self.add_opponent(client_id);
+                } else if let Connection::Host(server) = &mut self.connection {
+                    // @TODO spectators
+                    if let Err(err) = server.disconnect_client(client_id) {
+                        println!("[game][scenes][multiplayer_battle] disconnect problem: {:?}", err);
+                    }
+                }
+            }
+
+            ServerEvent::ClientDisconnect(client_id) | ServerEvent::ClientTimeOut(client_id) => {
+                match self.state {
+                    State::Playing => self.player_topped_out(client_id),
+                    _ => self.opponents.retain(|opponent| opponent.player_id != client_id),
+                }
+            }
+
+            ServerEvent::Data(client_id, _, data_payload) => {
+                let message = match MultiplayerMessages::parse(data_payload.data()) {
+                    Ok(message) => message,
+                    Err(err) => {
+                        println!("[game][scenes][multiplayer_battle] invalid message: {:?}", err);
+                        return;
+                    }
+                };
+
+                // The connection id is used instead of the player id in the messages
+                match message {
+                    MultiplayerMessages::Attack(attack) => {
+                        if let State::Playing = self.state {
+                            self.send_attack(client_id, attack.rows);
+                        }
+                    }
+
+                    MultiplayerMessages::PlayerInputs(inputs) => {
+                        if let State::Lobby = self.state { return; }
+
+                        let server = match &mut self.connection {
+                            Connection::Host(server) => server,
+                            _ => unreachable!(),
+                        };
+
+                        // Forward to the other guests
+                        let client_ids: Vec<ClientId> = server.connections().iter()
+                            .map(|conn| conn.client_id())
+                            .filter(|&id| id != client_id)
+                            .collect();
+
+                        for id in client_ids {
+                            let message = MultiplayerMessages::PlayerInputs(PlayerInputs {
+                                player_id: client_id,
+                                first_tick: inputs.first_tick,
+                                frames: inputs.frames.clone(),
+                            });
+
+                            if let Err(err) = server.send(id, Channel::Unreliable, message) {
+                                println!("[game][scenes][multiplayer_battle] inputs problem: {:?}", err);
+                            }
+                        }
+
+                        self.add_opponent_inputs(client_id, inputs.first_tick, &inputs.frames);
+                    }
+
+                    // The requested player answers with its frames. A guest sends them to the host
+                    // like the other inputs, so they are forwarded to all the guests
+                    MultiplayerMessages::PlayerInputRequest(request) => {
+                        if let State::Lobby = self.state { return; }
+
+                        let (target_id, message) = if request.player_id == self.player_id {
+                            (client_id, self.input_frames_message(request.tick as usize))
+                        } else {
+                            (request.player_id, MultiplayerMessages::PlayerInputRequest(request))
+                        };
+
+                        if let Connection::Host(server) = &mut self.connection {
+                            if let Err(err) = server.send(target_id, Channel::Unreliable, message) {
+                                println!("[game][scenes][multiplayer_battle] input request problem: {:?}", err);
+                            }
+                        }
+                    }
+
+                    MultiplayerMessages::ToppedOut(_) => self.player_topped_out(client_id),
+
+                    _ => {}
+                }
+            }
+
+            _ => {}
+        }
+    }
+
+    fn update_guest(&mut self) {
+        loop {
+            let event = match &mut self.connection {
+                Connection::Guest(client) => client.next_event(),
+                _ => return,
+            };
+
+            match event {
+                Ok(None) => {},
+                Ok(Some(event)) => {
+                    self.handle_client_event(event);
+                    continue;
+                },
+                Err(_err) => {}, //println!("client event error: {:?}", err),
+            }
+            break;
+        }
+    }
+
+    fn handle_client_event(&mut self, event: ClientEvent) {
+        match event {
+            ClientEvent::ServerConnectionAccept => {
+                self.state = State::Lobby;
+            },
+
+            ClientEvent::ServerConnectionReject(reason) => {
+                println!("connection rejected: {:?}", reason);
+                self.disconnect();
+                self.state = State::Menu;
+            },
+
+            ClientEvent::ServerTimedOut | ClientEvent::DisconnectedByServer => {
+                println!("disconnected from host!");
+                self.disconnect();
+                self.state = State::Menu;
+            },
+
+            ClientEvent::Data(_, data_payload) => {
+                let message = match MultiplayerMessages::parse(data_payload.data()) {
+                    Ok(message) => message,
+                    Err(err) => {
+                        println!("[game][scenes][multiplayer_battle] invalid message: {:?}", err);
+                        return;
+                    }
+                };
+
+                match message {
+                    MultiplayerMessages::BattleStart(start) => {
+                        self.start_game(start.rotation_system.into(), start.seed);
+                    }
+
+                    MultiplayerMessages::Attack(attack) => {
+                        self.received_garbage = self.received_garbage.saturating_add(attack.rows);
+                    }
+
+                    MultiplayerMessages::PlayerInputs(inputs) => {
+                        if let State::Playing = self.state {
+                            self.add_opponent_inputs(inputs.player_id, inputs.first_tick, &inputs.frames);
+                        }
+                    }
+
+                    MultiplayerMessages::PlayerInputRequest(request) => {
+                        if request.player_id == self.player_id {
+                            let message = self.input_frames_message(request.tick as usize);
+                            if let Connection::Guest(client) = &mut self.connection {
+                                if let Err(err) = client.send(Channel::Unreliable, message) {
+                                    println!("[game][scenes][multiplayer_battle] inputs problem: {:?}", err);
+                                }
+                            }
+                        }
+                    }
+
+                    MultiplayerMessages::ToppedOut(topped_out) => {
+                        if topped_out.player_id != self.player_id {
+                            self.player_topped_out(topped_out.player_id);
+                        }
+                    }
+
+                    MultiplayerMessages::BattleEnd(end) => {
+                        self.state = State::Finished { winner_id: end.winner_id };
+                    }
+
+                    _ => {}
+                }
+            },
+
+            _ => {}
+        }
+    }
+}
+
+impl RollbackStats {
+    fn update(&mut self, dt: u64, rollback_count: u32) {
+        self.timer += dt;
+        if self.timer >= 1_000_000 {
+            self.rollbacks_per_second = rollback_count.saturating_sub(self.last_rollback_count);
+            self.last_rollback_count = rollback_count;
+            self.timer = 0;
+        }
+    }
+}
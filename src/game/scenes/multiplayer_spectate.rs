@@ -1,10 +1,15 @@
 use crate::app::*;
-use crate::linalg::Vec2i;
+use crate::linalg::{Vec2, Vec2i};
 
 use super::*;
 
 use crate::game::{
-    network::MultiplayerMessages,
+    network::{
+        MultiplayerMessages,
+        InputRequest,
+        Inputs,
+        CHECKSUM_INTERVAL,
+    },
     render::*,
     rules::{
         RotationSystem,
@@ -13,12 +18,21 @@ use crate::game::{
     tetris_game::TetrisGame,
 };
 
+// Time to wait for the requested input frames before requesting them again. The next frames are
+// requested as soon as the previous ones arrive, this is only for the lost replies
+const INPUT_REQUEST_INTERVAL: u64 = 200_000;
+
+// Restarts after a desync before giving up. If the game diverges again, it's not a lost or
+// corrupted frame, so simulating everything again won't fix it
+const MAX_DESYNC_RESTARTS: u32 = 1;
+
 #[derive(Debug, ImDraw)]
 enum State {
     Normal,
     ConnectMenu,
     Connecting,
     Paused,
+    Desynced,
     Quitting,
 }
 
@@ -30,6 +44,16 @@ pub struct MultiPlayerSpectateScene {
     client: Client,
     tetris_game: TetrisGame,
 
+    // Lockstep
+    seed: u64,
+    rotation_system: RotationSystem,
+    next_tick: u32,   // tick of the next input frame to simulate
+    latest_tick: u32, // ticks simulated by the player
+    input_request_timer: u64,
+    local_checksums: Vec<(u32, u32)>,  // (tick, checksum), only the recent ones
+    remote_checksums: Vec<(u32, u32)>, // (tick, checksum), of ticks not simulated yet
+    desync_restarts: u32,
+
     playfield_pos: Vec2i,
     hold_piece_window_pos: Vec2i,
     next_pieces_preview_window_pos: Vec2i,
@@ -41,7 +65,7 @@ impl SceneTrait for MultiPlayerSpectateScene {
 
     fn update(
         &mut self,
-        _dt: u64,
+        dt: u64,
         app: &mut App,
         persistent: &mut Self::PersistentData
     ) {
@@ -67,26 +91,41 @@ impl SceneTrait for MultiPlayerSpectateScene {
                             self.state = State::ConnectMenu;
                         },
 
-                        ClientEvent::Data(data_payload) => {
-                            match MultiplayerMessages::parse(data_payload.data()).unwrap() {
+                        ClientEvent::Data(_, data_payload) => {
+                            let message = match MultiplayerMessages::parse(data_payload.data()) {
+                                Ok(message) => message,
+                                Err(err) => {
+                                    println!("[game][scenes][multiplayer_spectate] invalid message: {:?}", err);
+                                    continue;
+                                }
+                            };
+
+                            match message {
                                 MultiplayerMessages::Connect(c) => {
-                                    self.tetris_game = TetrisGame::from_network(
-                                        c.tetris_game,
-                                        c.rotation_system.into(),
-                                        c.randomizer,
-                                        c.timestamp,
-                                        app,
-                                        persistent
-                                    );
+                                    self.seed = c.seed;
+                                    self.rotation_system = c.rotation_system;
+                                    self.desync_restarts = 0;
+                                    self.restart_game();
+
+                                    // The server playfield may have other dimensions
+                                    self.update_layout(app, persistent);
+                                },
+
+                                MultiplayerMessages::Inputs(inputs) => {
+                                    self.simulate_inputs(inputs);
                                 },
 
-                                MultiplayerMessages::Update(u) => {
-                                    self.tetris_game.update_from_network(
-                                        u.tetris_game,
-                                        u.timestamp,
-                                        app
-                                    );
+                                MultiplayerMessages::Checksum(c) => {
+                                    if c.tick > self.next_tick {
+                                        self.remote_checksums.push((c.tick, c.checksum));
+                                    } else if let Some(&(_, checksum)) =
+                                        self.local_checksums.iter().find(|(tick, _)| *tick == c.tick)
+                                    {
+                                        self.verify_checksum(c.tick, checksum, c.checksum);
+                                    }
                                 },
+
+                                _ => {}
                             }
                         },
 
@@ -101,6 +140,16 @@ impl SceneTrait for MultiPlayerSpectateScene {
             break;
         }
 
+        // Request the missing frames (lost packets or just connected)
+        if let State::Normal | State::Paused = self.state {
+            self.input_request_timer = self.input_request_timer.saturating_sub(dt);
+            if self.next_tick < self.latest_tick && self.input_request_timer == 0 {
+                let request = InputRequest { tick: self.next_tick };
+                let _ = self.client.send(Channel::Unreliable, MultiplayerMessages::InputRequest(request));
+                self.input_request_timer = INPUT_REQUEST_INTERVAL;
+            }
+        }
+
         // pause
         let options_button = persistent.input_mapping.button("options".to_string());
         if options_button.pressed() {
@@ -171,6 +220,15 @@ impl SceneTrait for MultiPlayerSpectateScene {
                     }
                 },
 
+                State::Desynced => {
+                    ui::Ui::builder(window_layout).build(app);
+                    ui::Text::new("GAME DESYNCED", app);
+
+                    if ui::Button::new("QUIT", app).pressed {
+                        self.state = State::Quitting;
+                    }
+                },
+
                 _ => {}
             }
         }
@@ -215,6 +273,10 @@ impl SceneTrait for MultiPlayerSpectateScene {
             None,
             None,
         );
+
+        self.tetris_game.render_combo_and_back_to_back(Vec2 { x: 10.0, y: 252.0 }, app);
+
+        self.tetris_game.render_top_out_reason(Vec2 { x: 10.0, y: 336.0 }, app);
     }
 
     fn handle_input(
@@ -260,40 +322,120 @@ impl MultiPlayerSpectateScene {
 
         let client = Client::new(persistent.rng.next_u64()).unwrap();
 
+        let mut scene = Self {
+            state: State::ConnectMenu,
+            start_menu_server_ip: "127.0.0.1:42042".to_owned(),
+            tetris_game,
+            client,
+
+            seed: 0,
+            rotation_system: RotationSystem::SRS,
+            next_tick: 0,
+            latest_tick: 0,
+            input_request_timer: 0,
+            local_checksums: Vec::new(),
+            remote_checksums: Vec::new(),
+            desync_restarts: 0,
+
+            playfield_pos: Vec2i::new(),
+            hold_piece_window_pos: Vec2i::new(),
+            next_pieces_preview_window_pos: Vec2i::new(),
+        };
+
+        scene.update_layout(app, persistent);
+        scene
+    }
+
+    fn restart_game(&mut self) {
+        self.tetris_game = TetrisGame::new(self.rotation_system.into(), self.seed);
+        self.next_tick = 0;
+        self.latest_tick = 0;
+        self.input_request_timer = 0;
+        self.local_checksums.clear();
+        self.remote_checksums.clear();
+    }
+
+    fn simulate_inputs(&mut self, inputs: Inputs) {
+        if let State::Desynced = self.state { return; }
+
+        self.latest_tick = self.latest_tick.max(inputs.latest_tick);
+
+        let previous_next_tick = self.next_tick;
+
+        // Frames already simulated are skipped, and the ones after a gap wait for the request
+        for (tick, frame) in (inputs.first_tick..).zip(inputs.frames) {
+            if tick != self.next_tick { continue; }
+
+            self.tetris_game.update_with_input_frame(frame);
+            self.next_tick += 1;
+
+            if self.next_tick % CHECKSUM_INTERVAL == 0 {
+                let tick = self.next_tick;
+                let checksum = self.tetris_game.checksum();
+
+                self.local_checksums.push((tick, checksum));
+                if self.local_checksums.len() > 8 {
+                    self.local_checksums.remove(0);
+                }
+
+                if let Some(index) = self.remote_checksums.iter().position(|(t, _)| *t == tick) {
+                    let (_, remote_checksum) = self.remote_checksums.remove(index);
+                    if !self.verify_checksum(tick, checksum, remote_checksum) { return; }
+                }
+            }
+        }
+
+        // Still catching up, request the next frames right away
+        if self.next_tick > previous_next_tick && self.next_tick < self.latest_tick {
+            self.input_request_timer = 0;
+        }
+    }
+
+    // Returns false if the game desynced and was restarted, or stopped if it keeps desyncing
+    fn verify_checksum(&mut self, tick: u32, local_checksum: u32, remote_checksum: u32) -> bool {
+        if local_checksum == remote_checksum { return true; }
+        if let State::Desynced = self.state { return false; }
+
+        println!(
+            "[game][scenes][multiplayer_spectate] desync at tick {}: {:08x} != {:08x}",
+            tick, local_checksum, remote_checksum
+        );
+
+        // @TODO find what diverged. For now, simulate everything again once
+        if self.desync_restarts >= MAX_DESYNC_RESTARTS {
+            self.state = State::Desynced;
+            return false;
+        }
+
+        self.desync_restarts += 1;
+        self.restart_game();
+        false
+    }
+
+    fn update_layout(&mut self, app: &mut App, persistent: &mut PersistentData) {
         // @Refactor use InstanceStyle
         // Playfield rendering
         let playfield_draw_size = get_draw_playfield_size(
-            &tetris_game.playfield(),
+            &self.tetris_game.playfield(),
             persistent.pixel_scale,
             true,
         );
 
         let window_size = app.window_size();
-        let playfield_pos = Vec2i {
+        self.playfield_pos = Vec2i {
             x: (window_size.0 as i32 - playfield_draw_size.x) / 2,
             y: (window_size.1 as i32 - playfield_draw_size.y) / 2,
         };
 
-        let hold_window_size = tetris_game.hold_piece_window_size(true, persistent);
-        let hold_piece_window_pos =
-            playfield_pos +
+        let hold_window_size = self.tetris_game.hold_piece_window_size(true, persistent);
+        self.hold_piece_window_pos =
+            self.playfield_pos +
             Vec2i { x: -20, y: 0 } +
             Vec2i { x: -hold_window_size.x, y: 0 };
 
-        let next_pieces_preview_window_pos =
-            playfield_pos +
+        self.next_pieces_preview_window_pos =
+            self.playfield_pos +
             Vec2i { x: 20, y: 0 } +
             Vec2i { x: playfield_draw_size.x, y: 0 };
-
-        Self {
-            state: State::ConnectMenu,
-            start_menu_server_ip: "127.0.0.1:42042".to_owned(),
-            tetris_game,
-            client,
-
-            playfield_pos,
-            hold_piece_window_pos,
-            next_pieces_preview_window_pos,
-        }
     }
 }
@@ -2,6 +2,7 @@ pub use rand_core::RngCore;
 
 use crate::app::*;
 use crate::game::input::get_default_input_mapping;
+use crate::game::pieces::load_custom_rotation_systems;
 
 // Persistent Data
 #[derive(ImDraw)]
@@ -38,6 +39,9 @@ impl PersistentData {
         // Music
         let music_id = app.load_music("assets/sfx/Original-Tetris-theme.ogg");
 
+        // Rotation systems
+        load_custom_rotation_systems("assets/rotation_systems");
+
         Self {
             input_mapping,
             sprites: Sprites {
@@ -1,5 +1,5 @@
 use crate::app::*;
-use crate::linalg::Vec2i;
+use crate::linalg::{Vec2, Vec2i};
 
 use super::*;
 
@@ -137,6 +137,10 @@ impl SceneTrait for SinglePlayerScene {
             None,
             None,
         );
+
+        self.tetris_game.render_combo_and_back_to_back(Vec2 { x: 10.0, y: 252.0 }, app);
+
+        self.tetris_game.render_top_out_reason(Vec2 { x: 10.0, y: 336.0 }, app);
     }
 
     fn handle_input(
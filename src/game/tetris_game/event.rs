@@ -0,0 +1,31 @@
+use crate::app::ImDraw;
+use crate::game::{
+    pieces::PieceVariant,
+    rules::{
+        lock::{LockedPiece, LockedPieceResult},
+        topout::TopOutReason,
+    },
+};
+
+// Events that happened during a single TetrisGame update. Audio, effects, statistics, replays and
+// networking should use these instead of inspecting the game state
+#[derive(Copy, Clone, Debug, ImDraw)]
+pub enum TetrisEvent {
+    PieceSpawned { variant: PieceVariant },
+    Moved { dx: i8 },
+    Fell { rows: u8 }, // gravity
+    Rotated { kick: u8 },
+    SoftDropped,
+    HardDropped { rows: u8 },
+    Held,
+    LockStarted,
+    LockCancelled, // piece left the stack before locking
+    Locked { locked_piece: LockedPiece },
+    LinesCleared { rows: u8, kind: LockedPieceResult },
+    LevelUp { level: u32 },
+    Attack { rows: u8 }, // garbage to send to the opponents
+    GarbageReceived { rows: u8 },  // queued, waiting to be inserted
+    GarbageCancelled { rows: u8 }, // pending rows cancelled by an attack
+    GarbageInserted { rows: u8 },
+    ToppedOut { reason: TopOutReason },
+}
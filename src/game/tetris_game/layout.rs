@@ -7,6 +7,7 @@ use super::*;
 #[derive(Debug, ImDraw, Copy, Clone)]
 pub struct TetrisLayout {
     playfield_pos: Vec2i,
+    playfield_pixel_scale: u8,
 
     hold_piece_window_pos: Vec2i,
     // hold_piece_pixel_scale
@@ -26,15 +27,24 @@ impl TetrisGame {
         persistent: &mut PersistentData,
     ) -> TetrisLayout {
         let has_grid = true;
+        let window_size = app.window_size();
 
         // Playfield rendering
-        let playfield_draw_size = get_draw_playfield_size(
-            &self.playfield(),
-            persistent.pixel_scale, // @TODO change the order of has_grid and pixel_scale?
-            has_grid,
-        );
+        // Tall playfields use a smaller pixel scale to fit the window
+        let mut playfield_pixel_scale = persistent.pixel_scale;
+        let playfield_draw_size = loop {
+            let playfield_draw_size = get_draw_playfield_size(
+                &self.playfield(),
+                playfield_pixel_scale, // @TODO change the order of has_grid and pixel_scale?
+                has_grid,
+            );
+
+            if playfield_pixel_scale <= 1 || playfield_draw_size.y <= window_size.1 as i32 {
+                break playfield_draw_size;
+            }
+            playfield_pixel_scale -= 1;
+        };
 
-        let window_size = app.window_size();
         let playfield_pos = Vec2i {
             x: (window_size.0 as i32 - playfield_draw_size.x) / 2,
             y: (window_size.1 as i32 - playfield_draw_size.y) / 2,
@@ -83,6 +93,7 @@ impl TetrisGame {
 
         TetrisLayout {
             playfield_pos,
+            playfield_pixel_scale,
             hold_piece_window_pos,
             next_pieces_preview_window_pos,
             next_pieces_preview_pixel_scale,
@@ -106,7 +117,13 @@ impl TetrisGame {
         batch: &mut Batch,
         persistent: &mut PersistentData,
     ) {
-        self.render_playfield(layout.playfield_pos, layout.has_grid, batch, persistent);
+        self.render_playfield_with_pixel_scale(
+            layout.playfield_pos,
+            layout.playfield_pixel_scale,
+            layout.has_grid,
+            batch,
+            persistent
+        );
         self.render_hold_piece(layout.hold_piece_window_pos, layout.has_grid, batch, persistent);
 
         for index in 0..self.rules.next_pieces_preview_count {
@@ -127,11 +144,22 @@ impl TetrisGame {
         has_grid: bool,
         batch: &mut Batch,
         persistent: &mut PersistentData
+    ) {
+        self.render_playfield_with_pixel_scale(pos, persistent.pixel_scale, has_grid, batch, persistent);
+    }
+
+    pub fn render_playfield_with_pixel_scale(
+        &self,
+        pos: Vec2i,
+        pixel_scale: u8,
+        has_grid: bool,
+        batch: &mut Batch,
+        persistent: &mut PersistentData
     ) {
         // playfield
         let playfield_size = get_draw_playfield_size(
             &self.playfield,
-            persistent.pixel_scale,
+            pixel_scale,
             has_grid,
         );
 
@@ -147,11 +175,11 @@ impl TetrisGame {
             draw_playfield(
                 &self.playfield,
                 pos,
-                persistent.pixel_scale,
+                pixel_scale,
                 has_grid,
                 Some((
                     self.lock_piece_timestamp,
-                    self.rules.line_clear_delay,
+                    self.line_clear_delay(),
                     line_clear_animation_type,
                     lines_to_clear,
                     self.timestamp,
@@ -164,7 +192,7 @@ impl TetrisGame {
             draw_playfield(
                 &self.playfield,
                 pos,
-                persistent.pixel_scale,
+                pixel_scale,
                 has_grid,
                 None,
                 self.rules.rotation_system,
@@ -187,7 +215,7 @@ impl TetrisGame {
                     Color { r: 1., g: 1., b: 1., a: 0.1 }, // @TODO create ghost color
                     &self.playfield,
                     pos,
-                    persistent.pixel_scale,
+                    pixel_scale,
                     has_grid,
                     batch,
                     persistent
@@ -216,7 +244,7 @@ impl TetrisGame {
                         Color { r: 1., g: 1., b: 1., a: 0.1 }, // @TODO create ghost color
                         &self.playfield,
                         pos,
-                        persistent.pixel_scale,
+                        pixel_scale,
                         has_grid,
                         batch,
                         persistent
@@ -233,8 +261,8 @@ impl TetrisGame {
             }
 
             let color;
-            if self.is_locking && self.rules.has_locking_animation {
-                let delta_time = self.timestamp - self.locking_animation_timestamp;
+            if self.state == TetrisState::Locking && self.rules.has_locking_animation {
+                let delta_time = self.state_duration();
                 let alpha = 2.0 * std::f32::consts::PI * delta_time as f32;
                 let alpha = alpha / (self.rules.locking_animation_duration as f32);
                 let alpha = ((1.0 + alpha.sin()) / 2.0) / 2.0 + 0.5;
@@ -251,7 +279,7 @@ impl TetrisGame {
                 color,
                 &self.playfield,
                 pos,
-                persistent.pixel_scale,
+                pixel_scale,
                 has_grid,
                 batch,
                 persistent
@@ -391,4 +419,46 @@ impl TetrisGame {
             }
         }
     }
+
+    // Combo and back-to-back counters of the HUD, only drawn when active
+    pub fn render_combo_and_back_to_back(&self, pos: Vec2, app: &mut App) {
+        if let Some(combo) = self.combo() {
+            if combo > 0 {
+                app.queue_draw_text(
+                    &format!("combo: {}", combo),
+                    TransformBuilder::new().pos_xy(pos.x, pos.y).layer(800).build(),
+                    32.,
+                    WHITE,
+                    None,
+                    None,
+                );
+            }
+        }
+
+        if let Some(back_to_back) = self.back_to_back() {
+            if back_to_back > 0 {
+                app.queue_draw_text(
+                    &format!("b2b: {}", back_to_back),
+                    TransformBuilder::new().pos_xy(pos.x, pos.y + 42.0).layer(800).build(),
+                    32.,
+                    WHITE,
+                    None,
+                    None,
+                );
+            }
+        }
+    }
+
+    pub fn render_top_out_reason(&self, pos: Vec2, app: &mut App) {
+        if let Some(top_out_reason) = self.top_out_reason() {
+            app.queue_draw_text(
+                &format!("game over: {}", top_out_reason.name()),
+                TransformBuilder::new().pos_xy(pos.x, pos.y).layer(800).build(),
+                32.,
+                WHITE,
+                None,
+                None,
+            );
+        }
+    }
 }
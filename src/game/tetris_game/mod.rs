@@ -4,13 +4,19 @@ use crate::linalg::*;
 
 use crate::game::{
     input::*,
-    pieces::{get_piece_variant_color, Piece, PieceVariant},
-    playfield::{PLAYFIELD_VISIBLE_HEIGHT, Playfield},
+    pieces::{get_piece_set, get_piece_variant_color, Piece, PieceVariant},
+    playfield::Playfield,
     randomizer::*,
     render::*,
     rules::{
+        Gravity,
+        HardDropRule,
         LockDelayRule,
         Rules,
+        SoftDropRule,
+        tgm_level_up,
+        garbage::GarbageQueue,
+        line_clear::LineClear,
         lock::*,
         movement::*,
         rotation::*,
@@ -20,9 +26,11 @@ use crate::game::{
     scenes::PersistentData,
 };
 
+mod event;
 mod network;
 mod layout;
 
+pub use event::*;
 pub use network::*;
 pub use layout::*;
 
@@ -33,15 +41,24 @@ pub const NEXT_PIECES_COUNT: usize = 8;
 pub struct TetrisGame {
     timestamp: u64, // @TODO Time
 
-    // @Maybe add which topout rule was the cause
-    has_topped_out: bool, // per game
+    state: TetrisState, // per game
+    state_timestamp: u64, // per state
+    top_out_reason: Option<TopOutReason>, // per game
+
+    events: Vec<TetrisEvent>, // per frame
+
 
     rules: Rules,           // per game
     playfield: Playfield,   // per game
     randomizer: Randomizer, // per game
+    garbage_queue: GarbageQueue, // per game
 
     current_score: u32,       // per game
     total_lines_cleared: u32, // per game
+    total_line_clear_awards: u32, // per game
+    tgm_level: u32, // per game. Only used by LevelCurve::TGM
+    scoring_state: ScoringState, // per game
+    last_cascade_chains: u32, // per line clear
 
     current_piece: Option<(Piece, Vec2i)>,
     next_piece_types: [PieceVariant; NEXT_PIECES_COUNT], // per game
@@ -54,18 +71,20 @@ pub struct TetrisGame {
     hold_piece: Option<Piece>, // per game
     has_used_hold: bool, // per piece
 
-    is_locking: bool, // per piece
     remaining_lock_delay: LockDelayRule, // per piece // @Maybe we should make this count up to not copy on construction
+    lowest_row: i32, // per piece, lowest row reached by the piece blocks (for lock delay resets)
 
     has_moved: bool,   // per frame
     has_rotated: bool, // per frame
     has_stepped: bool, // per frame
     last_piece_action: LastPieceAction, // per frame
 
-    // Movement timestamps. Used for gravity and animations
+    // Movement timestamps. Used for animations
     movement_last_timestamp_x: u64,
     movement_last_timestamp_y: u64,
 
+    gravity_accumulator: u64, // per piece
+
     // @Maybe split animation data into another struct. This will for sure be modified when styles
     //        are implemented
     // Animations
@@ -73,8 +92,6 @@ pub struct TetrisGame {
     movement_animation_delta_grid_y: f32,
     movement_animation_current_delta_grid: Vec2,
 
-    locking_animation_timestamp: u64,
-
     // @Maybe this should be part of the game, but it depends on window size, pixel scale, etc
     //layout: TetrisLayout,
 }
@@ -84,6 +101,20 @@ impl TetrisGame {
     pub fn rules(&self)      -> &Rules      { &self.rules }
     pub fn playfield(&self)  -> &Playfield  { &self.playfield }
     pub fn randomizer(&self) -> &Randomizer { &self.randomizer }
+    pub fn state(&self)      -> TetrisState { self.state }
+    pub fn events(&self)     -> &[TetrisEvent] { &self.events }
+}
+
+impl TetrisGame {
+    // Returns the upcoming pieces: the next pieces preview followed by the pieces the randomizer
+    // will generate, without consuming them
+    pub fn peek_next_pieces(&self, count: usize) -> Vec<PieceVariant> {
+        let mut pieces: Vec<PieceVariant> = self.next_piece_types.iter().copied().take(count).collect();
+        if count > pieces.len() {
+            pieces.extend(self.randomizer.peek(count - pieces.len()));
+        }
+        pieces
+    }
 }
 
 impl TetrisGame {
@@ -91,11 +122,16 @@ impl TetrisGame {
         rules: Rules,
         seed: u64,
     ) -> Self {
-        let playfield_grid_size = Vec2i { x: 10, y: 40 };
-        let playfield = Playfield::new(playfield_grid_size, PLAYFIELD_VISIBLE_HEIGHT);
+        let playfield_grid_size = Vec2i {
+            x: rules.playfield_width as i32,
+            y: rules.playfield_height as i32,
+        };
+        let visible_height = rules.playfield_visible_height.min(rules.playfield_height);
+        let playfield = Playfield::new(playfield_grid_size, visible_height);
 
         // rng
-        let mut randomizer: Randomizer = rules.randomizer_type.build(seed);
+        let piece_set = get_piece_set(rules.rotation_system);
+        let mut randomizer: Randomizer = rules.randomizer_type.build(seed, piece_set);
 
         // next pieces preview window
         let mut next_piece_types = [PieceVariant::I; NEXT_PIECES_COUNT];
@@ -105,18 +141,28 @@ impl TetrisGame {
 
         // lock delay
         let remaining_lock_delay = rules.lock_delay;
+        let tgm_level = rules.start_level as u32;
 
         Self {
             timestamp: 0,
 
-            has_topped_out: false,
+            state: TetrisState::Spawning,
+            state_timestamp: 0,
+            top_out_reason: None,
+
+            events: Vec::new(),
 
             playfield,
             rules,
             randomizer,
+            garbage_queue: GarbageQueue::new(seed),
 
             current_score: 0,
             total_lines_cleared: 0,
+            total_line_clear_awards: 0,
+            tgm_level,
+            scoring_state: ScoringState::default(),
+            last_cascade_chains: 0,
 
             current_piece: None,
             next_piece_types,
@@ -129,8 +175,8 @@ impl TetrisGame {
             hold_piece: None,
             has_used_hold: false,
 
-            is_locking: false,
             remaining_lock_delay,
+            lowest_row: i32::MAX,
 
             has_moved: false,
             has_rotated: false,
@@ -140,11 +186,11 @@ impl TetrisGame {
             movement_last_timestamp_x: 0,
             movement_last_timestamp_y: 0,
 
+            gravity_accumulator: 0,
+
             movement_animation_delta_grid_x: 0.0,
             movement_animation_delta_grid_y: 0.0,
             movement_animation_current_delta_grid: Vec2::new(),
-
-            locking_animation_timestamp: 0,
         }
     }
 
@@ -161,20 +207,30 @@ impl TetrisGame {
 
         // lock delay
         let remaining_lock_delay = rules.lock_delay;
+        let tgm_level = rules.start_level as u32;
 
         //
 
         Self {
             timestamp: 0,
 
-            has_topped_out: false,
+            state: TetrisState::Spawning,
+            state_timestamp: 0,
+            top_out_reason: None,
+
+            events: Vec::new(),
 
             playfield,
             rules,
             randomizer,
+            garbage_queue: GarbageQueue::new(0),
 
             current_score: 0,
             total_lines_cleared: 0,
+            total_line_clear_awards: 0,
+            tgm_level,
+            scoring_state: ScoringState::default(),
+            last_cascade_chains: 0,
 
             current_piece: None,
             next_piece_types,
@@ -187,8 +243,8 @@ impl TetrisGame {
             hold_piece: None,
             has_used_hold: false,
 
-            is_locking: false,
             remaining_lock_delay,
+            lowest_row: i32::MAX,
 
             has_moved: false,
             has_rotated: false,
@@ -198,11 +254,11 @@ impl TetrisGame {
             movement_last_timestamp_x: 0,
             movement_last_timestamp_y: 0,
 
+            gravity_accumulator: 0,
+
             movement_animation_delta_grid_x: 0.0,
             movement_animation_delta_grid_y: 0.0,
             movement_animation_current_delta_grid: Vec2::new(),
-
-            locking_animation_timestamp: 0,
         }
     }
 
@@ -211,47 +267,66 @@ impl TetrisGame {
         dt: u64, // @TODO Duration
         input_mapping: &M,
         app: &mut App, // @Remove only used to check input repeated over time, which should be improved to not need the whole app
-    ) -> bool {
-        if self.has_topped_out { return false; }
+    ) -> &[TetrisEvent] {
+        let input = TetrisInput::from_input_mapping(input_mapping, &self.rules, self.level(), app);
+        self.update_with_input(dt, input)
+    }
 
-        self.timestamp += dt;
+    // Returns the events that happened in this update
+    pub fn update_with_input(
+        &mut self,
+        dt: u64, // @TODO Duration
+        input: TetrisInput,
+    ) -> &[TetrisEvent] {
+        self.events.clear();
 
-        let mut has_updated = false;
+        if self.state == TetrisState::ToppedOut { return &self.events; }
+
+        self.timestamp += dt;
+        self.randomizer.update(dt);
 
         // locking
         // This is done in the start of the frame to be just and consider the time the piece is
         // locking is the last frame duration. If the piece locks, all input will be ignored this
         // frame, even if there's no entry delay
-        if self.current_piece.is_some() && self.rules.lock_delay != LockDelayRule::NoDelay {
-            let was_locking = self.is_locking;
-            self.is_locking = is_piece_locking(
+        if self.state.has_piece() && self.rules.lock_delay != LockDelayRule::NoDelay {
+            let lock_delay = self.rules.get_lock_delay(self.level());
+            let was_locking = self.state == TetrisState::Locking;
+            let is_locking = is_piece_locking(
                 &self.current_piece.as_ref().unwrap().0,
                 self.current_piece.as_ref().unwrap().1,
                 &self.playfield,
             );
 
+            // Step reset and extended placement reset completely when the piece reaches a new lowest
+            // row
+            let (piece, piece_pos) = self.current_piece.unwrap();
+            let piece_row = piece_pos.y + piece.min_max_y().0 as i32;
+            let has_reached_lowest_row = piece_row < self.lowest_row;
+            self.lowest_row = self.lowest_row.min(piece_row);
+
+            if has_reached_lowest_row {
+                match lock_delay {
+                    LockDelayRule::StepReset(_) | LockDelayRule::ExtendedPlacement { .. } => {
+                        self.remaining_lock_delay = lock_delay;
+                    }
+                    _ => {}
+                }
+            }
+
             let has_locked = match self.remaining_lock_delay {
                 // Locking duration resets when a new piece enters
                 LockDelayRule::EntryReset(ref mut duration) => {
-                    if was_locking && self.is_locking {
+                    if was_locking && is_locking {
                         *duration = duration.saturating_sub(dt);
                     }
 
                     *duration == 0
                 },
 
-                // Every step (gravity movement) resets the locking duration
+                // Only steps (gravity movement) to a new lowest row reset the locking duration
                 LockDelayRule::StepReset(ref mut duration) => {
-                    if self.has_stepped {
-                        match self.rules.lock_delay {
-                            LockDelayRule::StepReset(lock_duration) => {
-                                *duration = lock_duration;
-                            }
-                            _ => unreachable!(),
-                        }
-                    }
-
-                    if self.is_locking {
+                    if is_locking {
                         *duration = duration.saturating_sub(dt);
                         *duration == 0
                     } else {
@@ -269,7 +344,7 @@ impl TetrisGame {
                 } => {
                     // Only reset duration if has movements/rotations left
                     if (self.has_moved && *movements > 0) || (self.has_rotated && *rotations > 0) {
-                        match self.rules.lock_delay {
+                        match lock_delay {
                             LockDelayRule::MoveReset { duration: lock_duration, .. } => {
                                 *duration = lock_duration;
                             }
@@ -277,7 +352,7 @@ impl TetrisGame {
                         }
                     };
 
-                    if self.is_locking {
+                    if is_locking {
                         if !self.has_moved && !self.has_rotated {
                             *duration = duration.saturating_sub(dt);
                         }
@@ -291,21 +366,51 @@ impl TetrisGame {
                     }
                 },
 
-                _ => { false }
+                // Like move reset, but movements and rotations share the limit, which resets when
+                // the piece reaches a new lowest row. Without moves left the piece locks as soon as
+                // it touches the stack
+                LockDelayRule::ExtendedPlacement { ref mut duration, ref mut moves } => {
+                    if is_locking && !has_reached_lowest_row {
+                        if *moves == 0 {
+                            true
+                        } else {
+                            if self.has_moved || self.has_rotated {
+                                *moves -= 1;
+                                *duration = lock_delay.duration();
+                            } else {
+                                *duration = duration.saturating_sub(dt);
+                            }
+
+                            *duration == 0
+                        }
+                    } else {
+                        false
+                    }
+                },
+
+                // Every movement and rotation resets the locking duration, without limits
+                LockDelayRule::Infinity(ref mut duration) => {
+                    if self.has_moved || self.has_rotated || self.has_stepped {
+                        *duration = lock_delay.duration();
+                    } else if is_locking {
+                        *duration = duration.saturating_sub(dt);
+                    }
+
+                    is_locking && *duration == 0
+                },
+
+                LockDelayRule::NoDelay => { false }
             };
 
             if has_locked {
                 self.lock_piece();
-                has_updated = true;
-            }
-
-            // animation
-            if !was_locking && self.is_locking {
-                self.locking_animation_timestamp = self.timestamp;
-                has_updated = true;
+            } else if !was_locking && is_locking {
+                self.set_state(TetrisState::Locking);
+                self.events.push(TetrisEvent::LockStarted);
+            } else if was_locking && !is_locking {
+                self.set_state(TetrisState::Falling);
+                self.events.push(TetrisEvent::LockCancelled);
             }
-        } else {
-            self.is_locking = false;
         }
 
         // reset frame values
@@ -314,27 +419,9 @@ impl TetrisGame {
         self.has_stepped = false;
 
         // movement input
-        if self.current_piece.is_some() {
+        if self.state.has_piece() {
             // Horizontal movement logic
-            let mut horizontal_movement = 0;
-
-            let left_button = input_mapping.button(KEY_LEFT.to_string());
-            if left_button.pressed_repeat_with_delay(
-                self.rules.das_repeat_delay,
-                self.rules.das_repeat_interval,
-                app
-            ) {
-                horizontal_movement -= 1;
-            }
-
-            let right_button = input_mapping.button(KEY_RIGHT.to_string());
-            if right_button.pressed_repeat_with_delay(
-                self.rules.das_repeat_delay,
-                self.rules.das_repeat_interval,
-                app
-            ) {
-                horizontal_movement += 1;
-            }
+            let horizontal_movement = input.horizontal_movement as i32;
 
             let (piece, piece_pos) = self.current_piece.as_mut().unwrap();
             if horizontal_movement != 0 && try_move_piece(
@@ -351,40 +438,31 @@ impl TetrisGame {
                 self.has_moved = true;
                 self.last_piece_action = LastPieceAction::Movement;
 
-                has_updated = true;
+                self.events.push(TetrisEvent::Moved { dx: input.horizontal_movement });
             }
 
             // Soft drop
-            let down_button = input_mapping.button(KEY_SOFT_DROP.to_string());
-            if down_button.pressed_repeat(self.rules.soft_drop_interval, app) {
+            if input.soft_drop {
                 if self.try_soft_drop_piece() {
-                    has_updated = true;
+                    self.events.push(TetrisEvent::SoftDropped);
                 }
             }
 
             // Rotate
-            let mut rotation = 0;
-
-            let ccw_button = input_mapping.button(KEY_ROTATE_CCW.to_string());
-            if ccw_button.pressed() { rotation -= 1; }
-
-            let cw_button = input_mapping.button(KEY_ROTATE_CW.to_string());
-            if cw_button.pressed() { rotation += 1; }
-
-            if rotation != 0 {
+            if let Some(direction) = input.rotation {
                 if let Some(ref mut piece) = self.current_piece {
-                    if try_rotate_piece(
+                    if let Some(kick) = try_rotate_piece(
                         &mut piece.0,
                         &mut piece.1,
-                        rotation > 0,
+                        direction,
                         &self.playfield,
                         &self.rules
                     ) {
                         self.has_rotated = true;
-                        self.last_piece_action = LastPieceAction::Rotation;
+                        self.last_piece_action = LastPieceAction::Rotation { direction, kick };
                         // @TODO soft drop scoring
 
-                        has_updated = true;
+                        self.events.push(TetrisEvent::Rotated { kick });
                     }
                 }
             }
@@ -395,88 +473,72 @@ impl TetrisGame {
         // verify again if the current piece is available or not
         //
 
+        // Soft drop lock: soft dropping a piece that is on the stack locks it right away
+        if self.state.has_piece() &&
+            input.soft_drop &&
+            self.rules.soft_drop_rule == SoftDropRule::SoftDropLock &&
+            is_piece_locking(
+                &self.current_piece.as_ref().unwrap().0,
+                self.current_piece.as_ref().unwrap().1,
+                &self.playfield,
+            )
+        {
+            self.lock_piece();
+        }
+
         // Hard drop
-        if self.current_piece.is_some() {
-            let hard_drop_button = input_mapping.button(KEY_HARD_DROP.to_string());
-            if hard_drop_button.pressed() { // @TODO DAS
-                if self.try_hard_drop_piece() {
-                    has_updated = true;
-                }
-            }
+        if self.state.has_piece() && input.hard_drop {
+            self.try_hard_drop_piece();
         }
 
         // Hold piece
-        if self.rules.has_hold_piece && self.current_piece.is_some() {
-            let hold_button = input_mapping.button(KEY_HOLD.to_string());
-            if hold_button.pressed() {
-                if !self.has_used_hold {
-                    match self.hold_piece.take() {
-                        Some(hold_piece) => {
-                            let (piece, piece_pos) = &mut self.current_piece.as_mut().unwrap();
-
-                            if self.rules.hold_piece_reset_rotation {
-                                piece.rot = 0;
-                            }
-
-                            self.hold_piece = Some(*piece);
-
-                            *piece = hold_piece;
-                            *piece_pos = Vec2i {
-                                x: self.playfield.grid_size.x / 2 - 2,
-                                y: self.rules.spawn_row as i32 - 3,
-                            };
-
-                            self.has_used_hold = true;
-
-                            // update movement timestamps
-                            self.movement_last_timestamp_x = self.timestamp;
-                            self.movement_last_timestamp_y = self.timestamp;
-                        }
-
-                        None => {
-                            let mut piece = self.current_piece.as_mut().unwrap().0;
-                            if self.rules.hold_piece_reset_rotation {
-                                piece.rot = 0;
-                            }
-                            self.hold_piece = Some(piece);
-
-                            // since this is not locking a piece (won't trigger animation or ARE),
-                            // so we don't update the lock timestamp.
-                            self.current_piece = None;
-                        }
-                    }
-
-                    has_updated = true;
-                }
+        if self.rules.has_hold_piece && self.state.has_piece() && input.hold && !self.has_used_hold {
+            if !self.swap_hold_piece() {
+                // since this is not locking a piece, there's no entry delay (ARE) and the next
+                // piece spawns right away
+                self.spawn_piece(TetrisInput::default());
+                self.has_used_hold = true;
             }
         }
 
         // Gravity
-        if self.current_piece.is_some() {
-            // @TODO move this to Rules (or something)
-            if let Some(gravity_interval) = self.rules.get_gravity_interval(self.level()) {
-                if self.timestamp >= self.movement_last_timestamp_y + gravity_interval {
-                    let (piece, piece_pos) = self.current_piece.as_mut().unwrap();
-                    if try_apply_gravity(
-                        piece,
-                        piece_pos,
-                        &self.playfield,
-                    ) {
-                        // Gravity move successful
-
+        if self.state.has_piece() {
+            if let Some(gravity) = self.rules.get_gravity(self.level()) {
+                let rows_to_move = if gravity.is_instant() {
+                    u64::MAX
+                } else {
+                    // The gravity can be higher than 1G, so the piece may move multiple rows in a
+                    // single update
+                    self.gravity_accumulator += gravity.accumulate(dt);
+                    let rows_to_move = self.gravity_accumulator / Gravity::ROW;
+                    self.gravity_accumulator %= Gravity::ROW;
+                    rows_to_move
+                };
+
+                if rows_to_move > 0 {
+                    let (rows_moved, is_blocked) = self.apply_gravity_rows(rows_to_move);
+
+                    if rows_moved > 0 {
                         self.has_stepped = true;
 
                         self.movement_last_timestamp_y = self.timestamp;
-                        self.movement_animation_delta_grid_y = self.movement_animation_current_delta_grid.y + 1.0;
+                        if !gravity.is_instant() {
+                            self.movement_animation_delta_grid_y =
+                                self.movement_animation_current_delta_grid.y + rows_moved as f32;
+                        }
 
-                        has_updated = true;
-                    } else {
+                        self.events.push(TetrisEvent::Fell { rows: rows_moved as u8 });
+                    }
+
+                    if is_blocked {
                         // Piece blocked: lock piece
 
+                        // Don't accumulate gravity while the piece is blocked
+                        self.gravity_accumulator = 0;
+
                         // Only lock on gravity movement if there's no lock delay
                         if self.rules.lock_delay == LockDelayRule::NoDelay {
                             self.lock_piece();
-                            has_updated = true;
                         }
                     }
                 }
@@ -484,70 +546,207 @@ impl TetrisGame {
         }
 
         // Line clear
-        let can_spawn_new_piece;
-        if let Some(LockedPiece { lock_piece_result, .. }) = self.last_locked_piece {
-            if let LockedPieceResult::Nothing = lock_piece_result {
-                // No lines to clear, so don't wait the line_clear_delay
-                can_spawn_new_piece = true;
-            } else {
-                // check if line_clear_delay time has passed
-                if self.timestamp >= self.lock_piece_timestamp + self.rules.line_clear_delay {
-                    self.update_score_and_line_cleared();
-                    if self.rules.try_clear_lines(&mut self.playfield) { has_updated = true; }
-                    can_spawn_new_piece = true;
-                } else {
-                    can_spawn_new_piece = false;
-                }
+        if self.state == TetrisState::LineClearDelay &&
+            self.timestamp >= self.state_timestamp + self.line_clear_delay()
+        {
+            let total_lines_cleared = self.total_lines_cleared;
+
+            self.update_score_and_line_cleared();
+            if let Some(line_clear) = self.rules.try_clear_lines(&mut self.playfield) {
+                self.update_score_and_line_cleared_cascade(&line_clear);
             }
-        } else {
-            can_spawn_new_piece = true;
+
+            let kind = self.last_locked_piece.unwrap().lock_piece_result;
+            self.events.push(TetrisEvent::LinesCleared {
+                rows: (self.total_lines_cleared - total_lines_cleared) as u8,
+                kind,
+            });
+
+            // The entry delay (ARE) only starts after the line clear delay
+            self.set_state(TetrisState::Spawning);
         }
 
         // New piece
-        if self.current_piece.is_none() &&
-            can_spawn_new_piece &&
-            // @Fix spawn_delay should be added after line_clear_delay
-            self.timestamp >= self.lock_piece_timestamp + self.rules.spawn_delay
+        if self.state == TetrisState::Spawning &&
+            self.timestamp >= self.state_timestamp + self.spawn_delay()
         {
-            self.new_piece();
+            self.spawn_piece(input);
+        }
+
+        &self.events
+    }
+
+    // Every state transition should go through this, so the state timer is consistent. Animations
+    // and effects that depend on state changes should be hooked here
+    fn set_state(&mut self, state: TetrisState) {
+        self.state = state;
+        self.state_timestamp = self.timestamp;
+    }
+
+    // Entry delay (ARE) of the next piece. Depends on the last locked piece
+    fn spawn_delay(&self) -> u64 {
+        let (lock_row, has_cleared_lines) = match self.last_locked_piece {
+            Some(LockedPiece { piece, pos, lock_piece_result, .. }) => {
+                let lock_row = piece.blocks().iter().map(|block| pos.y + block.y).min();
+                (lock_row, lock_piece_result.has_lines_to_clear())
+            }
+            None => (None, false),
+        };
+
+        self.rules.get_spawn_delay(self.level(), lock_row, has_cleared_lines)
+    }
+
+    pub fn line_clear_delay(&self) -> u64 {
+        self.rules.get_line_clear_delay(self.level())
+    }
+
+    // Time spent in the current state
+    pub fn state_duration(&self) -> u64 {
+        self.timestamp - self.state_timestamp
+    }
+
+    pub fn has_topped_out(&self) -> bool {
+        self.state == TetrisState::ToppedOut
+    }
+
+    pub fn top_out_reason(&self) -> Option<TopOutReason> {
+        self.top_out_reason
+    }
+
+    fn top_out(&mut self, reason: TopOutReason) {
+        self.set_state(TetrisState::ToppedOut);
+        self.top_out_reason = Some(reason);
+        self.events.push(TetrisEvent::ToppedOut { reason });
+    }
+
+    // Must be called before the garbage rows are added to the playfield
+    fn try_garbage_out(&mut self, garbage_rows: u8) -> bool {
+        if garbaged_out(&self.playfield, garbage_rows, &self.rules) {
+            self.top_out(TopOutReason::GarbageOut);
+            true
+        } else {
+            false
+        }
+    }
+
+    // Queues garbage rows received from another player. They enter the playfield after the garbage
+    // delay, when a piece locks without clearing lines
+    pub fn add_garbage(&mut self, rows: u8) {
+        if rows == 0 { return; }
+
+        let width = self.playfield.grid_size.x as u8;
+        self.garbage_queue.push(rows, self.timestamp, width, self.rules.garbage_hole_rule);
+        self.events.push(TetrisEvent::GarbageReceived { rows });
+    }
+
+    // Cancels pending garbage rows. Returns the rows that had nothing to cancel (they can be sent
+    // to the opponent)
+    pub fn cancel_garbage(&mut self, rows: u32) -> u32 {
+        let remaining_rows = self.garbage_queue.cancel(rows);
+        let cancelled_rows = rows - remaining_rows;
+        if cancelled_rows > 0 {
+            let rows = cancelled_rows.min(u8::MAX as u32) as u8;
+            self.events.push(TetrisEvent::GarbageCancelled { rows });
+        }
+        remaining_rows
+    }
+
+    // The attack cancels the pending garbage first, and what is left is sent to the opponents
+    fn attack(&mut self, rows: u32) {
+        let rows = if self.rules.has_garbage_cancel { self.cancel_garbage(rows) } else { rows };
+        if rows > 0 {
+            self.events.push(TetrisEvent::Attack { rows: rows.min(u8::MAX as u32) as u8 });
+        }
+    }
+
+    pub fn pending_garbage_rows(&self) -> u32 {
+        self.garbage_queue.pending_rows()
+    }
+
+    // Inserts the pending garbage that waited the garbage delay. If it garbages out, the game tops
+    // out instead
+    fn insert_garbage(&mut self) {
+        let holes = self.garbage_queue.take_ready(self.timestamp, self.rules.garbage_delay);
+        if holes.is_empty() { return; }
+
+        let rows = holes.len().min(u8::MAX as usize) as u8;
+        if self.try_garbage_out(rows) { return; }
+
+        self.playfield.add_garbage_rows(&holes);
+        self.events.push(TetrisEvent::GarbageInserted { rows });
+    }
+
+    // Spawns the next piece, checking for block out and applying the spawn rules
+    fn spawn_piece(&mut self, input: TetrisInput) {
+        self.new_piece();
+        self.tgm_level = tgm_level_up(self.tgm_level, 0);
+
+        // IHS: holding the hold button during the entry delay swaps the piece as it spawns
+        if self.rules.has_hold_piece && self.rules.has_initial_hold_system && input.hold_held {
+            if !self.swap_hold_piece() {
+                self.new_piece();
+            }
+            self.has_used_hold = true;
+        }
 
-            has_updated = true;
+        self.events.push(TetrisEvent::PieceSpawned {
+            variant: self.current_piece.unwrap().0.variant,
+        });
 
-            // check for block out
-            let (piece, piece_pos) = &self.current_piece.as_ref().unwrap();
-            let has_block_out = blocked_out(
+        // IRS: holding a rotation button during the entry delay spawns the piece rotated. If the
+        // rotation is blocked, the piece spawns unrotated
+        let initial_rotation = input.rotation_held.filter(|_| self.rules.has_initial_rotation_system);
+        if let Some(direction) = initial_rotation {
+            let (piece, piece_pos) = self.current_piece.as_mut().unwrap();
+            if let Some(kick) = try_rotate_piece(
                 piece,
-                *piece_pos,
+                piece_pos,
+                direction,
                 &self.playfield,
                 &self.rules
-            );
+            ) {
+                self.events.push(TetrisEvent::Rotated { kick });
+            }
+        }
+
+        // check for block out
+        let (piece, piece_pos) = &self.current_piece.as_ref().unwrap();
+        let has_block_out = blocked_out(
+            piece,
+            *piece_pos,
+            &self.playfield,
+            &self.rules
+        );
 
-            if has_block_out {
-                self.has_topped_out = true;
-                println!("game over: block out");
-                return true;
+        if has_block_out {
+            self.top_out(TopOutReason::BlockOut);
+            return;
+        }
+
+        // 20G: the piece spawns directly on the stack
+        if let Some(gravity) = self.rules.get_gravity(self.level()) {
+            if gravity.is_instant() {
+                self.apply_gravity_rows(u64::MAX);
             }
+        }
 
-            // spawn drop
-            if self.rules.spawn_drop {
-                while blocks_out_of_playfield(
-                    &self.current_piece.as_ref().unwrap().0,
-                    self.current_piece.as_ref().unwrap().1,
-                    self.playfield.visible_height,
-                ) > 0 {
-                    let (piece, piece_pos) = self.current_piece.as_mut().unwrap();
-                    if !try_apply_gravity(
-                        piece,
-                        piece_pos,
-                        &self.playfield,
-                    ) {
-                        break;
-                    }
+        // spawn drop
+        if self.rules.spawn_drop {
+            while blocks_out_of_playfield(
+                &self.current_piece.as_ref().unwrap().0,
+                self.current_piece.as_ref().unwrap().1,
+                self.playfield.visible_height,
+            ) > 0 {
+                let (piece, piece_pos) = self.current_piece.as_mut().unwrap();
+                if !try_apply_gravity(
+                    piece,
+                    piece_pos,
+                    &self.playfield,
+                ) {
+                    break;
                 }
             }
         }
-
-        has_updated
     }
 
     // @TODO private
@@ -611,12 +810,10 @@ impl TetrisGame {
             rotation_system: self.rules.rotation_system,
         };
 
-        let new_piece_pos = Vec2i {
-            x: (self.playfield.grid_size.x + 1) / 2 - 2,
-            y: self.rules.spawn_row as i32 - 3,
-        };
+        let new_piece_pos = self.spawn_position() + new_piece.spawn_offset();
 
         self.current_piece = Some((new_piece, new_piece_pos));
+        self.set_state(TetrisState::Falling);
 
         // cycle next pieces
         for i in 0..NEXT_PIECES_COUNT-1 { self.next_piece_types[i] = self.next_piece_types[i+1]; }
@@ -624,7 +821,8 @@ impl TetrisGame {
 
         // reset per piece data
         self.has_used_hold = false;
-        self.remaining_lock_delay = self.rules.lock_delay;
+        self.remaining_lock_delay = self.rules.get_lock_delay(self.level());
+        self.lowest_row = i32::MAX;
         self.last_locked_piece = None;
         self.soft_drop_steps = 0;
         self.hard_drop_steps = 0;
@@ -632,6 +830,7 @@ impl TetrisGame {
         // reset movement timestamps
         self.movement_last_timestamp_x = self.timestamp;
         self.movement_last_timestamp_y = self.timestamp;
+        self.gravity_accumulator = 0;
 
         // Animations
         // reset movement animation
@@ -640,35 +839,137 @@ impl TetrisGame {
         self.movement_animation_current_delta_grid = Vec2::new();
     }
 
+    // Moves the current piece down up to rows_to_move rows. Returns the amount of rows moved and if
+    // the piece was blocked by the stack
+    fn apply_gravity_rows(&mut self, rows_to_move: u64) -> (u64, bool) {
+        let (piece, piece_pos) = self.current_piece.as_mut().unwrap();
+
+        let mut rows_moved = 0;
+        while rows_moved < rows_to_move {
+            if !try_apply_gravity(piece, piece_pos, &self.playfield) {
+                return (rows_moved, true);
+            }
+            rows_moved += 1;
+        }
+
+        (rows_moved, false)
+    }
+
     // This is used as a deferred score update if there's a line clear animation
     fn update_score_and_line_cleared(&mut self) {
+        let level = self.level();
+
         if let Some(locked_piece) = self.last_locked_piece {
-            self.current_score += lock_piece_score(
+            let lines_to_clear = locked_piece.lock_piece_result.get_lines_to_clear_slice();
+            let is_perfect_clear =
+                !lines_to_clear.is_empty() && self.playfield.is_perfect_clear(lines_to_clear);
+
+            self.scoring_state.update(locked_piece.lock_piece_result);
+
+            self.current_score = self.current_score.saturating_add(lock_piece_score(
                 self.level(),
                 locked_piece,
+                self.scoring_state,
+                is_perfect_clear,
                 &self.rules
+            )).min(self.rules.scoring_curve.max_score());
+
+            self.total_line_clear_awards += self.rules.get_line_clear_awards(
+                locked_piece.lock_piece_result,
+                self.scoring_state.is_back_to_back_line_clear(locked_piece.lock_piece_result),
             );
 
             self.total_lines_cleared +=
                 locked_piece.lock_piece_result.get_lines_to_clear_slice().len() as u32;
+            self.tgm_level = tgm_level_up(
+                self.tgm_level,
+                locked_piece.lock_piece_result.get_lines_to_clear_slice().len() as u32,
+            );
+
+            let attack = self.rules.get_attack(
+                locked_piece.lock_piece_result,
+                self.scoring_state,
+                is_perfect_clear,
+            );
+            self.attack(attack);
         }
+
+        self.check_level_up(level);
     }
 
-    fn get_lock_piece_result(&self) -> LockedPieceResult {
-        // @TODO check T-Spins
+    // Swaps the current piece with the hold piece. Returns false if there was no hold piece, which
+    // leaves no current piece
+    fn swap_hold_piece(&mut self) -> bool {
+        let (mut piece, _) = self.current_piece.unwrap();
+        if self.rules.hold_piece_reset_rotation {
+            piece.rot = 0;
+        }
+
+        self.events.push(TetrisEvent::Held);
+
+        match self.hold_piece.replace(piece) {
+            Some(hold_piece) => {
+                self.current_piece = Some((
+                    hold_piece,
+                    self.spawn_position() + hold_piece.spawn_offset()
+                ));
+
+                self.has_used_hold = true;
+                self.lowest_row = i32::MAX;
 
-        let (total_lines_to_clear, lines_to_clear) = self.playfield.get_lines_to_clear();
+                // update movement timestamps
+                self.movement_last_timestamp_x = self.timestamp;
+                self.movement_last_timestamp_y = self.timestamp;
+                self.gravity_accumulator = 0;
+
+                // The swapped piece enters falling again
+                self.set_state(TetrisState::Falling);
 
-        match total_lines_to_clear {
-            0 => LockedPieceResult::Nothing,
-            1 => LockedPieceResult::Single([lines_to_clear[0]]),
-            2 => LockedPieceResult::Double([lines_to_clear[0], lines_to_clear[1]]),
-            3 => LockedPieceResult::Triple([lines_to_clear[0], lines_to_clear[1], lines_to_clear[2]]),
-            4 => LockedPieceResult::Tetris(lines_to_clear),
-            _ => unreachable!(),
+                true
+            }
+
+            None => {
+                self.current_piece = None;
+                false
+            }
         }
     }
 
+    fn update_score_and_line_cleared_cascade(&mut self, line_clear: &LineClear) {
+        let level = self.level();
+
+        for (chain, &lines_cleared) in line_clear.chains.iter().enumerate() {
+            self.current_score = self.current_score.saturating_add(cascade_chain_score(
+                self.level(),
+                chain as u32 + 1,
+                lines_cleared,
+                &self.rules
+            )).min(self.rules.scoring_curve.max_score());
+
+            self.total_lines_cleared += lines_cleared as u32;
+            self.tgm_level = tgm_level_up(self.tgm_level, lines_cleared as u32);
+        }
+
+        self.last_cascade_chains = line_clear.chains.len() as u32;
+
+        self.check_level_up(level);
+    }
+
+    fn check_level_up(&mut self, previous_level: u32) {
+        let level = self.level();
+        if level > previous_level {
+            self.events.push(TetrisEvent::LevelUp { level });
+        }
+    }
+
+    // Must be called after the piece is locked in the playfield, but before the playfield is
+    // updated, since the T-Spin check needs the piece blocks to be in the playfield
+    fn get_lock_piece_result(&self, piece: &Piece, piece_pos: Vec2i) -> LockedPieceResult {
+        let t_spin = get_t_spin_type(piece, piece_pos, &self.playfield, self.last_piece_action);
+        let lines_to_clear = self.playfield.get_lines_to_clear();
+        LockedPieceResult::new(t_spin, &lines_to_clear)
+    }
+
     fn lock_piece(&mut self) {
         let (piece, piece_pos) = self.current_piece.as_ref().unwrap();
         lock_piece(
@@ -678,55 +979,102 @@ impl TetrisGame {
         );
 
         // @Refactor this is repeated and any lock piece should check for this.
-        if locked_out(piece, *piece_pos, self.playfield.visible_height, &self.rules) {
-            self.has_topped_out = true;
-            println!("game over: locked out");
+        if let Some(reason) = locked_out(piece, *piece_pos, self.playfield.visible_height, &self.rules) {
+            self.top_out(reason);
             return;
         }
 
         let (piece, piece_pos) = self.current_piece.take().unwrap();
-        self.last_locked_piece = Some(LockedPiece {
+        let lock_piece_result = self.get_lock_piece_result(&piece, piece_pos);
+        let locked_piece = LockedPiece {
             piece,
             pos: piece_pos,
             soft_drop_steps: self.soft_drop_steps,
             hard_drop_steps: self.hard_drop_steps,
             last_piece_action: self.last_piece_action,
-            lock_piece_result: self.get_lock_piece_result(),
-        });
+            lock_piece_result,
+        };
+        self.last_locked_piece = Some(locked_piece);
+        self.events.push(TetrisEvent::Locked { locked_piece });
 
         self.lock_piece_timestamp = self.timestamp;
+
+        // Without lines to clear there's no line clear delay, so the score is updated right away
+        // (drops and T-Spins without lines still score)
+        if lock_piece_result.has_lines_to_clear() {
+            self.set_state(TetrisState::LineClearDelay);
+        } else {
+            self.update_score_and_line_cleared();
+            self.set_state(TetrisState::Spawning);
+
+            // Garbage only enters the playfield when the piece doesn't clear lines
+            self.insert_garbage();
+        }
     }
 
     fn try_hard_drop_piece(&mut self) -> bool {
-        if !self.rules.has_hard_drop { return false; }
+        if !self.rules.hard_drop_rule.is_enabled() { return false; }
 
         let (piece, piece_pos) = self.current_piece.as_mut().unwrap();
-        self.hard_drop_steps = full_drop_piece(piece, piece_pos, &mut self.playfield);
-        self.last_piece_action = LastPieceAction::Movement;
+        let rows = full_drop_piece(piece, piece_pos, &self.playfield);
+
+        // A firm dropped piece can be dropped again after moving, so the steps accumulate
+        self.hard_drop_steps = self.hard_drop_steps.saturating_add(rows);
+
+        // Hard dropping without moving keeps the last rotation (for T-Spins)
+        if rows > 0 {
+            self.last_piece_action = LastPieceAction::Movement;
+        }
+
+        self.events.push(TetrisEvent::HardDropped { rows });
+
+        match self.rules.hard_drop_rule {
+            HardDropRule::HardDrop => self.lock_piece(),
+
+            // The piece stays on the stack until the lock delay ends
+            HardDropRule::FirmDrop => {
+                if rows > 0 {
+                    self.movement_last_timestamp_y = self.timestamp;
+                    self.gravity_accumulator = 0;
+                    self.has_stepped = true;
+                }
+            }
+
+            HardDropRule::No => unreachable!(),
+        }
 
-        self.lock_piece();
         true
     }
 
     fn try_soft_drop_piece(&mut self) -> bool {
-        if !self.rules.has_soft_drop { return false; }
-
         let (piece, piece_pos) = self.current_piece.as_mut().unwrap();
-        if try_move_piece(piece, piece_pos, &self.playfield, 0, -1) {
-            self.movement_last_timestamp_y = self.timestamp;
-            self.movement_animation_delta_grid_y =
-                self.movement_animation_current_delta_grid.y + 1.0;
+        let rows = match self.rules.soft_drop_rule {
+            SoftDropRule::No => return false,
+
+            SoftDropRule::SoftDrop | SoftDropRule::SoftDropLock => {
+                if !try_move_piece(piece, piece_pos, &self.playfield, 0, -1) { return false; }
+                self.movement_animation_delta_grid_y =
+                    self.movement_animation_current_delta_grid.y + 1.0;
+                1
+            }
 
-            self.has_moved = true;
-            self.has_stepped = true;
-            self.last_piece_action = LastPieceAction::Movement;
+            SoftDropRule::SonicDrop => {
+                let rows = full_drop_piece(piece, piece_pos, &self.playfield);
+                if rows == 0 { return false; }
+                rows
+            }
+        };
 
-            self.soft_drop_steps += 1;
+        self.movement_last_timestamp_y = self.timestamp;
+        self.gravity_accumulator = 0;
 
-            true
-        } else {
-            false
-        }
+        self.has_moved = true;
+        self.has_stepped = true;
+        self.last_piece_action = LastPieceAction::Movement;
+
+        self.soft_drop_steps = self.soft_drop_steps.saturating_add(rows);
+
+        true
     }
 
 
@@ -734,8 +1082,21 @@ impl TetrisGame {
         self.playfield.grid_size
     }
 
+    // Pieces spawn centered, rounding to the left on odd widths
+    fn spawn_position(&self) -> Vec2i {
+        Vec2i {
+            x: (self.playfield.grid_size.x + 1) / 2 - 2,
+            y: self.rules.spawn_row as i32 - 3,
+        }
+    }
+
     pub fn level(&self) -> u32 {
-        self.rules.get_level(self.current_score, self.total_lines_cleared)
+        self.rules.get_level(
+            self.current_score,
+            self.total_lines_cleared,
+            self.total_line_clear_awards,
+            self.tgm_level,
+        )
     }
 
     pub fn score(&self) -> u32 {
@@ -745,12 +1106,261 @@ impl TetrisGame {
     pub fn total_lines_cleared(&self) -> u32 {
         self.total_lines_cleared
     }
+
+    pub fn combo(&self) -> Option<u32> {
+        self.scoring_state.combo
+    }
+
+    pub fn back_to_back(&self) -> Option<u32> {
+        self.scoring_state.back_to_back
+    }
+
+    // Amount of cascade chains caused by the last line clear
+    pub fn last_cascade_chains(&self) -> u32 {
+        self.last_cascade_chains
+    }
+}
+
+// https://tetris.wiki/ARE
+// https://tetris.fandom.com/wiki/Lock_delay
+#[derive(Copy, Clone, Debug, PartialEq, Eq, ImDraw)]
+pub enum TetrisState {
+    Spawning,       // Waiting the entry delay (ARE) to spawn the next piece
+    Falling,        // Piece is falling
+    Locking,        // Piece is on the stack, waiting the lock delay
+    LineClearDelay, // Piece was locked and the lines are being cleared
+    ToppedOut,
 }
 
-/*
-// @Maybe use a state machine?
-enum TetrisState {
-    Falling,
-    LineClearAnimation,
+impl TetrisState {
+    pub fn has_piece(self) -> bool {
+        matches!(self, TetrisState::Falling | TetrisState::Locking)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::network::InputFrame;
+    use crate::game::playfield::BlockType;
+    use crate::game::rules::{GravityCurve, RotationSystem};
+
+    #[test]
+    fn test_state_machine() {
+        let mut rules: Rules = RotationSystem::SRS.into();
+        rules.gravity_curve = GravityCurve::NoGravity;
+        rules.line_clear_delay = 100_000;
+        rules.spawn_delay = 50_000;
+
+        let mut game = TetrisGame::new(rules, 0);
+        assert_eq!(game.state(), TetrisState::Spawning);
+
+        game.update_with_input(50_000, TetrisInput::default());
+        assert_eq!(game.state(), TetrisState::Falling);
+
+        // Fill the bottom row, except where the current piece is going to be dropped
+        let (mut piece, mut piece_pos) = game.current_piece.unwrap();
+        full_drop_piece(&mut piece, &mut piece_pos, &game.playfield);
+        for x in 0..game.playfield.grid_size.x {
+            if !piece.blocks().iter().any(|b| piece_pos.x + b.x == x && piece_pos.y + b.y == 0) {
+                game.playfield.set_block(x, 0, PieceVariant::I);
+            }
+        }
+
+        let hard_drop = TetrisInput { hard_drop: true, ..TetrisInput::default() };
+        let events = game.update_with_input(0, hard_drop);
+        assert!(matches!(events[0], TetrisEvent::HardDropped { .. }));
+        assert!(matches!(events[1], TetrisEvent::Locked { .. }));
+        assert_eq!(game.state(), TetrisState::LineClearDelay);
+
+        game.update_with_input(99_999, TetrisInput::default());
+        assert_eq!(game.state(), TetrisState::LineClearDelay);
+
+        let events = game.update_with_input(1, TetrisInput::default());
+        assert!(matches!(events[0], TetrisEvent::LinesCleared { rows: 1, .. }));
+        assert_eq!(game.state(), TetrisState::Spawning);
+        assert_eq!(game.total_lines_cleared(), 1);
+
+        game.update_with_input(49_999, TetrisInput::default());
+        assert_eq!(game.state(), TetrisState::Spawning);
+
+        game.update_with_input(1, TetrisInput::default());
+        assert_eq!(game.state(), TetrisState::Falling);
+    }
+
+    #[test]
+    fn test_initial_rotation_and_hold() {
+        let mut rules: Rules = RotationSystem::SRS.into();
+        rules.gravity_curve = GravityCurve::NoGravity;
+        rules.has_initial_rotation_system = true;
+        rules.has_initial_hold_system = true;
+
+        let mut game = TetrisGame::new(rules, 0);
+        let next_piece_types = game.next_piece_types;
+
+        let input = TetrisInput { rotation_held: Some(RotationDirection::Clockwise), hold_held: true, ..TetrisInput::default() };
+        game.update_with_input(0, input);
+        assert_eq!(game.state(), TetrisState::Falling);
+
+        let (piece, _) = game.current_piece.unwrap();
+        assert_eq!(game.hold_piece.map(|piece| piece.variant), Some(next_piece_types[0]));
+        assert_eq!(piece.variant, next_piece_types[1]);
+        assert_eq!(piece.rot, 1);
+        assert!(game.has_used_hold);
+    }
+
+    #[test]
+    fn test_peek_next_pieces() {
+        let rules: Rules = RotationSystem::SRS.into();
+        let mut game = TetrisGame::new(rules, 0);
+
+        let peeked = game.peek_next_pieces(20);
+        assert_eq!(peeked.len(), 20);
+        assert_eq!(peeked, game.peek_next_pieces(20));
+
+        // Peeking doesn't consume pieces
+        for &variant in peeked.iter() {
+            assert_eq!(game.next_piece_types[0], variant);
+            game.new_piece();
+        }
+    }
+
+    #[test]
+    fn test_playfield_dimensions() {
+        for (width, height, visible_height, spawn_x) in [(4, 24, 20, 0), (10, 40, 20, 3), (12, 60, 40, 4)] {
+            let mut rules: Rules = RotationSystem::SRS.into();
+            rules.playfield_width = width;
+            rules.playfield_height = height;
+            rules.playfield_visible_height = visible_height;
+
+            let mut game = TetrisGame::new(rules, 0);
+            assert_eq!(game.playfield_grid_size(), Vec2i { x: width as i32, y: height as i32 });
+            assert_eq!(game.playfield().visible_height, visible_height);
+
+            game.new_piece();
+            let (_, pos) = game.current_piece.unwrap();
+            assert_eq!(pos.x, spawn_x);
+        }
+    }
+
+    #[test]
+    fn test_garbage() {
+        let mut rules: Rules = RotationSystem::SRS.into();
+        rules.gravity_curve = GravityCurve::NoGravity;
+        rules.spawn_delay = 0;
+        rules.garbage_delay = 100_000;
+        rules.top_out_rule |= TopOutRule::GARBAGE_OUT;
+
+        let mut game = TetrisGame::new(rules, 0);
+        game.update_with_input(0, TetrisInput::default());
+        game.add_garbage(2);
+
+        // The garbage waits for the delay before entering the playfield
+        let hard_drop = TetrisInput { hard_drop: true, ..TetrisInput::default() };
+        let events = game.update_with_input(0, hard_drop);
+        assert!(!events.iter().any(|event| matches!(event, TetrisEvent::GarbageInserted { .. })));
+        assert_eq!(game.pending_garbage_rows(), 2);
+
+        let events = game.update_with_input(100_000, hard_drop);
+        assert!(events.iter().any(|event| matches!(event, TetrisEvent::GarbageInserted { rows: 2 })));
+        assert_eq!(game.pending_garbage_rows(), 0);
+        assert!(matches!(game.playfield.block(0, 0), Some(BlockType::Garbage) | None));
+        assert_eq!((0..game.playfield.grid_size.x).filter(|&x| game.playfield.block(x, 1).is_none()).count(), 1);
+
+        // Networked games receive the garbage with the input frame
+        let frame = InputFrame { garbage: 3, ..InputFrame::default() };
+        let events = game.update_with_input_frame(frame);
+        assert!(events.iter().any(|event| matches!(event, TetrisEvent::GarbageReceived { rows: 3 })));
+
+        // Pushing blocks above the top of the playfield tops out
+        let top_row = game.playfield.grid_size.y - 1;
+        game.playfield.set_block(0, top_row, PieceVariant::I);
+        game.add_garbage(1);
+        game.update_with_input(100_000, hard_drop);
+        assert_eq!(game.top_out_reason(), Some(TopOutReason::GarbageOut));
+    }
+
+    #[test]
+    fn test_drop_rules() {
+        let mut rules: Rules = RotationSystem::SRS.into();
+        rules.gravity_curve = GravityCurve::NoGravity;
+        rules.hard_drop_rule = HardDropRule::FirmDrop;
+        rules.soft_drop_rule = SoftDropRule::SoftDropLock;
+
+        let mut game = TetrisGame::new(rules, 0);
+        game.update_with_input(0, TetrisInput::default());
+
+        // Firm drop moves the piece to the bottom without locking it
+        let hard_drop = TetrisInput { hard_drop: true, ..TetrisInput::default() };
+        let events = game.update_with_input(0, hard_drop);
+        assert_eq!(events.len(), 1);
+        assert!(matches!(events[0], TetrisEvent::HardDropped { .. }));
+        assert!(game.state().has_piece());
+
+        // Soft dropping the piece on the stack locks it
+        let soft_drop = TetrisInput { soft_drop: true, ..TetrisInput::default() };
+        let events = game.update_with_input(0, soft_drop);
+        assert!(events.iter().any(|event| matches!(event, TetrisEvent::Locked { .. })));
+        assert!(events.iter().any(|event| matches!(event, TetrisEvent::PieceSpawned { .. })));
+    }
+
+    #[test]
+    fn test_extended_placement() {
+        let mut rules: Rules = RotationSystem::SRS.into();
+        rules.gravity_curve = GravityCurve::NoGravity;
+        rules.soft_drop_rule = SoftDropRule::SonicDrop;
+        rules.lock_delay = LockDelayRule::ExtendedPlacement { duration: 100_000, moves: 1 };
+
+        let mut game = TetrisGame::new(rules, 0);
+        game.update_with_input(0, TetrisInput::default());
+
+        // Reaching the stack is a new lowest row, so it doesn't use moves
+        let soft_drop = TetrisInput { soft_drop: true, ..TetrisInput::default() };
+        game.update_with_input(0, soft_drop);
+        let left = TetrisInput { horizontal_movement: -1, ..TetrisInput::default() };
+        let events = game.update_with_input(0, left);
+        assert!(matches!(events[0], TetrisEvent::LockStarted));
+
+        // The movement resets the duration, but it was the last move
+        game.update_with_input(50_000, TetrisInput::default());
+        assert_eq!(game.state(), TetrisState::Locking);
+
+        let events = game.update_with_input(0, TetrisInput::default());
+        assert!(matches!(events[0], TetrisEvent::Locked { .. }));
+    }
+
+    #[test]
+    fn test_lockstep_checksum() {
+        let rules: Rules = RotationSystem::SRS.into();
+        let mut game = TetrisGame::new(rules.clone(), 42);
+        let mut replay = TetrisGame::new(rules, 42);
+        assert_eq!(game.checksum(), replay.checksum());
+
+        // The same seed and inputs always simulate the same game
+        let inputs = [
+            TetrisInput::default(),
+            TetrisInput { horizontal_movement: -1, ..TetrisInput::default() },
+            TetrisInput { rotation: Some(RotationDirection::Clockwise), ..TetrisInput::default() },
+            TetrisInput { hard_drop: true, ..TetrisInput::default() },
+            TetrisInput { hold: true, ..TetrisInput::default() },
+        ];
+
+        for i in 0..100 {
+            let input = inputs[i % inputs.len()];
+            game.update_with_input(16_666, input);
+            replay.update_with_input(16_666, input);
+            assert_eq!(game.checksum(), replay.checksum());
+        }
+
+        // A different input changes the state
+        let rules: Rules = RotationSystem::SRS.into();
+        let mut game = TetrisGame::new(rules.clone(), 42);
+        let mut replay = TetrisGame::new(rules, 42);
+        let hard_drop = TetrisInput { hard_drop: true, ..TetrisInput::default() };
+        game.update_with_input(16_666, TetrisInput::default());
+        replay.update_with_input(16_666, TetrisInput::default());
+        game.update_with_input(16_666, TetrisInput::default());
+        replay.update_with_input(16_666, hard_drop);
+        assert_ne!(game.checksum(), replay.checksum());
+    }
 }
-*/
@@ -1,11 +1,12 @@
 // Network
 use super::*;
 use crate::game::network;
+use crate::game::playfield::BlockType;
 
 impl TetrisGame {
     pub fn from_network(
         net_tetris_game: network::NetworkedTetrisGame,
-        rules: Rules,
+        mut rules: Rules,
         randomizer: Randomizer,
         net_timestamp: u64,
         app: &mut App,
@@ -14,6 +15,15 @@ impl TetrisGame {
         // lock delay
         let remaining_lock_delay = rules.lock_delay;
 
+        // The pieces were validated with the rotation system of the game
+        rules.rotation_system = net_tetris_game.rotation_system;
+
+        // The playfield dimensions come with the playfield
+        let playfield = &net_tetris_game.playfield;
+        rules.playfield_width = playfield.grid_size.x as u8;
+        rules.playfield_height = playfield.grid_size.y as u8;
+        rules.playfield_visible_height = playfield.visible_height;
+
         // @TODO do we need this?
         // Fix timestamps
         app.set_game_timestamp(net_timestamp);
@@ -21,14 +31,23 @@ impl TetrisGame {
         Self {
             timestamp: net_tetris_game.timestamp,
 
-            has_topped_out: net_tetris_game.has_topped_out,
+            state: net_tetris_game.state,
+            state_timestamp: net_tetris_game.state_timestamp,
+            top_out_reason: net_tetris_game.top_out_reason,
+
+            events: Vec::new(),
 
             playfield: net_tetris_game.playfield,
             rules,
             randomizer,
+            garbage_queue: GarbageQueue::new(0), // garbage only matters to the player
 
             current_score: net_tetris_game.current_score,
             total_lines_cleared: net_tetris_game.total_lines_cleared,
+            total_line_clear_awards: net_tetris_game.total_line_clear_awards,
+            tgm_level: net_tetris_game.tgm_level,
+            scoring_state: net_tetris_game.scoring_state,
+            last_cascade_chains: 0,
 
             current_piece: net_tetris_game.current_piece,
             next_piece_types: net_tetris_game.next_piece_types,
@@ -41,8 +60,8 @@ impl TetrisGame {
             hold_piece: net_tetris_game.hold_piece,
             has_used_hold: false,
 
-            is_locking: false,
             remaining_lock_delay,
+            lowest_row: i32::MAX,
 
             has_moved: false,
             has_rotated: false,
@@ -52,11 +71,11 @@ impl TetrisGame {
             movement_last_timestamp_x: net_tetris_game.movement_last_timestamp_x,
             movement_last_timestamp_y: net_tetris_game.movement_last_timestamp_y,
 
+            gravity_accumulator: 0,
+
             movement_animation_delta_grid_x: 0.0,
             movement_animation_delta_grid_y: 0.0,
             movement_animation_current_delta_grid: Vec2::new(),
-
-            locking_animation_timestamp: 0,
         }
     }
 
@@ -64,11 +83,17 @@ impl TetrisGame {
         network::NetworkedTetrisGame {
             timestamp: self.timestamp,
 
-            has_topped_out: self.has_topped_out,
+            state: self.state,
+            state_timestamp: self.state_timestamp,
+            top_out_reason: self.top_out_reason,
+            rotation_system: self.rules.rotation_system,
             playfield: self.playfield.clone(),
 
             current_score: self.current_score,
             total_lines_cleared: self.total_lines_cleared,
+            total_line_clear_awards: self.total_line_clear_awards,
+            tgm_level: self.tgm_level,
+            scoring_state: self.scoring_state,
 
             current_piece: self.current_piece,
             next_piece_types: self.next_piece_types.clone(),
@@ -93,12 +118,18 @@ impl TetrisGame {
         // Fix timestamps
         app.set_game_timestamp(net_timestamp);
 
-        self.has_topped_out = net_tetris_game.has_topped_out;
+        self.state = net_tetris_game.state;
+        self.state_timestamp = net_tetris_game.state_timestamp;
+        self.top_out_reason = net_tetris_game.top_out_reason;
+        self.rules.rotation_system = net_tetris_game.rotation_system;
         //self.playfield.update_from_network(net_tetris_game.playfield);
         self.playfield = net_tetris_game.playfield;
 
         self.current_score = net_tetris_game.current_score;
         self.total_lines_cleared = net_tetris_game.total_lines_cleared;
+        self.total_line_clear_awards = net_tetris_game.total_line_clear_awards;
+        self.tgm_level = net_tetris_game.tgm_level;
+        self.scoring_state = net_tetris_game.scoring_state;
 
         self.current_piece = net_tetris_game.current_piece;
         self.next_piece_types = net_tetris_game.next_piece_types;
@@ -111,4 +142,55 @@ impl TetrisGame {
         self.movement_last_timestamp_x = net_tetris_game.movement_last_timestamp_x;
         self.movement_last_timestamp_y = net_tetris_game.movement_last_timestamp_y;
     }
+
+    // Simulates a tick of a networked game (lockstep, rollback or replay)
+    pub fn update_with_input_frame(&mut self, frame: network::InputFrame) -> &[TetrisEvent] {
+        // The garbage is queued before the update, which clears the events, so the GarbageReceived
+        // event is added again after it
+        self.add_garbage(frame.garbage);
+        self.update_with_input(frame.dt as u64, frame.input);
+
+        if frame.garbage > 0 {
+            self.events.insert(0, TetrisEvent::GarbageReceived { rows: frame.garbage });
+        }
+        &self.events
+    }
+
+    // Hash of the simulated state, to detect desyncs between the lockstep peers
+    pub fn checksum(&self) -> u32 {
+        let mut hash = network::Fnv1a::new();
+
+        hash.write_u64(self.timestamp);
+        hash.write_u8(self.state as u8);
+        hash.write_u32(self.current_score);
+        hash.write_u32(self.total_lines_cleared);
+        hash.write_u32(self.tgm_level);
+        hash.write_u32(self.garbage_queue.pending_rows());
+
+        for block in self.playfield.blocks.iter() {
+            let id = match block {
+                BlockType::Empty => 0xff,
+                BlockType::Garbage => 0xfe,
+                BlockType::Piece(variant) => network::piece_variant_id(*variant),
+            };
+            hash.write_u8(id);
+        }
+
+        if let Some((piece, pos)) = self.current_piece {
+            hash.write_u8(network::piece_variant_id(piece.variant));
+            hash.write_u32(piece.rot as u32);
+            hash.write_u32(pos.x as u32);
+            hash.write_u32(pos.y as u32);
+        }
+
+        for variant in self.next_piece_types.iter() {
+            hash.write_u8(network::piece_variant_id(*variant));
+        }
+
+        if let Some(piece) = self.hold_piece {
+            hash.write_u8(network::piece_variant_id(piece.variant));
+        }
+
+        hash.finish()
+    }
 }